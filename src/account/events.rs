@@ -0,0 +1,56 @@
+use crate::shared::{Exchange, Price, Quantity, Side, TimestampMS, TimestampNS};
+use std::sync::Arc;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum OrderStatus {
+    New,
+    PartiallyFilled,
+    Filled,
+    Canceled,
+    Rejected,
+    Expired,
+}
+
+/// State of one of our own orders after an exchange-side change
+#[derive(Debug, Clone)]
+pub struct OrderUpdate {
+    pub exchange: Exchange,
+    pub ticker: Arc<String>,
+    pub order_id: String,
+    pub client_order_id: Option<String>,
+    pub side: Side,
+    pub status: OrderStatus,
+    pub price: Price,
+    pub quantity: Quantity,
+    pub filled_quantity: Quantity,
+    pub timestamp: TimestampMS,
+    pub received: TimestampNS,
+}
+
+/// Execution of one of our own orders
+#[derive(Debug, Clone)]
+pub struct Fill {
+    pub exchange: Exchange,
+    pub ticker: Arc<String>,
+    pub order_id: String,
+    pub trade_id: String,
+    pub side: Side,
+    pub price: Price,
+    pub quantity: Quantity,
+    pub fee: f64,
+    pub fee_asset: String,
+    pub is_maker: bool,
+    pub timestamp: TimestampMS,
+    pub received: TimestampNS,
+}
+
+/// Balances are kept in asset units: there is no per-asset multiplier
+#[derive(Debug, Clone)]
+pub struct BalanceUpdate {
+    pub exchange: Exchange,
+    pub asset: String,
+    pub free: f64,
+    pub locked: f64,
+    pub timestamp: TimestampMS,
+    pub received: TimestampNS,
+}
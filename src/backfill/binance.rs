@@ -0,0 +1,209 @@
+use crate::backfill::TradeSource;
+use crate::connector::errors::Error;
+use crate::connector::services::http::HttpClient;
+use crate::connector::errors::ExchangeError::BinanceError;
+use crate::connector::{convert_ticker_into_binance_symbol, SymbolCase};
+use crate::order_entry::{Instrument, InstrumentMap};
+use crate::shared::utils::now_timestamp_ns;
+use crate::shared::{Exchange, Side, TimestampMS};
+use crate::trade::TradeEvent;
+use serde::Deserialize;
+use std::sync::Arc;
+
+const PAGE_LIMIT: usize = 1000;
+// aggTrades accepts at most one hour between startTime and endTime
+const WINDOW_MS: TimestampMS = 60 * 60 * 1000;
+
+#[derive(Debug, Deserialize)]
+struct AggTrade {
+    #[serde(rename = "a")]
+    aggregate_id: u64,
+    #[serde(rename = "p")]
+    price: String,
+    #[serde(rename = "q")]
+    quantity: String,
+    #[serde(rename = "T")]
+    trade_time: u64,
+    #[serde(rename = "m")]
+    is_buyer_maker: bool,
+}
+
+/// Binance `aggTrades`: ids are the same aggregate ids the `@aggTrade` stream reports
+pub struct BinanceTradeSource {
+    exchange: Exchange,
+    instruments: InstrumentMap,
+    rest_url: String,
+    http: Arc<HttpClient>,
+}
+
+impl BinanceTradeSource {
+    pub fn new(tickers: &[(&str, u32, u32)]) -> Self {
+        Self {
+            exchange: Exchange::Binance,
+            instruments: InstrumentMap::new(tickers, |t| convert_ticker_into_binance_symbol(t, SymbolCase::Upper)),
+            rest_url: "https://api.binance.com".to_string(),
+            http: HttpClient::global(),
+        }
+    }
+
+    #[cfg(test)]
+    pub fn with_url(mut self, rest_url: &str) -> Self {
+        self.rest_url = rest_url.to_string();
+        self.http = Arc::new(HttpClient::new());
+        self
+    }
+
+    async fn agg_trades(&self, instrument: &Instrument, params: &[(&str, String)]) -> Result<Vec<AggTrade>, Error> {
+        let url = format!("{}/api/v3/aggTrades", self.rest_url);
+        let request = self
+            .http
+            .get(&url)
+            .query(&[("symbol", instrument.symbol.clone()), ("limit", PAGE_LIMIT.to_string())])
+            .query(params);
+        let resp = self.http.send(&self.exchange, 2, request).await?;
+        if !resp.status().is_success() {
+            return Err(BinanceError(resp.text().await?).into());
+        }
+        Ok(resp.json().await?)
+    }
+
+    fn trade_event(&self, instrument: &Instrument, trade: &AggTrade) -> Result<TradeEvent, Error> {
+        Ok(TradeEvent {
+            exchange: self.exchange.clone(),
+            ticker: Arc::clone(&instrument.ticker),
+            trade_id: trade.aggregate_id,
+            price: instrument.parse_price(&trade.price)?,
+            quantity: instrument.parse_quantity(&trade.quantity)?,
+            timestamp: trade.trade_time,
+            market_maker: [Side::Sell, Side::Buy][trade.is_buyer_maker as usize],
+            received: now_timestamp_ns(),
+        })
+    }
+
+    /// Trades with `from_id <= trade_id <= to_id`
+    pub async fn trades_by_id(&self, ticker: &str, from_id: u64, to_id: u64) -> Result<Vec<TradeEvent>, Error> {
+        let instrument = self.instruments.get(ticker)?;
+        let mut result = Vec::new();
+        let mut next_id = from_id;
+
+        while next_id <= to_id {
+            let page = self.agg_trades(instrument, &[("fromId", next_id.to_string())]).await?;
+            for trade in page.iter().take_while(|t| t.aggregate_id <= to_id) {
+                result.push(self.trade_event(instrument, trade)?);
+            }
+            match page.last() {
+                Some(last) if page.len() == PAGE_LIMIT => next_id = last.aggregate_id + 1,
+                _ => break,
+            }
+        }
+        Ok(result)
+    }
+}
+
+impl TradeSource for BinanceTradeSource {
+    fn exchange(&self) -> &Exchange {
+        &self.exchange
+    }
+
+    async fn trades_between(&self, ticker: &str, from: TimestampMS, to: TimestampMS) -> Result<Vec<TradeEvent>, Error> {
+        let instrument = self.instruments.get(ticker)?;
+        let mut result = Vec::new();
+        let mut window_start = from;
+        let mut next_id: Option<u64> = None;
+
+        // The first trade is found by time, the following pages continue by id
+        loop {
+            let params = match next_id {
+                Some(id) => vec![("fromId", id.to_string())],
+                None => vec![
+                    ("startTime", window_start.to_string()),
+                    ("endTime", (window_start + WINDOW_MS - 1).min(to).to_string()),
+                ],
+            };
+            let page = self.agg_trades(instrument, &params).await?;
+
+            let Some(last) = page.last() else {
+                if next_id.is_some() {
+                    break;
+                }
+                window_start += WINDOW_MS;
+                if window_start > to {
+                    break;
+                }
+                continue;
+            };
+
+            for trade in &page {
+                if trade.trade_time > to {
+                    return Ok(result);
+                }
+                result.push(self.trade_event(instrument, trade)?);
+            }
+            if next_id.is_some() && page.len() < PAGE_LIMIT {
+                break;
+            }
+            next_id = Some(last.aggregate_id + 1);
+        }
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::connector::services::mock_server::mock_http_server;
+
+    fn source(url: &str) -> BinanceTradeSource {
+        BinanceTradeSource::new(&[("btc/usdt", 100, 1000)]).with_url(url)
+    }
+
+    fn agg_trade(id: u64, time: u64) -> String {
+        format!(r#"{{"a":{id},"p":"100.50","q":"0.250","f":{id},"l":{id},"T":{time},"m":true,"M":true}}"#)
+    }
+
+    #[tokio::test]
+    async fn test_trades_between() {
+        let (url, server) = mock_http_server(vec![
+            (200, "[]".to_string()),
+            (200, format!("[{},{}]", agg_trade(10, 3_700_000), agg_trade(11, 3_700_500))),
+            (200, format!("[{},{}]", agg_trade(12, 3_800_000), agg_trade(13, 9_000_000))),
+        ])
+        .await;
+
+        let trades = source(&url).trades_between("btc/usdt", 0, 5_000_000).await.unwrap();
+        let requests = server.await.unwrap();
+
+        assert!(requests[0].contains("symbol=BTCUSDT"));
+        assert!(requests[0].contains("startTime=0&endTime=3599999"));
+        assert!(requests[1].contains("startTime=3600000&endTime=5000000"));
+        assert!(requests[2].contains("fromId=12"));
+
+        let ids: Vec<u64> = trades.iter().map(|t| t.trade_id).collect();
+        assert_eq!(ids, vec![10, 11, 12]);
+        assert_eq!(trades[0].price, 10050);
+        assert_eq!(trades[0].quantity, 250);
+        assert_eq!(trades[0].timestamp, 3_700_000);
+        assert_eq!(trades[0].market_maker, Side::Buy);
+    }
+
+    #[tokio::test]
+    async fn test_trades_by_id() {
+        let (url, server) = mock_http_server(vec![(
+            200,
+            format!("[{},{},{}]", agg_trade(5, 1), agg_trade(6, 2), agg_trade(7, 3)),
+        )])
+        .await;
+
+        let trades = source(&url).trades_by_id("btc/usdt", 5, 6).await.unwrap();
+        let requests = server.await.unwrap();
+        assert!(requests[0].contains("fromId=5"));
+        assert_eq!(trades.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_error_response() {
+        let (url, _server) = mock_http_server(vec![(400, r#"{"code":-1121,"msg":"Invalid symbol."}"#.to_string())]).await;
+        let res = source(&url).trades_between("btc/usdt", 0, 1).await;
+        assert!(res.is_err());
+    }
+}
@@ -0,0 +1,11 @@
+use crate::connector::errors::Error;
+use crate::trade::TradeError;
+
+#[derive(Debug, thiserror::Error)]
+pub enum BackfillError {
+    #[error("SourceError: {0}")]
+    SourceError(#[from] Error),
+
+    #[error("TradeError: {0}")]
+    TradeError(#[from] TradeError),
+}
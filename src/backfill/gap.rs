@@ -0,0 +1,101 @@
+use crate::shared::{Exchange, TimestampMS};
+use crate::trade::TradeEvent;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Trades missed by the live stream, e.g. while it was reconnecting
+#[derive(Debug, Clone, PartialEq)]
+pub struct TradeGap {
+    pub exchange: Exchange,
+    pub ticker: Arc<String>,
+    pub from_id: u64,
+    pub to_id: u64,
+    /// Timestamps of the trades around the gap
+    pub from: TimestampMS,
+    pub to: TimestampMS,
+}
+
+/// Watches the ids of live trades. Only exchanges with consecutive per-instrument ids are tracked
+pub struct TradeGapTracker {
+    last: HashMap<(&'static str, Arc<String>), (u64, TimestampMS)>,
+}
+
+fn has_sequential_ids(exchange: &Exchange) -> bool {
+    matches!(exchange, Exchange::Binance | Exchange::Kraken | Exchange::Deribit)
+}
+
+impl TradeGapTracker {
+    pub fn new() -> Self {
+        Self { last: HashMap::new() }
+    }
+
+    pub fn observe(&mut self, trade: &TradeEvent) -> Option<TradeGap> {
+        if !has_sequential_ids(&trade.exchange) {
+            return None;
+        }
+        let key = (trade.exchange.to_str(), Arc::clone(&trade.ticker));
+        let prev = self.last.get(&key).copied();
+        if let Some((last_id, _)) = prev {
+            if trade.trade_id <= last_id {
+                return None;
+            }
+        }
+        self.last.insert(key, (trade.trade_id, trade.timestamp));
+
+        match prev {
+            Some((last_id, last_ts)) if trade.trade_id > last_id + 1 => Some(TradeGap {
+                exchange: trade.exchange.clone(),
+                ticker: Arc::clone(&trade.ticker),
+                from_id: last_id + 1,
+                to_id: trade.trade_id - 1,
+                from: last_ts,
+                to: trade.timestamp,
+            }),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shared::Side;
+
+    fn trade(exchange: Exchange, id: u64, ts: TimestampMS) -> TradeEvent {
+        TradeEvent {
+            exchange,
+            ticker: Arc::new("btc/usdt".to_string()),
+            trade_id: id,
+            price: 100,
+            quantity: 1,
+            timestamp: ts,
+            received: 0,
+            market_maker: Side::Buy,
+        }
+    }
+
+    #[test]
+    fn test_gap_detected() {
+        let mut tracker = TradeGapTracker::new();
+        assert_eq!(tracker.observe(&trade(Exchange::Binance, 10, 1_000)), None);
+        assert_eq!(tracker.observe(&trade(Exchange::Binance, 11, 1_001)), None);
+
+        let gap = tracker.observe(&trade(Exchange::Binance, 15, 5_000)).unwrap();
+        assert_eq!((gap.from_id, gap.to_id), (12, 14));
+        assert_eq!((gap.from, gap.to), (1_001, 5_000));
+    }
+
+    #[test]
+    fn test_duplicates_and_other_exchanges() {
+        let mut tracker = TradeGapTracker::new();
+        tracker.observe(&trade(Exchange::Binance, 10, 1_000));
+        // Replayed trade after a reconnect
+        assert_eq!(tracker.observe(&trade(Exchange::Binance, 9, 999)), None);
+        assert_eq!(tracker.observe(&trade(Exchange::Binance, 11, 1_001)), None);
+
+        // Instruments are tracked separately, Hyperliquid ids are not consecutive
+        assert_eq!(tracker.observe(&trade(Exchange::Kraken, 500, 1_000)), None);
+        tracker.observe(&trade(Exchange::Hyperliquid, 1, 1_000));
+        assert_eq!(tracker.observe(&trade(Exchange::Hyperliquid, 100, 1_001)), None);
+    }
+}
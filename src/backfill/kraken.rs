@@ -0,0 +1,164 @@
+use crate::backfill::TradeSource;
+use crate::connector::errors::Error;
+use crate::connector::services::http::HttpClient;
+use crate::connector::errors::ExchangeError::KrakenError;
+use crate::connector::errors::ParsingError::{ConvertingError, MessageParsingError};
+use crate::order_entry::{convert_ticker_into_kraken_pair, Instrument, InstrumentMap};
+use crate::shared::utils::now_timestamp_ns;
+use crate::shared::{Exchange, Side, TimestampMS};
+use crate::trade::TradeEvent;
+use serde_json::Value;
+use std::sync::Arc;
+
+/// Kraken `Trades`. Pages are chained with the `last` cursor of the previous response
+pub struct KrakenTradeSource {
+    exchange: Exchange,
+    instruments: InstrumentMap,
+    rest_url: String,
+    http: Arc<HttpClient>,
+}
+
+impl KrakenTradeSource {
+    pub fn new(tickers: &[(&str, u32, u32)]) -> Self {
+        Self {
+            exchange: Exchange::Kraken,
+            instruments: InstrumentMap::new(tickers, convert_ticker_into_kraken_pair),
+            rest_url: "https://api.kraken.com".to_string(),
+            http: HttpClient::global(),
+        }
+    }
+
+    #[cfg(test)]
+    pub fn with_url(mut self, rest_url: &str) -> Self {
+        self.rest_url = rest_url.to_string();
+        self.http = Arc::new(HttpClient::new());
+        self
+    }
+
+    // [price, volume, time, side, order type, misc, trade id]
+    fn trade_event(&self, instrument: &Instrument, row: &Value) -> Result<TradeEvent, Error> {
+        let field = |i: usize| row.get(i).ok_or_else(|| MessageParsingError(format!("Trades: short row {}", row)));
+        let text = |i: usize| field(i)?.as_str().ok_or_else(|| MessageParsingError(format!("Trades: bad row {}", row)));
+
+        let time = field(2)?
+            .as_f64()
+            .ok_or_else(|| ConvertingError(format!("Trades: bad time in {}", row)))?;
+        let market_maker = match text(3)? {
+            "b" => Side::Buy,
+            "s" => Side::Sell,
+            other => Err(ConvertingError(format!("Unexpected side {}", other)))?,
+        };
+        let trade_id = field(6)?
+            .as_u64()
+            .ok_or_else(|| ConvertingError(format!("Trades: bad trade id in {}", row)))?;
+
+        Ok(TradeEvent {
+            exchange: self.exchange.clone(),
+            ticker: Arc::clone(&instrument.ticker),
+            trade_id,
+            price: instrument.parse_price(text(0)?)?,
+            quantity: instrument.parse_quantity(text(1)?)?,
+            timestamp: (time * 1000.0).round() as TimestampMS,
+            market_maker,
+            received: now_timestamp_ns(),
+        })
+    }
+}
+
+impl TradeSource for KrakenTradeSource {
+    fn exchange(&self) -> &Exchange {
+        &self.exchange
+    }
+
+    async fn trades_between(&self, ticker: &str, from: TimestampMS, to: TimestampMS) -> Result<Vec<TradeEvent>, Error> {
+        let instrument = self.instruments.get(ticker)?;
+        let mut result = Vec::new();
+        // `since` takes seconds for the first page, later pages pass the nanosecond cursor back
+        let mut since = (from / 1000).to_string();
+
+        loop {
+            let url = format!(
+                "{}/0/public/Trades?pair={}&since={}&count=1000",
+                self.rest_url, instrument.symbol, since
+            );
+            let resp: Value = self.http.send(&self.exchange, 1, self.http.get(&url)).await?.json().await?;
+            if let Some(err) = resp["error"].as_array().and_then(|e| e.first()) {
+                Err(KrakenError(format!("Trades: {}", err)))?;
+            }
+
+            let data = resp["result"]
+                .as_object()
+                .ok_or_else(|| MessageParsingError("Trades: missing result".into()))?;
+            let rows = data
+                .iter()
+                .find(|(key, _)| key.as_str() != "last")
+                .and_then(|(_, rows)| rows.as_array())
+                .ok_or_else(|| MessageParsingError("Trades: missing pair".into()))?;
+            let last = match &data["last"] {
+                Value::String(s) => s.clone(),
+                other => other.to_string(),
+            };
+
+            for row in rows {
+                let trade = self.trade_event(instrument, row)?;
+                if trade.timestamp > to {
+                    return Ok(result);
+                }
+                if trade.timestamp >= from {
+                    result.push(trade);
+                }
+            }
+
+            if rows.is_empty() || last == since {
+                break;
+            }
+            since = last;
+        }
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::connector::services::mock_server::mock_http_server;
+
+    fn source(url: &str) -> KrakenTradeSource {
+        KrakenTradeSource::new(&[("btc/usd", 10, 1000)]).with_url(url)
+    }
+
+    #[tokio::test]
+    async fn test_trades_between() {
+        let first = r#"{"error":[],"result":{"XXBTZUSD":[
+            ["60000.5","0.100",999.9,"b","l","",1],
+            ["60001.0","0.250",1000.5,"s","m","",2]
+        ],"last":"1000500000000"}}"#;
+        let second = r#"{"error":[],"result":{"XXBTZUSD":[
+            ["60002.0","1.000",1001.0,"b","l","",3],
+            ["60003.0","1.000",1003.0,"b","l","",4]
+        ],"last":"1003000000000"}}"#;
+        let (url, server) = mock_http_server(vec![(200, first.to_string()), (200, second.to_string())]).await;
+
+        let trades = source(&url).trades_between("btc/usd", 1_000_000, 1_002_000).await.unwrap();
+        let requests = server.await.unwrap();
+
+        assert!(requests[0].contains("pair=XBTUSD&since=1000&"));
+        assert!(requests[1].contains("since=1000500000000"));
+        let ids: Vec<u64> = trades.iter().map(|t| t.trade_id).collect();
+        assert_eq!(ids, vec![2, 3]);
+        assert_eq!(trades[0].price, 600010);
+        assert_eq!(trades[0].quantity, 250);
+        assert_eq!(trades[0].timestamp, 1_000_500);
+        assert_eq!(trades[0].market_maker, Side::Sell);
+    }
+
+    #[tokio::test]
+    async fn test_error_response() {
+        let body = r#"{"error":["EQuery:Unknown asset pair"]}"#;
+        let (url, _server) = mock_http_server(vec![(200, body.to_string())]).await;
+        match source(&url).trades_between("btc/usd", 0, 1).await {
+            Err(Error::ExchangeError(KrakenError(msg))) => assert!(msg.contains("Unknown asset pair")),
+            other => panic!("unexpected result {:?}", other.map(|t| t.len())),
+        }
+    }
+}
@@ -0,0 +1,13 @@
+mod binance;
+mod errors;
+mod gap;
+mod kraken;
+mod service;
+mod source;
+
+pub use binance::BinanceTradeSource;
+pub use errors::BackfillError;
+pub use gap::{TradeGap, TradeGapTracker};
+pub use kraken::KrakenTradeSource;
+pub use service::Backfill;
+pub use source::TradeSource;
@@ -0,0 +1,117 @@
+use crate::backfill::{BackfillError, TradeGap, TradeSource};
+use crate::shared::logger::Logger;
+use crate::shared::TimestampMS;
+use crate::trade::{TradeEvent, TradeEventRepo};
+use chrono::NaiveDate;
+use clickhouse::Client;
+use std::collections::HashSet;
+use tracing::Level;
+
+const HOUR_MS: TimestampMS = 60 * 60 * 1000;
+// Live Binance trades carry the event time, which lags the trade time of the REST history
+const DEDUP_SLACK_MS: TimestampMS = 1000;
+
+/// Trades that are not stored yet
+fn missing(trades: Vec<TradeEvent>, known: &HashSet<u64>) -> Vec<TradeEvent> {
+    trades.into_iter().filter(|t| !known.contains(&t.trade_id)).collect()
+}
+
+/// Loads trades from the REST history of an exchange into `trade_events`, skipping the ones already stored
+pub struct Backfill<'a, S: TradeSource> {
+    source: S,
+    repo: TradeEventRepo<'a>,
+    logger: Logger,
+}
+
+impl<'a, S: TradeSource> Backfill<'a, S> {
+    pub fn new(source: S, client: &'a Client) -> Self {
+        let logger = Logger::new(source.exchange().to_str(), Level::INFO);
+        Self {
+            source,
+            repo: TradeEventRepo::new(client),
+            logger,
+        }
+    }
+
+    /// Returns the number of inserted trades
+    pub async fn fill(&self, ticker: &str, from: TimestampMS, to: TimestampMS) -> Result<usize, BackfillError> {
+        let trades = self.source.trades_between(ticker, from, to).await?;
+        self.store(ticker, from, to, trades).await
+    }
+
+    /// Only the trades inside the gap: the ones around it may still wait in the live buffer
+    pub async fn fill_gap(&self, gap: &TradeGap) -> Result<usize, BackfillError> {
+        let mut trades = self.source.trades_between(&gap.ticker, gap.from, gap.to).await?;
+        trades.retain(|t| (gap.from_id..=gap.to_id).contains(&t.trade_id));
+        self.store(&gap.ticker, gap.from, gap.to, trades).await
+    }
+
+    async fn store(
+        &self,
+        ticker: &str,
+        from: TimestampMS,
+        to: TimestampMS,
+        trades: Vec<TradeEvent>,
+    ) -> Result<usize, BackfillError> {
+        let known = self
+            .repo
+            .trade_ids(
+                self.source.exchange(),
+                ticker,
+                from.saturating_sub(DEDUP_SLACK_MS),
+                to + DEDUP_SLACK_MS,
+            )
+            .await?;
+        let fetched = trades.len();
+        let new = missing(trades, &known);
+        self.repo.save(&new).await?;
+
+        self.logger.info(&format!(
+            "{} {}..{}: fetched {}, inserted {}",
+            ticker, from, to, fetched, new.len()
+        ));
+        Ok(new.len())
+    }
+
+    /// Whole UTC days starting from `day`, one hour at a time
+    pub async fn fill_days(&self, ticker: &str, day: NaiveDate, days: u32) -> Result<usize, BackfillError> {
+        let start = day.and_hms_opt(0, 0, 0).unwrap().and_utc().timestamp_millis() as TimestampMS;
+        let end = start + days as TimestampMS * 24 * HOUR_MS;
+
+        let mut inserted = 0;
+        let mut from = start;
+        while from < end {
+            inserted += self.fill(ticker, from, from + HOUR_MS - 1).await?;
+            from += HOUR_MS;
+        }
+        Ok(inserted)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shared::{Exchange, Side};
+    use std::sync::Arc;
+
+    fn trade(id: u64) -> TradeEvent {
+        TradeEvent {
+            exchange: Exchange::Binance,
+            ticker: Arc::new("btc/usdt".to_string()),
+            trade_id: id,
+            price: 100,
+            quantity: 1,
+            timestamp: id,
+            received: 0,
+            market_maker: Side::Sell,
+        }
+    }
+
+    #[test]
+    fn test_missing() {
+        let known: HashSet<u64> = [2, 3].into_iter().collect();
+        let new = missing(vec![trade(1), trade(2), trade(3), trade(4)], &known);
+        let ids: Vec<u64> = new.iter().map(|t| t.trade_id).collect();
+        assert_eq!(ids, vec![1, 4]);
+    }
+}
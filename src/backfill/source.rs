@@ -0,0 +1,11 @@
+use crate::connector::errors::Error;
+use crate::shared::{Exchange, TimestampMS};
+use crate::trade::TradeEvent;
+
+/// REST endpoint with the trade history of an exchange
+pub trait TradeSource: Send + Sync {
+    fn exchange(&self) -> &Exchange;
+
+    /// Trades with `from <= timestamp <= to`, oldest first
+    async fn trades_between(&self, ticker: &str, from: TimestampMS, to: TimestampMS) -> Result<Vec<TradeEvent>, Error>;
+}
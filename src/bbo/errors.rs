@@ -0,0 +1,5 @@
+#[derive(Debug, thiserror::Error)]
+pub enum BboError {
+    #[error("RepoError: {0}")]
+    RepoError(#[from] clickhouse::error::Error),
+}
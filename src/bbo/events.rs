@@ -0,0 +1,17 @@
+use crate::shared::{Exchange, Price, Quantity, TimestampMS, TimestampNS};
+use std::sync::Arc;
+
+#[derive(Debug, Clone)]
+pub struct BestBidAsk {
+    pub exchange: Exchange,
+    pub ticker: Arc<String>,
+    pub bid_price: Price,
+    pub bid_quantity: Quantity,
+    pub ask_price: Price,
+    pub ask_quantity: Quantity,
+    /// Exchange order of the updates, 0 when the feed has none
+    pub update_id: u64,
+    /// Exchange time, 0 when the feed has none
+    pub timestamp: TimestampMS,
+    pub received: TimestampNS,
+}
@@ -0,0 +1,7 @@
+mod errors;
+mod events;
+mod repo;
+
+pub use errors::BboError;
+pub use events::BestBidAsk;
+pub use repo::{create_best_bid_ask_table, BestBidAskRepo};
@@ -0,0 +1,103 @@
+use clickhouse::{insert::Insert, Client, Row};
+use serde::Serialize;
+
+use crate::bbo::{BboError, BestBidAsk};
+use crate::shared::logger::Logger;
+use crate::shared::utils::buffer_service::Callback;
+use crate::shared::{Price, Quantity, TimestampMS};
+
+#[derive(Row, Serialize)]
+struct BestBidAskRow {
+    exchange: u8,
+    ticker: String,
+    bid_price: Price,
+    bid_quantity: Quantity,
+    ask_price: Price,
+    ask_quantity: Quantity,
+    update_id: u64,
+    timestamp: TimestampMS,
+    received: u64,
+}
+
+impl BestBidAskRow {
+    pub fn from_bbo(ev: &BestBidAsk) -> Self {
+        Self {
+            exchange: ev.exchange.clone() as u8,
+            ticker: ev.ticker.to_string(),
+            bid_price: ev.bid_price,
+            bid_quantity: ev.bid_quantity,
+            ask_price: ev.ask_price,
+            ask_quantity: ev.ask_quantity,
+            update_id: ev.update_id,
+            timestamp: ev.timestamp,
+            received: ev.received,
+        }
+    }
+}
+
+pub struct BestBidAskRepo<'a> {
+    client: &'a Client,
+}
+
+impl<'a> BestBidAskRepo<'a> {
+    pub fn new(client: &'a Client) -> Self {
+        Self { client }
+    }
+
+    pub async fn save(&self, events: &[BestBidAsk]) -> Result<(), BboError> {
+        if events.is_empty() {
+            return Ok(());
+        }
+
+        let mut insert: Insert<BestBidAskRow> = self.client.insert("best_bid_asks").await?;
+
+        for ev in events {
+            insert.write(&BestBidAskRow::from_bbo(ev)).await?;
+        }
+
+        insert.end().await?;
+        Ok(())
+    }
+}
+
+impl<'a> Callback<BestBidAsk, BboError> for BestBidAskRepo<'a> {
+    async fn on_buffer_flush(&self, data: &[BestBidAsk]) -> Result<(), BboError> {
+        self.save(data).await
+    }
+}
+
+pub async fn create_best_bid_ask_table(
+    client: &Client,
+    logger: &Logger,
+    db_name: &str,
+) -> Result<(), BboError> {
+    logger.info("Creating best bid ask table");
+
+    let query = format!(
+        r#"
+        CREATE TABLE IF NOT EXISTS {}.best_bid_asks (
+            exchange UInt8,
+            ticker String,
+            bid_price UInt64,
+            bid_quantity UInt64,
+            ask_price UInt64,
+            ask_quantity UInt64,
+            update_id UInt64,
+            timestamp UInt64,
+            received UInt64
+        ) ENGINE = MergeTree()
+        ORDER BY (exchange, ticker, received)
+    "#,
+        db_name
+    );
+
+    client.query(&query).execute().await?;
+
+    // Tables created before update ids were stored
+    let migrate = format!(
+        "ALTER TABLE {}.best_bid_asks ADD COLUMN IF NOT EXISTS update_id UInt64 AFTER ask_quantity",
+        db_name
+    );
+    client.query(&migrate).execute().await?;
+    Ok(())
+}
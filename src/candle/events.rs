@@ -0,0 +1,60 @@
+use crate::shared::{Exchange, Price, Quantity, TimestampMS, TimestampNS};
+use std::sync::Arc;
+
+/// Intervals supported by both Binance klines and Kraken ohlc
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum CandleInterval {
+    M1,
+    M5,
+    M15,
+    M30,
+    H1,
+    H4,
+    D1,
+    W1,
+}
+
+impl CandleInterval {
+    pub fn minutes(&self) -> u32 {
+        match self {
+            CandleInterval::M1 => 1,
+            CandleInterval::M5 => 5,
+            CandleInterval::M15 => 15,
+            CandleInterval::M30 => 30,
+            CandleInterval::H1 => 60,
+            CandleInterval::H4 => 240,
+            CandleInterval::D1 => 1440,
+            CandleInterval::W1 => 10080,
+        }
+    }
+
+    pub fn from_minutes(minutes: u32) -> Option<Self> {
+        match minutes {
+            1 => Some(CandleInterval::M1),
+            5 => Some(CandleInterval::M5),
+            15 => Some(CandleInterval::M15),
+            30 => Some(CandleInterval::M30),
+            60 => Some(CandleInterval::H1),
+            240 => Some(CandleInterval::H4),
+            1440 => Some(CandleInterval::D1),
+            10080 => Some(CandleInterval::W1),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Candle {
+    pub exchange: Exchange,
+    pub ticker: Arc<String>,
+    pub interval: CandleInterval,
+    pub open_time: TimestampMS,
+    pub open: Price,
+    pub high: Price,
+    pub low: Price,
+    pub close: Price,
+    pub volume: Quantity,
+    pub closed: bool,
+    pub timestamp: TimestampMS,
+    pub received: TimestampNS,
+}
@@ -0,0 +1,7 @@
+mod errors;
+mod events;
+mod repo;
+
+pub use errors::CandleError;
+pub use events::{Candle, CandleInterval};
+pub use repo::{create_candles_table, CandleRepo};
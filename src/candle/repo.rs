@@ -0,0 +1,106 @@
+use clickhouse::{insert::Insert, Client, Row};
+use serde::Serialize;
+
+use crate::candle::{Candle, CandleError};
+use crate::shared::logger::Logger;
+use crate::shared::utils::buffer_service::Callback;
+use crate::shared::{Price, Quantity, TimestampMS};
+
+#[derive(Row, Serialize)]
+struct CandleRow {
+    exchange: u8,
+    ticker: String,
+    interval: u32,
+    open_time: TimestampMS,
+    open: Price,
+    high: Price,
+    low: Price,
+    close: Price,
+    volume: Quantity,
+    closed: u8,
+    timestamp: TimestampMS,
+    received: u64,
+}
+
+impl CandleRow {
+    pub fn from_candle(ev: &Candle) -> Self {
+        Self {
+            exchange: ev.exchange.clone() as u8,
+            ticker: ev.ticker.to_string(),
+            interval: ev.interval.minutes(),
+            open_time: ev.open_time,
+            open: ev.open,
+            high: ev.high,
+            low: ev.low,
+            close: ev.close,
+            volume: ev.volume,
+            closed: ev.closed as u8,
+            timestamp: ev.timestamp,
+            received: ev.received,
+        }
+    }
+}
+
+pub struct CandleRepo<'a> {
+    client: &'a Client,
+}
+
+impl<'a> CandleRepo<'a> {
+    pub fn new(client: &'a Client) -> Self {
+        Self { client }
+    }
+
+    pub async fn save(&self, events: &[Candle]) -> Result<(), CandleError> {
+        if events.is_empty() {
+            return Ok(());
+        }
+
+        let mut insert: Insert<CandleRow> = self.client.insert("candles").await?;
+
+        for ev in events {
+            insert.write(&CandleRow::from_candle(ev)).await?;
+        }
+
+        insert.end().await?;
+        Ok(())
+    }
+}
+
+impl<'a> Callback<Candle, CandleError> for CandleRepo<'a> {
+    async fn on_buffer_flush(&self, data: &[Candle]) -> Result<(), CandleError> {
+        self.save(data).await
+    }
+}
+
+pub async fn create_candles_table(
+    client: &Client,
+    logger: &Logger,
+    db_name: &str,
+) -> Result<(), CandleError> {
+    logger.info("Creating candles table");
+
+    // Exchanges resend the current bar on every change, the latest received version wins
+    let query = format!(
+        r#"
+        CREATE TABLE IF NOT EXISTS {}.candles (
+            exchange UInt8,
+            ticker String,
+            interval UInt32,
+            open_time UInt64,
+            open UInt64,
+            high UInt64,
+            low UInt64,
+            close UInt64,
+            volume UInt64,
+            closed UInt8,
+            timestamp UInt64,
+            received UInt64
+        ) ENGINE = ReplacingMergeTree(received)
+        ORDER BY (exchange, ticker, interval, open_time)
+    "#,
+        db_name
+    );
+
+    client.query(&query).execute().await?;
+    Ok(())
+}
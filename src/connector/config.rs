@@ -1,15 +1,23 @@
 use std::sync::Arc;
 use tracing::Level;
+use url::Url;
 use crate::connector::errors::{Error, ErrorHandler};
 use crate::connector::errors::Error::BuilderError;
+use crate::candle::CandleInterval;
+use crate::connector::services::http::HttpClient;
+use crate::shared::InstrumentKind;
 
 #[derive(Debug)]
 pub struct TickerConfig {
     pub ticker: Arc<String>,
+    pub instrument: InstrumentKind,
     pub price_multiply: f64,
     pub quantity_multiply: f64,
     pub subscribe_trades: bool,
     pub subscribe_depth: bool,
+    pub subscribe_bbo: bool,
+    pub candle_interval: Option<CandleInterval>,
+    pub level3_depth: Option<u16>,
     pub depth_value: u8,
 }
 
@@ -25,30 +33,14 @@ impl<'a> TickerConfigValidator<'a> {
         }
     }
     fn is_valid_symbol(&self, t: &str) -> bool {
-        // ABC
-        if t.len() >= 1 && t.len() <= 10 && t.chars().all(|c| c.is_ascii_alphabetic()) {
-            return true;
-        }
-
-        if let Some((base, quote)) = t.split_once('/') {
-            if base.len() >= 1
-                && base.len() <= 10
-                && quote.len() >= 1
-                && quote.len() <= 10
-                && base.chars().all(|c| c.is_ascii_alphabetic())
-                && quote.chars().all(|c| c.is_ascii_alphabetic())
-            {
-                return true;
-            }
-        }
-
-        false
+        InstrumentKind::from_ticker(t).is_some()
     }
     fn validate_symbol(&mut self) {
         if !self.is_valid_symbol(&self.ticker.ticker) {
             let err = BuilderError(
                 format!(
-                    "Ticker should be one of the following formats: AAPL for stocks; BTC/USD for cryptocurrencies.\
+                    "Ticker should be one of the following formats: AAPL for stocks; BTC/USD for cryptocurrencies; \
+                     BTC-PERPETUAL for perpetuals; BTC-27DEC24 for futures; BTC-27DEC24-60000-C for options.\
                      Upper case or lower case are available. Your value is '{}'",
                     self.ticker.ticker
                 ));
@@ -73,8 +65,79 @@ impl<'a> TickerConfigValidator<'a> {
     }
 }
 
+#[derive(Clone)]
+pub struct ApiCredentials {
+    pub api_key: String,
+    pub api_secret: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ProxyKind {
+    /// HTTP CONNECT tunnel
+    Http,
+    Socks5,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProxyConfig {
+    pub kind: ProxyKind,
+    pub host: String,
+    pub port: u16,
+    pub username: Option<String>,
+    pub password: Option<String>,
+}
+
+impl ProxyConfig {
+    /// `http://[user:pass@]host:port` or `socks5://[user:pass@]host:port`
+    pub fn parse(raw: &str) -> Result<Self, Error> {
+        let url = Url::parse(raw).map_err(|e| BuilderError(format!("Invalid proxy url '{}': {}", raw, e)))?;
+        let kind = match url.scheme() {
+            "http" => ProxyKind::Http,
+            "socks5" | "socks5h" => ProxyKind::Socks5,
+            other => Err(BuilderError(format!("Unsupported proxy scheme '{}'", other)))?,
+        };
+        let host = url
+            .host_str()
+            .ok_or_else(|| BuilderError(format!("Proxy url '{}' has no host", raw)))?;
+        let port = url.port().unwrap_or(match kind {
+            ProxyKind::Http => 8080,
+            ProxyKind::Socks5 => 1080,
+        });
+        Ok(Self {
+            kind,
+            host: host.to_string(),
+            port,
+            username: Some(url.username()).filter(|u| !u.is_empty()).map(str::to_string),
+            password: url.password().map(str::to_string),
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum TlsBackend {
+    /// System TLS library and certificate store
+    #[default]
+    NativeTls,
+    /// rustls with the roots of the platform certificate store
+    Rustls,
+}
+
+/// How connectors reach exchanges, applied to WebSocket and REST traffic alike
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct NetworkConfig {
+    pub proxy: Option<ProxyConfig>,
+    pub tls_backend: TlsBackend,
+    /// Extra trusted roots in PEM, e.g. for a local mock with a self-signed certificate
+    pub ca_certificates: Vec<Vec<u8>>,
+}
+
 pub struct ConnectorConfig {
     pub ticker_configs: Vec<TickerConfig>,
     pub error_handlers: Vec<ErrorHandler>,
     pub log_level: Level,
+    pub kraken_token: Option<String>,
+    pub credentials: Option<ApiCredentials>,
+    pub network: NetworkConfig,
+    /// REST client for `network`, shared by connectors with the same settings
+    pub http: Arc<HttpClient>,
 }
@@ -1,6 +1,12 @@
+use crate::account::{BalanceUpdate, Fill, OrderUpdate};
+use crate::bbo::BestBidAsk;
+use crate::candle::Candle;
 use crate::connector::errors::Error;
+use crate::dead_letter::DeadLetter;
+use crate::derivatives::DerivativeTicker;
 use crate::connector::services::websocket::{websocket_stream, Connection};
 use crate::level2::LevelUpdated;
+use crate::level3::OrderEvent;
 use crate::trade::TradeEvent;
 use async_stream::stream;
 use futures::Stream;
@@ -8,11 +14,22 @@ use futures_util::StreamExt;
 use std::pin::Pin;
 use crossbeam::queue::SegQueue;
 use crate::shared::logger::Logger;
+use crate::shared::utils::now_timestamp_ns;
+use crate::shared::Exchange;
+use std::sync::atomic::{AtomicU64, Ordering};
 
 #[derive(Debug, Clone)]
 pub enum Event {
     Trade(TradeEvent),
     LevelUpdate(LevelUpdated),
+    BestBidAsk(BestBidAsk),
+    Candle(Candle),
+    DerivativeTicker(DerivativeTicker),
+    Order(OrderEvent),
+    OrderUpdate(OrderUpdate),
+    Fill(Fill),
+    BalanceUpdate(BalanceUpdate),
+    DeadLetter(DeadLetter),
 }
 
 pub type StreamBuffer = SegQueue<Event>;
@@ -31,12 +48,39 @@ pub(crate) trait ConnectorInternal: Send + Sync {
     fn on_error(&self, err: &Error);
 
     fn logger(&self) ->  &Logger;
+
+    fn exchange(&self) -> &Exchange;
+
+    /// Data messages read by `connect` while it was waiting for subscription acks
+    fn take_pending(&self) -> Vec<String> {
+        Vec::new()
+    }
+}
+
+// Tells apart reconnects of the same exchange in logs and dead letters
+static CONNECTION_ID: AtomicU64 = AtomicU64::new(1);
+
+fn dead_letter(err: &Error, raw: &str) -> Option<DeadLetter> {
+    let context = err.context()?;
+    if !context.class.is_feed_format() {
+        return None;
+    }
+    Some(DeadLetter {
+        exchange: context.exchange.clone(),
+        connection_id: context.connection_id,
+        class: context.class,
+        error: err.to_string(),
+        raw: raw.to_string(),
+        received: now_timestamp_ns(),
+    })
 }
 
 impl<T: ConnectorInternal + 'static> Connector for T {
     async fn stream(self) -> Result<EventStream, Error> {
         let (write, read) = self.connect().await?;
-        let ws = websocket_stream(write, read);
+        let connection_id = CONNECTION_ID.fetch_add(1, Ordering::Relaxed);
+        let pending = futures_util::stream::iter(self.take_pending().into_iter().map(Ok));
+        let ws = pending.chain(websocket_stream(write, read));
         let buffer: StreamBuffer = SegQueue::new();
 
         // перемещаем self внутрь стрима через move
@@ -54,12 +98,17 @@ impl<T: ConnectorInternal + 'static> Connector for T {
                                 }
                             }
                             Err(err) => {
+                                let err = err.with_context(this.exchange().clone(), connection_id, &txt);
+                                if let Some(letter) = dead_letter(&err, &txt) {
+                                    yield Event::DeadLetter(letter);
+                                }
                                 this.on_error(&err);
                                 continue;
                             }
                         }
                     }
                     Some(Err(err)) => {
+                        let err = err.with_context(this.exchange().clone(), connection_id, "");
                         this.on_error(&err);
                         continue;
                     }
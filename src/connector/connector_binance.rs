@@ -1,22 +1,27 @@
-use crate::connector::config::{ConnectorConfig, TickerConfig};
+use crate::bbo::BestBidAsk;
+use crate::candle::{Candle, CandleInterval};
+use crate::connector::config::{ConnectorConfig, TickerConfig, NetworkConfig};
 use crate::connector::connector::{ConnectorInternal, StreamBuffer};
 use crate::connector::errors::Error::InternalError;
 use crate::connector::errors::ExchangeError::BinanceError;
-use crate::connector::errors::ParsingError::MessageParsingError;
+use crate::connector::errors::ParsingError::{ConvertingError, MessageParsingError, UnknownMessage};
 use crate::connector::errors::{Error, ErrorHandler};
 use crate::connector::services::parser::{model_from_string, parse_number, parse_serde_value};
 use crate::connector::services::ticker_map::TickerMap;
-use crate::connector::services::websocket::{connect_websocket, Connection};
+use crate::connector::services::control::{await_subscriptions, parse_binance_control, ControlMessage};
+use crate::connector::services::http::HttpClient;
+use crate::connector::services::symbols::SymbolCache;
+use crate::connector::services::websocket::{connect_websocket, send_ws_message, Connection};
 use crate::connector::Event;
 use crate::level2::LevelUpdated;
 use crate::shared::logger::Logger;
 use crate::shared::{Exchange, Price, Quantity, Side};
 use crate::trade::TradeEvent;
-use reqwest::get;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use std::collections::HashSet;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
+use tokio_tungstenite::tungstenite::Message;
 use crate::shared::utils::now_timestamp_ns;
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -45,6 +50,8 @@ struct AggTradeMessage {
     event_time: u64,
     #[serde(rename = "s")]
     symbol: String,
+    #[serde(rename = "a")]
+    aggregate_id: u64,
     #[serde(rename = "p")]
     price: String,
     #[serde(rename = "q")]
@@ -53,16 +60,85 @@ struct AggTradeMessage {
     is_buyer_maker: bool,
 }
 
-fn convert_ticker_into_binance_symbol(raw: &str) -> String {
+#[derive(Debug, Serialize, Deserialize)]
+struct BookTickerMessage {
+    #[serde(rename = "u")]
+    update_id: u64,
+    #[serde(rename = "s")]
+    symbol: String,
+    #[serde(rename = "b")]
+    bid_price: String,
+    #[serde(rename = "B")]
+    bid_quantity: String,
+    #[serde(rename = "a")]
+    ask_price: String,
+    #[serde(rename = "A")]
+    ask_quantity: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct KlineMessage {
+    #[serde(rename = "E")]
+    event_time: u64,
+    #[serde(rename = "s")]
+    symbol: String,
+    #[serde(rename = "k")]
+    kline: Kline,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Kline {
+    #[serde(rename = "t")]
+    open_time: u64,
+    #[serde(rename = "i")]
+    interval: String,
+    #[serde(rename = "o")]
+    open: String,
+    #[serde(rename = "h")]
+    high: String,
+    #[serde(rename = "l")]
+    low: String,
+    #[serde(rename = "c")]
+    close: String,
+    #[serde(rename = "v")]
+    volume: String,
+    #[serde(rename = "x")]
+    closed: bool,
+}
+
+fn binance_interval(interval: CandleInterval) -> &'static str {
+    match interval {
+        CandleInterval::M1 => "1m",
+        CandleInterval::M5 => "5m",
+        CandleInterval::M15 => "15m",
+        CandleInterval::M30 => "30m",
+        CandleInterval::H1 => "1h",
+        CandleInterval::H4 => "4h",
+        CandleInterval::D1 => "1d",
+        CandleInterval::W1 => "1w",
+    }
+}
+
+/// Letter case of Binance symbols: websocket streams take lower case, the REST API upper case
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum SymbolCase {
+    Lower,
+    Upper,
+}
+
+pub(crate) fn convert_ticker_into_binance_symbol(raw: &str, case: SymbolCase) -> String {
     raw.chars()
-        .filter(|c| c.is_ascii_alphabetic()) // удаляем "/", "-" и всё лишнее
-        .flat_map(|c| c.to_lowercase()) // to_lowercase возвращает итератор
+        .filter(|c| c.is_ascii_alphanumeric()) // удаляем "/", "-" и всё лишнее
+        .map(|c| match case {
+            SymbolCase::Lower => c.to_ascii_lowercase(),
+            SymbolCase::Upper => c.to_ascii_uppercase(),
+        })
         .collect()
 }
 
-async fn fetch_binance_symbols() -> Result<HashSet<String>, Error> {
-    let url = "https://api.binance.com/api/v3/exchangeInfo";
-    let resp: Value = get(url).await?.json().await?;
+async fn fetch_binance_symbols(http: &HttpClient, rest_url: &str) -> Result<HashSet<String>, Error> {
+    let url = format!("{}/api/v3/exchangeInfo", rest_url);
+    let resp: Value = http.send(&Exchange::Binance, 20, http.get(&url)).await?.json().await?;
     let result = resp["symbols"]
         .as_array()
         .unwrap()
@@ -72,34 +148,36 @@ async fn fetch_binance_symbols() -> Result<HashSet<String>, Error> {
     Ok(result)
 }
 
-pub struct BinanceUrlBuilder<'a> {
+pub struct BinanceSubscriptionBuilder<'a> {
     configs: &'a [TickerConfig],
 }
 
-impl<'a> BinanceUrlBuilder<'a> {
+impl<'a> BinanceSubscriptionBuilder<'a> {
     pub fn new(configs: &'a [TickerConfig]) -> Self {
         Self { configs }
     }
 
-    pub fn build_url(&self) -> Result<String, Error> {
+    pub fn build_message(&self, id: u64) -> Result<String, Error> {
         let streams = self.build_streams()?;
-        Ok(format!(
-            "wss://stream.binance.com:9443/stream?streams={}",
-            streams.join("/")
-        ))
+        let msg = serde_json::json!({
+            "method": "SUBSCRIBE",
+            "params": streams,
+            "id": id
+        });
+        Ok(msg.to_string())
     }
 
     fn build_streams(&self) -> Result<Vec<String>, Error> {
         let mut out = Vec::new();
 
         for cfg in self.configs {
-            let symbol = convert_ticker_into_binance_symbol(&cfg.ticker);
+            let symbol = convert_ticker_into_binance_symbol(&cfg.ticker, SymbolCase::Lower);
             out.extend(self.build_streams_for_symbol(cfg, &symbol));
         }
 
         if out.is_empty() {
             Err(BinanceError(
-                "No streams configured. Enable subscribe_trades/subscribe_depth and provide tickers"
+                "No streams configured. Enable subscribe_trades/subscribe_depth/subscribe_bbo/subscribe_candles and provide tickers"
                     .to_string()
             ))?;
         }
@@ -118,6 +196,14 @@ impl<'a> BinanceUrlBuilder<'a> {
             streams.push(self.build_trades_stream(symbol));
         }
 
+        if cfg.subscribe_bbo {
+            streams.push(self.build_bbo_stream(symbol));
+        }
+
+        if let Some(interval) = cfg.candle_interval {
+            streams.push(self.build_candles_stream(symbol, interval));
+        }
+
         streams
     }
 
@@ -128,6 +214,14 @@ impl<'a> BinanceUrlBuilder<'a> {
     fn build_trades_stream(&self, symbol: &str) -> String {
         format!("{symbol}@aggTrade")
     }
+
+    fn build_bbo_stream(&self, symbol: &str) -> String {
+        format!("{symbol}@bookTicker")
+    }
+
+    fn build_candles_stream(&self, symbol: &str, interval: CandleInterval) -> String {
+        format!("{symbol}@kline_{}", binance_interval(interval))
+    }
 }
 
 pub struct BinanceConnector {
@@ -135,6 +229,12 @@ pub struct BinanceConnector {
     configs: TickerMap,
     logger: Logger,
     error_handlers: Vec<ErrorHandler>,
+    network: NetworkConfig,
+    rest_url: String,
+    ws_url: String,
+    pending: Mutex<Vec<String>>,
+    symbols: Arc<SymbolCache>,
+    http: Arc<HttpClient>,
 }
 
 impl BinanceConnector {
@@ -142,18 +242,36 @@ impl BinanceConnector {
         Self {
             configs: TickerMap::from_configs(
                 config.ticker_configs,
-                convert_ticker_into_binance_symbol,
+                |t| convert_ticker_into_binance_symbol(t, SymbolCase::Lower),
             ),
             logger: Logger::new("binance", config.log_level),
             exchange: Exchange::Binance,
             error_handlers: config.error_handlers,
+            network: config.network.clone(),
+            rest_url: "https://api.binance.com".to_string(),
+            ws_url: "wss://stream.binance.com:9443".to_string(),
+            pending: Mutex::new(Vec::new()),
+            symbols: SymbolCache::global(),
+            http: Arc::clone(&config.http),
         }
     }
 
+    #[cfg(test)]
+    pub fn with_urls(mut self, rest_url: &str, ws_url: &str) -> Self {
+        self.rest_url = rest_url.to_string();
+        self.ws_url = ws_url.to_string();
+        self.symbols = SymbolCache::temporary();
+        self.http = Arc::new(HttpClient::new());
+        self
+    }
+
     async fn check_symbols(&self) -> Result<(), Error> {
         self.logger.info("Check symbols");
 
-        let valid_symbols = fetch_binance_symbols().await?;
+        let valid_symbols = self
+            .symbols
+            .get_or_fetch(&self.exchange, || fetch_binance_symbols(&self.http, &self.rest_url), &self.logger)
+            .await?;
         let symbols = self.configs.get_all_symbols();
 
         if symbols.is_empty() {
@@ -188,6 +306,8 @@ impl BinanceConnector {
                 quantity: quantity as Quantity,
                 timestamp: parsed.event_time,
                 received: now_timestamp_ns(),
+                sequence: parsed.final_update_id,
+                prev_sequence: parsed.first_update_id.saturating_sub(1),
             };
             result.push(Event::LevelUpdate(ev));
         }
@@ -204,6 +324,8 @@ impl BinanceConnector {
                 quantity: quantity as Quantity,
                 timestamp: parsed.event_time,
                 received: now_timestamp_ns(),
+                sequence: parsed.final_update_id,
+                prev_sequence: parsed.first_update_id.saturating_sub(1),
             };
             result.push(Event::LevelUpdate(ev));
         }
@@ -225,6 +347,7 @@ impl BinanceConnector {
         let event = TradeEvent {
             ticker: Arc::clone(&ticker_config.ticker),
             exchange: self.exchange.clone(),
+            trade_id: trade.aggregate_id,
             price: price as Price,
             quantity: qty as Quantity,
             timestamp: trade.event_time,
@@ -235,24 +358,114 @@ impl BinanceConnector {
 
         Ok(())
     }
+
+    fn handle_bbo(&self, data: &Value, result: &StreamBuffer) -> Result<(), Error> {
+        self.logger.debug("Handle book ticker message");
+
+        let txt = data.to_string();
+        let bbo = model_from_string::<BookTickerMessage>(&txt)?;
+
+        let ticker_config = self.configs.get_by_symbol(&bbo.symbol.to_lowercase())?;
+        let price = |v: &str| -> Result<Price, Error> {
+            Ok((parse_number(v)? * ticker_config.price_multiply) as Price)
+        };
+        let quantity = |v: &str| -> Result<Quantity, Error> {
+            Ok((parse_number(v)? * ticker_config.quantity_multiply) as Quantity)
+        };
+
+        let event = BestBidAsk {
+            exchange: self.exchange.clone(),
+            ticker: Arc::clone(&ticker_config.ticker),
+            bid_price: price(&bbo.bid_price)?,
+            bid_quantity: quantity(&bbo.bid_quantity)?,
+            ask_price: price(&bbo.ask_price)?,
+            ask_quantity: quantity(&bbo.ask_quantity)?,
+            update_id: bbo.update_id,
+            // bookTicker carries no event time
+            timestamp: 0,
+            received: now_timestamp_ns(),
+        };
+        result.push(Event::BestBidAsk(event));
+
+        Ok(())
+    }
+
+    fn handle_candle(&self, data: &Value, result: &StreamBuffer) -> Result<(), Error> {
+        self.logger.debug("Handle kline message");
+
+        let txt = data.to_string();
+        let msg = model_from_string::<KlineMessage>(&txt)?;
+
+        let ticker_config = self.configs.get_by_symbol(&msg.symbol.to_lowercase())?;
+        let interval = ticker_config
+            .candle_interval
+            .ok_or_else(|| InternalError(format!("Unexpected kline for {}", msg.symbol)))?;
+        if binance_interval(interval) != msg.kline.interval {
+            Err(ConvertingError(format!("Unexpected interval {}", msg.kline.interval)))?;
+        }
+
+        let price = |v: &str| -> Result<Price, Error> {
+            Ok((parse_number(v)? * ticker_config.price_multiply) as Price)
+        };
+
+        let event = Candle {
+            exchange: self.exchange.clone(),
+            ticker: Arc::clone(&ticker_config.ticker),
+            interval,
+            open_time: msg.kline.open_time,
+            open: price(&msg.kline.open)?,
+            high: price(&msg.kline.high)?,
+            low: price(&msg.kline.low)?,
+            close: price(&msg.kline.close)?,
+            volume: (parse_number(&msg.kline.volume)? * ticker_config.quantity_multiply) as Quantity,
+            closed: msg.kline.closed,
+            timestamp: msg.event_time,
+            received: now_timestamp_ns(),
+        };
+        result.push(Event::Candle(event));
+
+        Ok(())
+    }
 }
 
 impl ConnectorInternal for BinanceConnector {
     async fn connect(&self) -> Result<Connection, Error> {
         self.logger.info("Connecting...");
-        let builder = BinanceUrlBuilder::new(self.configs.get_all_configs());
-        let url = builder.build_url()?;
+        let subscribe = BinanceSubscriptionBuilder::new(self.configs.get_all_configs()).build_message(1)?;
         self.check_symbols().await?;
-        connect_websocket(&url, &self.logger).await
+
+        let url = format!("{}/stream", self.ws_url);
+        let (mut write, mut read) = connect_websocket(&url, &self.network, &self.logger).await?;
+        send_ws_message(&mut write, Message::Text(subscribe)).await?;
+        let pending = await_subscriptions(&mut read, 1, parse_binance_control, &self.logger).await?;
+        *self.pending.lock().unwrap() = pending;
+
+        Ok((write, read))
+    }
+
+    fn take_pending(&self) -> Vec<String> {
+        std::mem::take(&mut self.pending.lock().unwrap())
     }
 
     fn on_message(&self, msg: &str, result: &StreamBuffer) -> Result<(), Error> {
         let wrapper = parse_serde_value(msg)?;
 
+        if let Some(control) = wrapper.as_object().and_then(parse_binance_control) {
+            return match control {
+                ControlMessage::Rejected { reason, .. } => Err(BinanceError(reason))?,
+                ControlMessage::Subscribed { .. } => Ok(()),
+            };
+        }
+
         let data = wrapper.get("data").ok_or_else(|| {
             MessageParsingError(format!("Missing 'data' field in wrapper: {}", msg))
         })?;
 
+        let stream = wrapper.get("stream").and_then(|v| v.as_str()).unwrap_or_default();
+        if stream.ends_with("@bookTicker") {
+            return self.handle_bbo(data, result);
+        }
+
         let event_type = data
             .get("e")
             .and_then(|v| v.as_str())
@@ -261,16 +474,13 @@ impl ConnectorInternal for BinanceConnector {
         match event_type {
             "depthUpdate" => self.handle_depth(data, result),
             "aggTrade" => self.handle_trade(data, result),
-            other => Err(MessageParsingError(format!(
-                "Unknown errors type: {}",
-                other
-            )))?,
+            "kline" => self.handle_candle(data, result),
+            other => Err(UnknownMessage(format!("Unexpected event type {}", other)))?,
         }
     }
 
     fn on_error(&self, err: &Error) {
-        let err_message = format!("{:?}", err);
-        self.logger.error(&err_message);
+        self.logger.error(&err.to_string());
         for handler in self.error_handlers.iter() {
             handler(err)
         }
@@ -279,4 +489,159 @@ impl ConnectorInternal for BinanceConnector {
     fn logger(&self) -> &Logger {
         &self.logger
     }
+
+    fn exchange(&self) -> &Exchange {
+        &self.exchange
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::connector::errors::ExchangeError;
+    use crate::connector::services::mock_server::{mock_http_server, mock_ws_server};
+    use crate::connector::Connector;
+    use futures_util::StreamExt;
+    use crate::shared::InstrumentKind;
+    use tracing::Level;
+
+    fn config(subscribe_bbo: bool) -> TickerConfig {
+        TickerConfig {
+            ticker: Arc::new("btc/usdt".to_string()),
+            instrument: InstrumentKind::Spot,
+            price_multiply: 100.0,
+            quantity_multiply: 1000.0,
+            subscribe_trades: false,
+            subscribe_depth: false,
+            subscribe_bbo,
+            candle_interval: None,
+            level3_depth: None,
+            depth_value: 0,
+        }
+    }
+
+    #[test]
+    fn test_symbol_case() {
+        assert_eq!(convert_ticker_into_binance_symbol("1000pepe/usdt", SymbolCase::Lower), "1000pepeusdt");
+        assert_eq!(convert_ticker_into_binance_symbol("btc/usdt", SymbolCase::Upper), "BTCUSDT");
+    }
+
+    #[test]
+    fn test_bbo_stream_subscribed() {
+        let configs = [config(true)];
+        let msg = BinanceSubscriptionBuilder::new(&configs).build_message(1).unwrap();
+        assert_eq!(msg, r#"{"id":1,"method":"SUBSCRIBE","params":["btcusdt@bookTicker"]}"#);
+    }
+
+    fn exchange_info() -> (u16, String) {
+        (200, r#"{"symbols":[{"symbol":"BTCUSDT"}]}"#.to_string())
+    }
+
+    fn connector(rest_url: &str, ws_url: &str) -> BinanceConnector {
+        BinanceConnector::new(ConnectorConfig {
+            ticker_configs: vec![config(true)],
+            error_handlers: vec![],
+            log_level: Level::ERROR,
+            kraken_token: None,
+            credentials: None,
+            network: NetworkConfig::default(),
+            http: Arc::new(HttpClient::new()),
+        })
+        .with_urls(rest_url, ws_url)
+    }
+
+    #[tokio::test]
+    async fn test_connect_waits_for_ack() {
+        let (rest_url, _rest) = mock_http_server(vec![exchange_info()]).await;
+        let (ws_url, ws) = mock_ws_server(
+            1,
+            vec![
+                r#"{"result":null,"id":1}"#.to_string(),
+                r#"{"stream":"btcusdt@bookTicker","data":{"u":1,"s":"BTCUSDT","b":"1.00","B":"1.000","a":"2.00","A":"1.000"}}"#.to_string(),
+            ],
+        )
+        .await;
+
+        let stream = connector(&rest_url, &ws_url).stream().await.unwrap();
+        let events: Vec<Event> = stream.collect().await;
+
+        let requests = ws.await.unwrap();
+        assert!(requests[0].contains(r#""method":"SUBSCRIBE""#));
+        assert_eq!(events.len(), 1);
+        assert!(matches!(events[0], Event::BestBidAsk(_)));
+    }
+
+    #[tokio::test]
+    async fn test_connect_fails_on_rejected_subscription() {
+        let (rest_url, _rest) = mock_http_server(vec![exchange_info()]).await;
+        let (ws_url, _ws) = mock_ws_server(
+            1,
+            vec![r#"{"error":{"code":2,"msg":"Invalid request: unknown property"},"id":1}"#.to_string()],
+        )
+        .await;
+
+        let res = connector(&rest_url, &ws_url).stream().await;
+        assert!(matches!(res, Err(Error::ExchangeError(ExchangeError::SubscriptionRejected(_)))));
+    }
+
+    #[test]
+    fn test_handle_book_ticker() {
+        let connector = BinanceConnector::new(ConnectorConfig {
+            ticker_configs: vec![config(true)],
+            error_handlers: vec![],
+            log_level: Level::ERROR,
+            kraken_token: None,
+            credentials: None,
+            network: NetworkConfig::default(),
+            http: Arc::new(HttpClient::new()),
+        });
+        let buffer = StreamBuffer::new();
+        let msg = r#"{"stream":"btcusdt@bookTicker","data":{"u":400900217,"s":"BTCUSDT",
+            "b":"60000.10","B":"1.500","a":"60000.20","A":"0.250"}}"#;
+        connector.on_message(msg, &buffer).unwrap();
+
+        match buffer.pop() {
+            Some(Event::BestBidAsk(bbo)) => {
+                assert_eq!(bbo.bid_price, 6_000_010);
+                assert_eq!(bbo.bid_quantity, 1500);
+                assert_eq!(bbo.ask_price, 6_000_020);
+                assert_eq!(bbo.ask_quantity, 250);
+                assert_eq!((bbo.update_id, bbo.timestamp), (400900217, 0));
+            }
+            other => panic!("unexpected event {:?}", other),
+        }
+        assert!(buffer.is_empty());
+    }
+
+    #[test]
+    fn test_handle_kline() {
+        let mut cfg = config(false);
+        cfg.candle_interval = Some(CandleInterval::M5);
+        let connector = BinanceConnector::new(ConnectorConfig {
+            ticker_configs: vec![cfg],
+            error_handlers: vec![],
+            log_level: Level::ERROR,
+            kraken_token: None,
+            credentials: None,
+            network: NetworkConfig::default(),
+            http: Arc::new(HttpClient::new()),
+        });
+        let buffer = StreamBuffer::new();
+        let msg = r#"{"stream":"btcusdt@kline_5m","data":{"e":"kline","E":1700000100000,"s":"BTCUSDT",
+            "k":{"t":1700000000000,"T":1700000299999,"s":"BTCUSDT","i":"5m","f":1,"L":2,
+                 "o":"100.00","c":"101.50","h":"102.00","l":"99.50","v":"3.5","n":2,"x":true,
+                 "q":"350.0","V":"1.0","Q":"100.0","B":"0"}}}"#;
+        connector.on_message(msg, &buffer).unwrap();
+
+        match buffer.pop() {
+            Some(Event::Candle(c)) => {
+                assert_eq!(c.interval, CandleInterval::M5);
+                assert_eq!(c.open_time, 1_700_000_000_000);
+                assert_eq!((c.open, c.high, c.low, c.close), (10_000, 10_200, 9_950, 10_150));
+                assert_eq!(c.volume, 3_500);
+                assert!(c.closed);
+            }
+            other => panic!("unexpected event {:?}", other),
+        }
+    }
 }
@@ -0,0 +1,407 @@
+use crate::account::{BalanceUpdate, Fill, OrderStatus, OrderUpdate};
+use crate::connector::config::{ApiCredentials, ConnectorConfig, NetworkConfig};
+use crate::connector::connector::{ConnectorInternal, StreamBuffer};
+use crate::connector::connector_binance::{convert_ticker_into_binance_symbol, SymbolCase};
+use crate::connector::errors::Error::BuilderError;
+use crate::connector::errors::ExchangeError::BinanceError;
+use crate::connector::errors::ParsingError::ConvertingError;
+use crate::connector::errors::{Error, ErrorHandler};
+use crate::connector::services::http::HttpClient;
+use crate::connector::services::parser::{model_from_serde_value, parse_number, parse_serde_object};
+use crate::connector::services::ticker_map::TickerMap;
+use crate::connector::services::websocket::{connect_websocket, Connection};
+use crate::connector::Event;
+use crate::shared::logger::Logger;
+use crate::shared::utils::now_timestamp_ns;
+use crate::shared::{Exchange, Price, Quantity, Side};
+use serde::Deserialize;
+use serde_json::Value;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::task::JoinHandle;
+
+// Binance closes a listenKey after 60 minutes without a keepalive
+const KEEPALIVE_PERIOD: Duration = Duration::from_secs(30 * 60);
+
+#[derive(Debug, Deserialize)]
+struct ExecutionReport {
+    #[serde(rename = "s")]
+    symbol: String,
+    #[serde(rename = "c")]
+    client_order_id: String,
+    #[serde(rename = "S")]
+    side: String,
+    #[serde(rename = "q")]
+    quantity: String,
+    #[serde(rename = "p")]
+    price: String,
+    #[serde(rename = "x")]
+    execution_type: String,
+    #[serde(rename = "X")]
+    status: String,
+    #[serde(rename = "i")]
+    order_id: u64,
+    #[serde(rename = "l")]
+    last_quantity: String,
+    #[serde(rename = "z")]
+    cumulative_quantity: String,
+    #[serde(rename = "L")]
+    last_price: String,
+    #[serde(rename = "n")]
+    commission: String,
+    #[serde(rename = "N")]
+    commission_asset: Option<String>,
+    #[serde(rename = "T")]
+    transaction_time: u64,
+    #[serde(rename = "t")]
+    trade_id: i64,
+    #[serde(rename = "m")]
+    is_maker: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct AccountPosition {
+    #[serde(rename = "u")]
+    update_time: u64,
+    #[serde(rename = "B")]
+    balances: Vec<AccountBalance>,
+}
+
+#[derive(Debug, Deserialize)]
+struct AccountBalance {
+    #[serde(rename = "a")]
+    asset: String,
+    #[serde(rename = "f")]
+    free: String,
+    #[serde(rename = "l")]
+    locked: String,
+}
+
+fn parse_side(raw: &str) -> Result<Side, Error> {
+    match raw {
+        "BUY" => Ok(Side::Buy),
+        "SELL" => Ok(Side::Sell),
+        _ => Err(ConvertingError(format!("Unexpected side {}", raw)))?,
+    }
+}
+
+fn parse_status(raw: &str) -> Result<OrderStatus, Error> {
+    let status = match raw {
+        // Still resting until the exchange confirms the cancel
+        "NEW" | "PENDING_NEW" | "PENDING_CANCEL" => OrderStatus::New,
+        "PARTIALLY_FILLED" => OrderStatus::PartiallyFilled,
+        "FILLED" => OrderStatus::Filled,
+        "CANCELED" => OrderStatus::Canceled,
+        "REJECTED" => OrderStatus::Rejected,
+        "EXPIRED" | "EXPIRED_IN_MATCH" => OrderStatus::Expired,
+        _ => Err(ConvertingError(format!("Unexpected order status {}", raw)))?,
+    };
+    Ok(status)
+}
+
+fn check_binance_response(resp: &Value) -> Result<(), Error> {
+    if let Some(msg) = resp.get("msg").and_then(|m| m.as_str()) {
+        Err(BinanceError(msg.to_string()))?;
+    }
+    Ok(())
+}
+
+/// Private account stream: our own order updates, fills and balances.
+/// Opens a listenKey over REST and keeps it alive while the stream is running.
+pub struct BinanceUserConnector {
+    configs: TickerMap,
+    exchange: Exchange,
+    logger: Logger,
+    error_handlers: Vec<ErrorHandler>,
+    network: NetworkConfig,
+    credentials: Option<ApiCredentials>,
+    rest_url: String,
+    ws_url: String,
+    keepalive: Mutex<Option<JoinHandle<()>>>,
+    http: Arc<HttpClient>,
+}
+
+impl BinanceUserConnector {
+    pub fn new(config: ConnectorConfig) -> Self {
+        Self {
+            configs: TickerMap::from_configs(
+                config.ticker_configs,
+                |t| convert_ticker_into_binance_symbol(t, SymbolCase::Lower),
+            ),
+            exchange: Exchange::Binance,
+            logger: Logger::new("binance-user", config.log_level),
+            error_handlers: config.error_handlers,
+            network: config.network.clone(),
+            credentials: config.credentials,
+            rest_url: "https://api.binance.com".to_string(),
+            ws_url: "wss://stream.binance.com:9443".to_string(),
+            keepalive: Mutex::new(None),
+            http: Arc::clone(&config.http),
+        }
+    }
+
+    #[cfg(test)]
+    pub fn with_urls(mut self, rest_url: &str, ws_url: &str) -> Self {
+        self.rest_url = rest_url.to_string();
+        self.ws_url = ws_url.to_string();
+        self.http = Arc::new(HttpClient::new());
+        self
+    }
+
+    async fn create_listen_key(&self, api_key: &str) -> Result<String, Error> {
+        let url = format!("{}/api/v3/userDataStream", self.rest_url);
+        let request = self.http.post(&url).header("X-MBX-APIKEY", api_key);
+        let resp: Value = self.http.send_once(&self.exchange, 2, request).await?.json().await?;
+        check_binance_response(&resp)?;
+
+        let key = resp["listenKey"]
+            .as_str()
+            .ok_or_else(|| BinanceError("Response has no listenKey".to_string()))?;
+        Ok(key.to_string())
+    }
+
+    fn spawn_keepalive(&self, api_key: &str, listen_key: &str) {
+        let url = format!("{}/api/v3/userDataStream", self.rest_url);
+        let api_key = api_key.to_string();
+        let listen_key = listen_key.to_string();
+        let logger = self.logger.clone();
+        let http = Arc::clone(&self.http);
+
+        let handle = tokio::spawn(async move {
+            loop {
+                tokio::time::sleep(KEEPALIVE_PERIOD).await;
+                let request = http
+                    .put(&url)
+                    .header("X-MBX-APIKEY", &api_key)
+                    .query(&[("listenKey", &listen_key)]);
+                let res = http.send_once(&Exchange::Binance, 2, request).await;
+                match res {
+                    Ok(_) => logger.debug("listenKey keepalive sent"),
+                    Err(err) => logger.error(&format!("listenKey keepalive failed: {}", err)),
+                }
+            }
+        });
+
+        if let Some(prev) = self.keepalive.lock().unwrap().replace(handle) {
+            prev.abort();
+        }
+    }
+
+    fn handle_execution(&self, data: Value, buffer: &StreamBuffer) -> Result<(), Error> {
+        self.logger.debug("Handle execution report");
+
+        let report: ExecutionReport = model_from_serde_value(data)?;
+        let symbol = report.symbol.to_lowercase();
+        let Ok(config) = self.configs.get_by_symbol(&symbol) else {
+            self.logger.debug(&format!("Skip execution for untracked symbol {}", symbol));
+            return Ok(());
+        };
+
+        let side = parse_side(&report.side)?;
+        let received = now_timestamp_ns();
+        let order_id = report.order_id.to_string();
+
+        buffer.push(Event::OrderUpdate(OrderUpdate {
+            exchange: self.exchange.clone(),
+            ticker: Arc::clone(&config.ticker),
+            order_id: order_id.clone(),
+            client_order_id: Some(report.client_order_id.clone()),
+            side,
+            status: parse_status(&report.status)?,
+            price: (parse_number(&report.price)? * config.price_multiply) as Price,
+            quantity: (parse_number(&report.quantity)? * config.quantity_multiply) as Quantity,
+            filled_quantity: (parse_number(&report.cumulative_quantity)? * config.quantity_multiply)
+                as Quantity,
+            timestamp: report.transaction_time,
+            received,
+        }));
+
+        if report.execution_type == "TRADE" {
+            buffer.push(Event::Fill(Fill {
+                exchange: self.exchange.clone(),
+                ticker: Arc::clone(&config.ticker),
+                order_id,
+                trade_id: report.trade_id.to_string(),
+                side,
+                price: (parse_number(&report.last_price)? * config.price_multiply) as Price,
+                quantity: (parse_number(&report.last_quantity)? * config.quantity_multiply)
+                    as Quantity,
+                fee: parse_number(&report.commission)?,
+                fee_asset: report.commission_asset.unwrap_or_default(),
+                is_maker: report.is_maker,
+                timestamp: report.transaction_time,
+                received,
+            }));
+        }
+
+        Ok(())
+    }
+
+    fn handle_account(&self, data: Value, buffer: &StreamBuffer) -> Result<(), Error> {
+        self.logger.debug("Handle account position");
+
+        let position: AccountPosition = model_from_serde_value(data)?;
+        let received = now_timestamp_ns();
+        for balance in position.balances {
+            buffer.push(Event::BalanceUpdate(BalanceUpdate {
+                exchange: self.exchange.clone(),
+                asset: balance.asset,
+                free: parse_number(&balance.free)?,
+                locked: parse_number(&balance.locked)?,
+                timestamp: position.update_time,
+                received,
+            }));
+        }
+        Ok(())
+    }
+}
+
+impl Drop for BinanceUserConnector {
+    fn drop(&mut self) {
+        if let Some(handle) = self.keepalive.lock().unwrap().take() {
+            handle.abort();
+        }
+    }
+}
+
+impl ConnectorInternal for BinanceUserConnector {
+    async fn connect(&self) -> Result<Connection, Error> {
+        self.logger.info("Connecting...");
+
+        let credentials = self
+            .credentials
+            .as_ref()
+            .ok_or_else(|| BuilderError("Binance user stream requires an API key".to_string()))?;
+
+        let listen_key = self.create_listen_key(&credentials.api_key).await?;
+        let url = format!("{}/ws/{}", self.ws_url, listen_key);
+        let connection = connect_websocket(&url, &self.network, &self.logger).await?;
+        self.spawn_keepalive(&credentials.api_key, &listen_key);
+
+        Ok(connection)
+    }
+
+    fn on_message(&self, msg: &str, buffer: &StreamBuffer) -> Result<(), Error> {
+        let obj = parse_serde_object(msg)?;
+        let event_type = obj.get("e").and_then(|e| e.as_str()).unwrap_or_default();
+
+        match event_type {
+            "executionReport" => self.handle_execution(Value::Object(obj), buffer)?,
+            "outboundAccountPosition" => self.handle_account(Value::Object(obj), buffer)?,
+            "listenKeyExpired" => Err(BinanceError("listenKey expired".to_string()))?,
+            _ => self
+                .logger
+                .debug(&format!("Skip user data event {}", event_type)),
+        };
+        Ok(())
+    }
+
+    fn on_error(&self, err: &Error) {
+        self.logger.error(&err.to_string());
+        for handler in self.error_handlers.iter() {
+            handler(err)
+        }
+    }
+
+    fn logger(&self) -> &Logger {
+        &self.logger
+    }
+
+    fn exchange(&self) -> &Exchange {
+        &self.exchange
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::connector::config::TickerConfig;
+    use crate::connector::services::mock_server::{mock_http_server, mock_ws_server};
+    use crate::connector::Connector;
+    use crate::shared::InstrumentKind;
+    use futures_util::StreamExt;
+    use tracing::Level;
+
+    fn connector(rest_url: &str, ws_url: &str) -> BinanceUserConnector {
+        let config = ConnectorConfig {
+            ticker_configs: vec![TickerConfig {
+                ticker: Arc::new("btc/usdt".to_string()),
+                instrument: InstrumentKind::Spot,
+                price_multiply: 100.0,
+                quantity_multiply: 1000.0,
+                subscribe_trades: false,
+                subscribe_depth: false,
+                subscribe_bbo: false,
+                candle_interval: None,
+                level3_depth: None,
+                depth_value: 0,
+            }],
+            error_handlers: vec![],
+            log_level: Level::ERROR,
+            kraken_token: None,
+            credentials: Some(ApiCredentials {
+                api_key: "key".to_string(),
+                api_secret: "secret".to_string(),
+            }),
+            network: NetworkConfig::default(),
+            http: Arc::new(HttpClient::new()),
+        };
+        BinanceUserConnector::new(config).with_urls(rest_url, ws_url)
+    }
+
+    const EXECUTION: &str = r#"{"e":"executionReport","E":1700000000001,"s":"BTCUSDT","c":"my-1","S":"BUY",
+        "o":"LIMIT","q":"0.500","p":"100.50","x":"TRADE","X":"PARTIALLY_FILLED","i":42,"l":"0.200",
+        "z":"0.200","L":"100.50","n":"0.0002","N":"BTC","T":1700000000000,"t":7,"m":true}"#;
+
+    const ACCOUNT: &str = r#"{"e":"outboundAccountPosition","E":1700000000002,"u":1700000000002,
+        "B":[{"a":"BTC","f":"1.5","l":"0.25"},{"a":"USDT","f":"100.0","l":"0.0"}]}"#;
+
+    #[tokio::test]
+    async fn test_user_stream() {
+        let (rest_url, rest) = mock_http_server(vec![(200, r#"{"listenKey":"abc"}"#.to_string())]).await;
+        let (ws_url, ws) = mock_ws_server(0, vec![EXECUTION.to_string(), ACCOUNT.to_string()]).await;
+
+        let stream = connector(&rest_url, &ws_url).stream().await.unwrap();
+        let events: Vec<Event> = stream.collect().await;
+
+        let requests = rest.await.unwrap();
+        assert!(requests[0].starts_with("POST /api/v3/userDataStream"));
+        assert!(requests[0].to_lowercase().contains("x-mbx-apikey: key"));
+        ws.await.unwrap();
+
+        assert_eq!(events.len(), 4);
+        match &events[0] {
+            Event::OrderUpdate(u) => {
+                assert_eq!(u.order_id, "42");
+                assert_eq!(u.client_order_id.as_deref(), Some("my-1"));
+                assert_eq!(u.status, OrderStatus::PartiallyFilled);
+                assert_eq!((u.price, u.quantity, u.filled_quantity), (10050, 500, 200));
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+        match &events[1] {
+            Event::Fill(f) => {
+                assert_eq!((f.price, f.quantity, f.is_maker), (10050, 200, true));
+                assert_eq!(f.fee_asset, "BTC");
+                assert_eq!(f.trade_id, "7");
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+        match &events[2] {
+            Event::BalanceUpdate(b) => assert_eq!((b.asset.as_str(), b.free, b.locked), ("BTC", 1.5, 0.25)),
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_rejected_api_key() {
+        let (rest_url, _rest) = mock_http_server(vec![(
+            401,
+            r#"{"code":-2014,"msg":"API-key format invalid."}"#.to_string(),
+        )])
+        .await;
+
+        let res = connector(&rest_url, "ws://127.0.0.1:1").stream().await;
+        assert!(matches!(res, Err(Error::ExchangeError(BinanceError(_)))));
+    }
+}
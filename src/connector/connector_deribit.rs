@@ -0,0 +1,563 @@
+use crate::connector::config::{ConnectorConfig, TickerConfig, NetworkConfig};
+use crate::connector::connector::{ConnectorInternal, StreamBuffer};
+use crate::connector::errors::ExchangeError::DeribitError;
+use crate::connector::errors::ParsingError::{ConvertingError, MessageParsingError, UnknownMessage};
+use crate::connector::errors::{Error, ErrorHandler};
+use crate::connector::services::known_levels::KnownLevels;
+use crate::connector::services::parser::{model_from_serde_value, parse_serde_object};
+use crate::connector::services::ticker_map::TickerMap;
+use crate::connector::services::websocket::{connect_websocket, send_ws_message, Connection};
+use crate::connector::Event;
+use crate::derivatives::{DerivativeTicker, OptionGreeks};
+use crate::level2::LevelUpdated;
+use crate::shared::logger::Logger;
+use crate::shared::utils::now_timestamp_ns;
+use crate::shared::{Exchange, Price, Quantity, Side};
+use crate::trade::TradeEvent;
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio_tungstenite::tungstenite::Message;
+
+#[derive(Debug, Deserialize)]
+struct DeribitBook {
+    /// `snapshot` after subscribing, `change` afterwards
+    #[serde(rename = "type")]
+    kind: String,
+    timestamp: u64,
+    instrument_name: String,
+    change_id: u64,
+    /// Missing on the initial snapshot
+    prev_change_id: Option<u64>,
+    bids: Vec<(String, f64, f64)>, // Action, Price, Amount
+    asks: Vec<(String, f64, f64)>, // Action, Price, Amount
+}
+
+#[derive(Debug, Deserialize)]
+struct DeribitTrade {
+    trade_seq: u64,
+    timestamp: u64,
+    instrument_name: String,
+    price: f64,
+    amount: f64,
+    direction: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct DeribitGreeks {
+    delta: f64,
+    gamma: f64,
+    vega: f64,
+    theta: f64,
+    rho: f64,
+}
+
+#[derive(Debug, Deserialize)]
+struct DeribitTicker {
+    timestamp: u64,
+    instrument_name: String,
+    mark_price: f64,
+    index_price: f64,
+    #[serde(default)]
+    best_bid_price: Option<f64>,
+    #[serde(default)]
+    best_bid_amount: Option<f64>,
+    #[serde(default)]
+    best_ask_price: Option<f64>,
+    #[serde(default)]
+    best_ask_amount: Option<f64>,
+    #[serde(default)]
+    open_interest: f64,
+    #[serde(default)]
+    underlying_price: Option<f64>,
+    #[serde(default)]
+    mark_iv: Option<f64>,
+    #[serde(default)]
+    bid_iv: Option<f64>,
+    #[serde(default)]
+    ask_iv: Option<f64>,
+    #[serde(default)]
+    greeks: Option<DeribitGreeks>,
+}
+
+fn convert_ticker_into_deribit_symbol(raw: &str) -> String {
+    // btc/usdc -> BTC_USDC, btc-27dec24-60000-c -> BTC-27DEC24-60000-C
+    raw.to_uppercase().replace('/', "_")
+}
+
+fn build_channels(config: &TickerConfig, symbol: &str) -> Vec<String> {
+    let mut channels = Vec::new();
+    if config.subscribe_depth {
+        channels.push(format!("book.{symbol}.100ms"));
+    }
+    if config.subscribe_trades {
+        channels.push(format!("trades.{symbol}.100ms"));
+    }
+    // Greeks and implied volatility come only with the ticker channel
+    if config.instrument.is_option() {
+        channels.push(format!("ticker.{symbol}.100ms"));
+    }
+    channels
+}
+
+pub struct DeribitConnector {
+    configs: TickerMap,
+    exchange: Exchange,
+    logger: Logger,
+    error_handlers: Vec<ErrorHandler>,
+    network: NetworkConfig,
+    levels: Arc<KnownLevels>,
+    url: String,
+}
+
+impl DeribitConnector {
+    pub fn new(config: ConnectorConfig) -> Self {
+        Self {
+            configs: TickerMap::from_configs(
+                config.ticker_configs,
+                convert_ticker_into_deribit_symbol,
+            ),
+            exchange: Exchange::Deribit,
+            logger: Logger::new("deribit", config.log_level),
+            error_handlers: config.error_handlers,
+            network: config.network.clone(),
+            levels: KnownLevels::shared(&Exchange::Deribit),
+            url: "wss://www.deribit.com/ws/api/v2".to_string(),
+        }
+    }
+
+    #[cfg(test)]
+    pub fn with_url(mut self, url: &str) -> Self {
+        self.url = url.to_string();
+        self
+    }
+
+    #[cfg(test)]
+    pub fn with_levels(mut self, levels: Arc<KnownLevels>) -> Self {
+        self.levels = levels;
+        self
+    }
+
+    fn handle_depth(&self, data: &Value, result: &StreamBuffer) -> Result<(), Error> {
+        self.logger.debug("Handle book message");
+
+        let book: DeribitBook = model_from_serde_value(data.clone())?;
+        let config = self.configs.get_by_symbol(&book.instrument_name)?;
+
+        let mut updates: Vec<(Side, Price, Quantity)> = Vec::new();
+        let sides = [(Side::Buy, &book.bids), (Side::Sell, &book.asks)];
+        for (side, levels) in sides {
+            for (action, price, amount) in levels.iter() {
+                let quantity = match action.as_str() {
+                    "new" | "change" => amount * config.quantity_multiply,
+                    "delete" => 0.0,
+                    other => Err(ConvertingError(format!("Unexpected book action {}", other)))?,
+                };
+                updates.push((side, (price * config.price_multiply) as Price, quantity as Quantity));
+            }
+        }
+
+        // A snapshot replaces the book: levels known from before it, e.g. from the previous
+        // connection, are deleted unless it lists them again
+        if book.kind == "snapshot" {
+            let (mut bids, mut asks) = (HashMap::new(), HashMap::new());
+            for (side, price, quantity) in updates {
+                match side {
+                    Side::Buy => bids.insert(price, quantity),
+                    Side::Sell => asks.insert(price, quantity),
+                };
+            }
+            updates = self.levels.replace(&book.instrument_name, bids, asks)?;
+        } else {
+            for &(side, price, quantity) in updates.iter() {
+                self.levels.apply(&book.instrument_name, side, price, quantity)?;
+            }
+        }
+
+        for (side, price, quantity) in updates {
+            let event = LevelUpdated {
+                exchange: self.exchange.clone(),
+                ticker: Arc::clone(&config.ticker),
+                side,
+                price,
+                quantity,
+                timestamp: book.timestamp,
+                received: now_timestamp_ns(),
+                sequence: book.change_id,
+                prev_sequence: book.prev_change_id.unwrap_or(0),
+            };
+            result.push(Event::LevelUpdate(event));
+        }
+
+        Ok(())
+    }
+
+    fn handle_trade(&self, data: &Value, result: &StreamBuffer) -> Result<(), Error> {
+        self.logger.debug("Handle trade message");
+
+        let data = data
+            .as_array()
+            .ok_or_else(|| MessageParsingError("trades: data is not an array".into()))?;
+
+        for item in data {
+            let tr: DeribitTrade = model_from_serde_value(item.clone())?;
+            let config = self.configs.get_by_symbol(&tr.instrument_name)?;
+
+            // Deribit reports the taker direction, the maker is on the other side
+            let market_maker = match tr.direction.as_str() {
+                "buy" => Side::Sell,
+                "sell" => Side::Buy,
+                _ => Err(ConvertingError(format!("Unexpected direction {}", tr.direction)))?,
+            };
+
+            let event = TradeEvent {
+                exchange: self.exchange.clone(),
+                ticker: Arc::clone(&config.ticker),
+                trade_id: tr.trade_seq,
+                price: (tr.price * config.price_multiply) as Price,
+                quantity: (tr.amount * config.quantity_multiply) as Quantity,
+                timestamp: tr.timestamp,
+                market_maker,
+                received: now_timestamp_ns(),
+            };
+            result.push(Event::Trade(event));
+        }
+
+        Ok(())
+    }
+
+    fn handle_ticker(&self, data: &Value, result: &StreamBuffer) -> Result<(), Error> {
+        self.logger.debug("Handle ticker message");
+
+        let t: DeribitTicker = model_from_serde_value(data.clone())?;
+        let config = self.configs.get_by_symbol(&t.instrument_name)?;
+        let price = |v: f64| (v * config.price_multiply) as Price;
+        let quantity = |v: f64| (v * config.quantity_multiply) as Quantity;
+
+        let event = DerivativeTicker {
+            exchange: self.exchange.clone(),
+            ticker: Arc::clone(&config.ticker),
+            mark_price: price(t.mark_price),
+            index_price: price(t.index_price),
+            best_bid_price: price(t.best_bid_price.unwrap_or_default()),
+            best_bid_quantity: quantity(t.best_bid_amount.unwrap_or_default()),
+            best_ask_price: price(t.best_ask_price.unwrap_or_default()),
+            best_ask_quantity: quantity(t.best_ask_amount.unwrap_or_default()),
+            open_interest: t.open_interest,
+            underlying_price: t.underlying_price.map(price),
+            mark_iv: t.mark_iv,
+            bid_iv: t.bid_iv,
+            ask_iv: t.ask_iv,
+            greeks: t.greeks.map(|g| OptionGreeks {
+                delta: g.delta,
+                gamma: g.gamma,
+                vega: g.vega,
+                theta: g.theta,
+                rho: g.rho,
+            }),
+            timestamp: t.timestamp,
+            received: now_timestamp_ns(),
+        };
+        result.push(Event::DerivativeTicker(event));
+
+        Ok(())
+    }
+}
+
+impl ConnectorInternal for DeribitConnector {
+    async fn connect(&self) -> Result<Connection, Error> {
+        self.logger.info("Connecting...");
+
+        let (mut write, read) = connect_websocket(&self.url, &self.network, &self.logger).await?;
+
+        let mut channels = Vec::new();
+        for ticker_config in self.configs.get_all_configs() {
+            let symbol = self.configs.get_symbol_from_ticker(&ticker_config.ticker);
+            channels.extend(build_channels(ticker_config, &symbol));
+        }
+
+        if channels.is_empty() {
+            Err(DeribitError(
+                "No channels configured. Enable subscribe_trades/subscribe_depth and provide tickers"
+                    .to_string(),
+            ))?;
+        }
+
+        let sub = serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "public/subscribe",
+            "params": {
+                "channels": channels
+            }
+        });
+        send_ws_message(&mut write, Message::Text(sub.to_string())).await?;
+        self.logger.info(&format!("Sent subscribe for {}", channels.join(", ")));
+
+        Ok((write, read))
+    }
+
+    fn on_message(&self, msg: &str, buffer: &StreamBuffer) -> Result<(), Error> {
+        let obj = parse_serde_object(msg)?;
+
+        if let Some(error) = obj.get("error") {
+            Err(DeribitError(error.to_string()))?;
+        }
+
+        // Responses to our own requests
+        if obj.contains_key("result") {
+            return Ok(());
+        }
+
+        let method = obj.get("method").and_then(|m| m.as_str()).unwrap_or_default();
+        if method != "subscription" {
+            Err(UnknownMessage(format!("Unexpected method {}", method)))?;
+        }
+
+        let params = obj
+            .get("params")
+            .ok_or_else(|| MessageParsingError("subscription: missing params".into()))?;
+        let channel = params
+            .get("channel")
+            .and_then(|c| c.as_str())
+            .ok_or_else(|| DeribitError("Deribit channel is null".to_string()))?;
+        let data = params
+            .get("data")
+            .ok_or_else(|| MessageParsingError("subscription: missing data".into()))?;
+
+        match channel.split('.').next().unwrap_or_default() {
+            "book" => self.handle_depth(data, buffer)?,
+            "trades" => self.handle_trade(data, buffer)?,
+            "ticker" => self.handle_ticker(data, buffer)?,
+            _ => Err(UnknownMessage(format!("Unexpected channel {}", channel)))?,
+        };
+        Ok(())
+    }
+
+    fn on_error(&self, err: &Error) {
+        self.logger.error(&err.to_string());
+        for handler in self.error_handlers.iter() {
+            handler(err)
+        }
+    }
+
+    fn logger(&self) -> &Logger {
+        &self.logger
+    }
+
+    fn exchange(&self) -> &Exchange {
+        &self.exchange
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::connector::services::http::HttpClient;
+    use crate::connector::services::mock_server::mock_ws_server;
+    use crate::connector::Connector;
+    use crate::shared::InstrumentKind;
+    use futures_util::StreamExt;
+    use tracing::Level;
+
+    fn connector(ticker: &str) -> DeribitConnector {
+        let config = ConnectorConfig {
+            ticker_configs: vec![TickerConfig {
+                ticker: Arc::new(ticker.to_string()),
+                instrument: InstrumentKind::from_ticker(ticker).unwrap(),
+                price_multiply: 10_000.0,
+                quantity_multiply: 10.0,
+                subscribe_trades: true,
+                subscribe_depth: true,
+                subscribe_bbo: false,
+                candle_interval: None,
+                level3_depth: None,
+                depth_value: 10,
+            }],
+            error_handlers: vec![],
+            log_level: Level::ERROR,
+            kraken_token: None,
+            credentials: None,
+            network: NetworkConfig::default(),
+            http: Arc::new(HttpClient::new()),
+        };
+        DeribitConnector::new(config).with_levels(Arc::new(KnownLevels::default()))
+    }
+
+    fn book(kind: &str, change_id: u64, bids: &[(&str, f64, f64)], asks: &[(&str, f64, f64)]) -> String {
+        serde_json::json!({
+            "jsonrpc": "2.0",
+            "method": "subscription",
+            "params": {
+                "channel": "book.BTC-PERPETUAL.100ms",
+                "data": {
+                    "type": kind, "timestamp": 1700000000000u64, "instrument_name": "BTC-PERPETUAL",
+                    "change_id": change_id, "prev_change_id": change_id - 1, "bids": bids, "asks": asks
+                }
+            }
+        })
+        .to_string()
+    }
+
+    fn levels(events: &[Event]) -> Vec<(Side, Price, Quantity)> {
+        events
+            .iter()
+            .filter_map(|ev| match ev {
+                Event::LevelUpdate(v) => Some((v.side, v.price, v.quantity)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn drain(buffer: &StreamBuffer) -> Vec<Event> {
+        let mut result = vec![];
+        while let Some(ev) = buffer.pop() {
+            result.push(ev);
+        }
+        result
+    }
+
+    #[test]
+    fn test_channels_for_option() {
+        let c = connector("btc-27dec24-60000-c");
+        let config = &c.configs.get_all_configs()[0];
+        let channels = build_channels(config, "BTC-27DEC24-60000-C");
+        assert_eq!(
+            channels,
+            vec![
+                "book.BTC-27DEC24-60000-C.100ms",
+                "trades.BTC-27DEC24-60000-C.100ms",
+                "ticker.BTC-27DEC24-60000-C.100ms",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_book_change() {
+        let c = connector("btc-perpetual");
+        let buffer = StreamBuffer::new();
+        let msg = r#"{"jsonrpc":"2.0","method":"subscription","params":{
+            "channel":"book.BTC-PERPETUAL.100ms",
+            "data":{"type":"change","timestamp":1700000000000,"instrument_name":"BTC-PERPETUAL",
+                    "change_id":2,"prev_change_id":1,
+                    "bids":[["new",60000.5,3.0]],"asks":[["delete",60001.0,0.0]]}}}"#;
+        c.on_message(msg, &buffer).unwrap();
+
+        let events = drain(&buffer);
+        assert_eq!(events.len(), 2);
+        match &events[0] {
+            Event::LevelUpdate(ev) => {
+                assert_eq!(ev.side, Side::Buy);
+                assert_eq!(ev.price, 600_005_000);
+                assert_eq!(ev.quantity, 30);
+                assert_eq!(ev.timestamp, 1700000000000);
+            }
+            other => panic!("unexpected event {:?}", other),
+        }
+        match &events[1] {
+            Event::LevelUpdate(ev) => {
+                assert_eq!(ev.side, Side::Sell);
+                assert_eq!(ev.quantity, 0);
+            }
+            other => panic!("unexpected event {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_subscribes_and_streams_book() {
+        let responses = vec![
+            r#"{"jsonrpc":"2.0","id":1,"result":["book.BTC-PERPETUAL.100ms","trades.BTC-PERPETUAL.100ms"]}"#.to_string(),
+            book("snapshot", 1, &[("new", 60000.0, 1.0)], &[("new", 60001.0, 2.0)]),
+            book("change", 2, &[("change", 60000.0, 3.0)], &[("delete", 60001.0, 0.0)]),
+        ];
+        let (url, server) = mock_ws_server(1, responses).await;
+
+        let stream = connector("btc-perpetual").with_url(&url).stream().await.unwrap();
+        let events: Vec<Event> = stream.collect().await;
+
+        let requests = server.await.unwrap();
+        assert!(requests[0].contains(r#""method":"public/subscribe""#));
+        assert!(requests[0].contains("book.BTC-PERPETUAL.100ms"));
+        assert!(requests[0].contains("trades.BTC-PERPETUAL.100ms"));
+        assert_eq!(
+            levels(&events),
+            vec![
+                (Side::Buy, 600_000_000, 10),
+                (Side::Sell, 600_010_000, 20),
+                (Side::Buy, 600_000_000, 30),
+                (Side::Sell, 600_010_000, 0),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_snapshot_after_reconnect_deletes_vanished_levels() {
+        let levels_store = Arc::new(KnownLevels::default());
+        let buffer = StreamBuffer::new();
+        let first = connector("btc-perpetual").with_levels(Arc::clone(&levels_store));
+        first.on_message(&book("snapshot", 1, &[("new", 60000.0, 1.0)], &[("new", 60002.0, 1.0)]), &buffer).unwrap();
+        first.on_message(&book("change", 2, &[("new", 59999.0, 1.0)], &[]), &buffer).unwrap();
+        drain(&buffer);
+
+        // The next connection starts with a new snapshot that no longer lists 60000 and 59999
+        let second = connector("btc-perpetual").with_levels(levels_store);
+        second.on_message(&book("snapshot", 10, &[("new", 59998.0, 1.0)], &[("new", 60002.0, 1.0)]), &buffer).unwrap();
+        assert_eq!(
+            levels(&drain(&buffer)),
+            vec![(Side::Buy, 599_980_000, 10), (Side::Buy, 599_990_000, 0), (Side::Buy, 600_000_000, 0)]
+        );
+    }
+
+    #[test]
+    fn test_trade_maker_side() {
+        let c = connector("btc-perpetual");
+        let buffer = StreamBuffer::new();
+        let msg = r#"{"jsonrpc":"2.0","method":"subscription","params":{
+            "channel":"trades.BTC-PERPETUAL.100ms",
+            "data":[{"trade_seq":1,"trade_id":"1","timestamp":1700000000000,"tick_direction":0,
+                     "price":60000.0,"mark_price":60000.0,"instrument_name":"BTC-PERPETUAL",
+                     "index_price":60000.0,"direction":"buy","amount":10.0}]}}"#;
+        c.on_message(msg, &buffer).unwrap();
+
+        match drain(&buffer).as_slice() {
+            [Event::Trade(tr)] => {
+                assert_eq!(tr.market_maker, Side::Sell);
+                assert_eq!(tr.quantity, 100);
+            }
+            other => panic!("unexpected events {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_option_ticker_with_greeks() {
+        let c = connector("btc-27dec24-60000-c");
+        let buffer = StreamBuffer::new();
+        let msg = r#"{"jsonrpc":"2.0","method":"subscription","params":{
+            "channel":"ticker.BTC-27DEC24-60000-C.100ms",
+            "data":{"timestamp":1700000000000,"instrument_name":"BTC-27DEC24-60000-C",
+                    "mark_price":0.0125,"index_price":60000.0,"underlying_price":60100.0,
+                    "best_bid_price":0.012,"best_bid_amount":5.0,"best_ask_price":0.013,"best_ask_amount":2.0,
+                    "open_interest":120.5,"mark_iv":55.1,"bid_iv":54.0,"ask_iv":56.2,
+                    "greeks":{"delta":0.52,"gamma":0.0001,"vega":60.1,"theta":-40.2,"rho":10.3}}}}"#;
+        c.on_message(msg, &buffer).unwrap();
+
+        match drain(&buffer).as_slice() {
+            [Event::DerivativeTicker(t)] => {
+                assert_eq!(t.mark_price, 125);
+                assert_eq!(t.underlying_price, Some(601_000_000));
+                assert_eq!(t.mark_iv, Some(55.1));
+                assert!((t.greeks.as_ref().unwrap().delta - 0.52).abs() < 1e-9);
+            }
+            other => panic!("unexpected events {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_error_response() {
+        let c = connector("btc-perpetual");
+        let buffer = StreamBuffer::new();
+        let msg = r#"{"jsonrpc":"2.0","id":1,"error":{"code":-32602,"message":"Invalid params"}}"#;
+        assert!(c.on_message(msg, &buffer).is_err());
+    }
+}
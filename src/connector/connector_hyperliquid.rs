@@ -0,0 +1,422 @@
+use crate::connector::config::{ConnectorConfig, NetworkConfig};
+use crate::connector::connector::{ConnectorInternal, StreamBuffer};
+use crate::connector::errors::ExchangeError::HyperliquidError;
+use crate::connector::errors::ParsingError::{ConvertingError, MessageParsingError, UnknownMessage};
+use crate::connector::errors::{Error, ErrorHandler};
+use crate::connector::services::known_levels::KnownLevels;
+use crate::connector::services::parser::{model_from_serde_value, parse_number, parse_serde_object};
+use crate::connector::services::ticker_map::TickerMap;
+use crate::connector::services::websocket::{connect_websocket, send_ws_message, Connection};
+use crate::connector::Event;
+use crate::level2::LevelUpdated;
+use crate::shared::logger::Logger;
+use crate::shared::utils::now_timestamp_ns;
+use crate::shared::{Exchange, Price, Quantity, Side, TimestampMS};
+use crate::trade::TradeEvent;
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio_tungstenite::tungstenite::Message;
+
+#[derive(Debug, Deserialize)]
+struct HyperliquidLevel {
+    px: String,
+    sz: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct HyperliquidBook {
+    coin: String,
+    time: u64,
+    levels: (Vec<HyperliquidLevel>, Vec<HyperliquidLevel>), // Bids, Asks
+}
+
+#[derive(Debug, Deserialize)]
+struct HyperliquidTrade {
+    coin: String,
+    side: String,
+    px: String,
+    sz: String,
+    time: u64,
+    tid: u64,
+}
+
+// Coins that Hyperliquid lists per 1000 units
+static THOUSAND_COINS: [&str; 7] = ["PEPE", "SHIB", "BONK", "FLOKI", "LUNC", "DOGS", "NEIRO"];
+
+fn convert_ticker_into_hyperliquid_coin(raw: &str) -> String {
+    // btc/usdt, btc/usdc, btc-perpetual -> BTC. Perps are always quoted in USD
+    let base = raw
+        .split(['/', '-'])
+        .next()
+        .unwrap_or_default()
+        .to_uppercase();
+    if THOUSAND_COINS.contains(&base.as_str()) {
+        return format!("k{}", base);
+    }
+    base
+}
+
+pub struct HyperliquidConnector {
+    configs: TickerMap,
+    exchange: Exchange,
+    logger: Logger,
+    error_handlers: Vec<ErrorHandler>,
+    network: NetworkConfig,
+    levels: Arc<KnownLevels>,
+    url: String,
+}
+
+impl HyperliquidConnector {
+    pub fn new(config: ConnectorConfig) -> Self {
+        Self {
+            configs: TickerMap::from_configs(
+                config.ticker_configs,
+                convert_ticker_into_hyperliquid_coin,
+            ),
+            exchange: Exchange::Hyperliquid,
+            logger: Logger::new("hyperliquid", config.log_level),
+            error_handlers: config.error_handlers,
+            network: config.network.clone(),
+            levels: KnownLevels::shared(&Exchange::Hyperliquid),
+            url: "wss://api.hyperliquid.xyz/ws".to_string(),
+        }
+    }
+
+    #[cfg(test)]
+    pub fn with_url(mut self, url: &str) -> Self {
+        self.url = url.to_string();
+        self
+    }
+
+    #[cfg(test)]
+    pub fn with_levels(mut self, levels: Arc<KnownLevels>) -> Self {
+        self.levels = levels;
+        self
+    }
+
+    fn parse_levels(
+        &self,
+        levels: &[HyperliquidLevel],
+        price_multiply: f64,
+        quantity_multiply: f64,
+    ) -> Result<HashMap<Price, Quantity>, Error> {
+        let mut result = HashMap::with_capacity(levels.len());
+        for level in levels {
+            let price = parse_number(&level.px)? * price_multiply;
+            let qty = parse_number(&level.sz)? * quantity_multiply;
+            result.insert(price as Price, qty as Quantity);
+        }
+        Ok(result)
+    }
+
+    fn handle_depth(&self, data: &Value, result: &StreamBuffer) -> Result<(), Error> {
+        self.logger.debug("Handle l2Book message");
+
+        let book: HyperliquidBook = model_from_serde_value(data.clone())?;
+        let config = self.configs.get_by_symbol(&book.coin)?;
+
+        let bids = self.parse_levels(&book.levels.0, config.price_multiply, config.quantity_multiply)?;
+        let asks = self.parse_levels(&book.levels.1, config.price_multiply, config.quantity_multiply)?;
+
+        // Every l2Book message is a full book, only the difference to the levels already sent
+        // goes out. The store outlives the connection, so levels that vanished while
+        // disconnected are deleted by the first book after a reconnect
+        for (side, price, quantity) in self.levels.replace(&book.coin, bids, asks)? {
+            let event = LevelUpdated {
+                exchange: self.exchange.clone(),
+                ticker: Arc::clone(&config.ticker),
+                side,
+                price,
+                quantity,
+                timestamp: book.time,
+                received: now_timestamp_ns(),
+                sequence: 0,
+                prev_sequence: 0,
+            };
+            result.push(Event::LevelUpdate(event));
+        }
+        Ok(())
+    }
+
+    fn handle_trade(&self, data: &Value, result: &StreamBuffer) -> Result<(), Error> {
+        self.logger.debug("Handle trade message");
+
+        let data = data
+            .as_array()
+            .ok_or_else(|| MessageParsingError("trades: data is not an array".into()))?;
+
+        for item in data {
+            let tr: HyperliquidTrade = model_from_serde_value(item.clone())?;
+            let config = self.configs.get_by_symbol(&tr.coin)?;
+
+            // B - aggressive buy, A - aggressive sell. The maker is on the other side
+            let market_maker = match tr.side.as_str() {
+                "B" => Side::Sell,
+                "A" => Side::Buy,
+                _ => Err(ConvertingError(format!("Unexpected side {}", tr.side)))?,
+            };
+
+            let event = TradeEvent {
+                exchange: self.exchange.clone(),
+                ticker: Arc::clone(&config.ticker),
+                trade_id: tr.tid,
+                price: (parse_number(&tr.px)? * config.price_multiply) as Price,
+                quantity: (parse_number(&tr.sz)? * config.quantity_multiply) as Quantity,
+                timestamp: tr.time,
+                market_maker,
+                received: now_timestamp_ns(),
+            };
+            result.push(Event::Trade(event));
+        }
+
+        Ok(())
+    }
+}
+
+impl ConnectorInternal for HyperliquidConnector {
+    async fn connect(&self) -> Result<Connection, Error> {
+        self.logger.info("Connecting...");
+
+        let (mut write, read) = connect_websocket(&self.url, &self.network, &self.logger).await?;
+
+        for ticker_config in self.configs.get_all_configs() {
+            let coin = self.configs.get_symbol_from_ticker(&ticker_config.ticker);
+
+            if ticker_config.subscribe_trades {
+                let sub_trade = serde_json::json!({
+                    "method": "subscribe",
+                    "subscription": { "type": "trades", "coin": coin }
+                });
+                send_ws_message(&mut write, Message::Text(sub_trade.to_string())).await?;
+                self.logger.info(&format!("Sent trades subscribe for {}", coin));
+            }
+
+            if ticker_config.subscribe_depth {
+                let sub_book = serde_json::json!({
+                    "method": "subscribe",
+                    "subscription": { "type": "l2Book", "coin": coin }
+                });
+                send_ws_message(&mut write, Message::Text(sub_book.to_string())).await?;
+                self.logger.info(&format!("Sent l2Book subscribe for {}", coin));
+            }
+        }
+
+        Ok((write, read))
+    }
+
+    fn on_message(&self, msg: &str, buffer: &StreamBuffer) -> Result<(), Error> {
+        let obj = parse_serde_object(msg)?;
+
+        let channel = obj
+            .get("channel")
+            .and_then(|c| c.as_str())
+            .ok_or_else(|| HyperliquidError("Hyperliquid channel is null".to_string()))?;
+        let data = obj.get("data").unwrap_or(&Value::Null);
+
+        match channel {
+            "l2Book" => self.handle_depth(data, buffer)?,
+            "trades" => self.handle_trade(data, buffer)?,
+            "error" => Err(HyperliquidError(data.to_string()))?,
+            "subscriptionResponse" => {}
+            "pong" => {}
+            _ => Err(UnknownMessage(format!("Unexpected channel {}", channel)))?,
+        };
+        Ok(())
+    }
+
+    fn on_error(&self, err: &Error) {
+        self.logger.error(&err.to_string());
+        for handler in self.error_handlers.iter() {
+            handler(err)
+        }
+    }
+
+    fn logger(&self) -> &Logger {
+        &self.logger
+    }
+
+    fn exchange(&self) -> &Exchange {
+        &self.exchange
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::connector::services::http::HttpClient;
+    use crate::connector::errors::ErrorClass;
+    use crate::connector::config::TickerConfig;
+    use crate::connector::services::mock_server::mock_ws_server;
+    use crate::connector::Connector;
+    use crate::shared::InstrumentKind;
+    use futures_util::StreamExt;
+    use tracing::Level;
+
+    fn connector(url: &str) -> HyperliquidConnector {
+        let config = ConnectorConfig {
+            ticker_configs: vec![TickerConfig {
+                ticker: Arc::new("btc/usdt".to_string()),
+                instrument: InstrumentKind::Spot,
+                price_multiply: 10.0,
+                quantity_multiply: 100.0,
+                subscribe_trades: true,
+                subscribe_depth: true,
+                subscribe_bbo: false,
+                candle_interval: None,
+                level3_depth: None,
+                depth_value: 10,
+            }],
+            error_handlers: vec![],
+            log_level: Level::ERROR,
+            kraken_token: None,
+            credentials: None,
+            network: NetworkConfig::default(),
+            http: Arc::new(HttpClient::new()),
+        };
+        HyperliquidConnector::new(config)
+            .with_url(url)
+            .with_levels(Arc::new(KnownLevels::default()))
+    }
+
+    fn book(time: u64, bids: &[(&str, &str)], asks: &[(&str, &str)]) -> String {
+        let levels = |xs: &[(&str, &str)]| {
+            xs.iter()
+                .map(|(px, sz)| serde_json::json!({"px": px, "sz": sz, "n": 1}))
+                .collect::<Vec<_>>()
+        };
+        serde_json::json!({
+            "channel": "l2Book",
+            "data": {"coin": "BTC", "time": time, "levels": [levels(bids), levels(asks)]}
+        })
+        .to_string()
+    }
+
+    fn levels(events: &[Event]) -> Vec<(Side, Price, Quantity)> {
+        events
+            .iter()
+            .filter_map(|ev| match ev {
+                Event::LevelUpdate(v) => Some((v.side, v.price, v.quantity)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_coin_mapping() {
+        assert_eq!(convert_ticker_into_hyperliquid_coin("btc/usdt"), "BTC");
+        assert_eq!(convert_ticker_into_hyperliquid_coin("eth-perpetual"), "ETH");
+        assert_eq!(convert_ticker_into_hyperliquid_coin("pepe/usdc"), "kPEPE");
+    }
+
+    #[tokio::test]
+    async fn test_subscribes_and_streams_snapshots_as_diffs() {
+        let responses = vec![
+            r#"{"channel":"subscriptionResponse","data":{"method":"subscribe","subscription":{"type":"l2Book","coin":"BTC"}}}"#.to_string(),
+            book(1, &[("100.0", "1.0"), ("99.0", "2.0")], &[("101.0", "1.5")]),
+            // 99.0 disappeared, 100.0 changed, 101.0 unchanged, 102.0 appeared
+            book(2, &[("100.0", "3.0")], &[("101.0", "1.5"), ("102.0", "0.5")]),
+        ];
+        let (url, server) = mock_ws_server(2, responses).await;
+
+        let stream = connector(&url).stream().await.unwrap();
+        let events: Vec<Event> = stream.collect().await;
+
+        let requests = server.await.unwrap();
+        assert!(requests[0].contains(r#""type":"trades""#));
+        assert!(requests[1].contains(r#""type":"l2Book""#));
+        assert!(requests.iter().all(|r| r.contains(r#""coin":"BTC""#)));
+
+        assert_eq!(
+            levels(&events),
+            vec![
+                // First snapshot
+                (Side::Buy, 990, 200),
+                (Side::Buy, 1000, 100),
+                (Side::Sell, 1010, 150),
+                // Second snapshot
+                (Side::Buy, 990, 0),
+                (Side::Buy, 1000, 300),
+                (Side::Sell, 1020, 50),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_book_after_reconnect_deletes_vanished_levels() {
+        let levels_store = Arc::new(KnownLevels::default());
+        let buffer = StreamBuffer::new();
+        let first = connector("ws://unused").with_levels(Arc::clone(&levels_store));
+        first.on_message(&book(1, &[("100.0", "1.0"), ("99.0", "2.0")], &[("101.0", "1.5")]), &buffer).unwrap();
+        while buffer.pop().is_some() {}
+
+        // A new connection's first book no longer has 99.0
+        let second = connector("ws://unused").with_levels(levels_store);
+        second.on_message(&book(2, &[("100.0", "1.0")], &[("101.0", "1.5")]), &buffer).unwrap();
+        let events: Vec<Event> = std::iter::from_fn(|| buffer.pop()).collect();
+        assert_eq!(levels(&events), vec![(Side::Buy, 990, 0)]);
+    }
+
+    #[tokio::test]
+    async fn test_dead_letters_for_bad_messages() {
+        let responses = vec![
+            "not json".to_string(),
+            r#"{"channel":"somethingNew","data":{}}"#.to_string(),
+        ];
+        let (url, server) = mock_ws_server(2, responses).await;
+
+        let stream = connector(&url).stream().await.unwrap();
+        let events: Vec<Event> = stream.collect().await;
+        server.await.unwrap();
+
+        let letters: Vec<_> = events
+            .into_iter()
+            .filter_map(|ev| match ev {
+                Event::DeadLetter(v) => Some(v),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(letters.len(), 2);
+        assert_eq!(letters[0].class, ErrorClass::Parsing);
+        assert_eq!(letters[0].raw, "not json");
+        assert_eq!(letters[1].class, ErrorClass::UnknownMessage);
+        assert!(letters[1].error.contains("somethingNew"));
+        assert_eq!(letters[0].exchange, Exchange::Hyperliquid);
+        assert_eq!(letters[0].connection_id, letters[1].connection_id);
+    }
+
+    #[tokio::test]
+    async fn test_streams_trades() {
+        let responses = vec![serde_json::json!({
+            "channel": "trades",
+            "data": [
+                {"coin": "BTC", "side": "B", "px": "100.5", "sz": "0.25", "time": 7, "hash": "0x0", "tid": 1},
+                {"coin": "BTC", "side": "A", "px": "100.0", "sz": "1", "time": 8, "hash": "0x1", "tid": 2}
+            ]
+        })
+        .to_string()];
+        let (url, server) = mock_ws_server(2, responses).await;
+
+        let stream = connector(&url).stream().await.unwrap();
+        let events: Vec<Event> = stream.collect().await;
+        server.await.unwrap();
+
+        let trades: Vec<(Price, Quantity, Side, TimestampMS)> = events
+            .iter()
+            .filter_map(|ev| match ev {
+                Event::Trade(t) => Some((t.price, t.quantity, t.market_maker, t.timestamp)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(trades, vec![(1005, 25, Side::Sell, 7), (1000, 100, Side::Buy, 8)]);
+    }
+
+    #[test]
+    fn test_error_channel() {
+        let c = connector("ws://unused");
+        let buffer = StreamBuffer::new();
+        let msg = r#"{"channel":"error","data":"Invalid subscription"}"#;
+        assert!(c.on_message(msg, &buffer).is_err());
+    }
+}
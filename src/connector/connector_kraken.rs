@@ -1,18 +1,25 @@
+use crate::bbo::BestBidAsk;
+use crate::candle::{Candle, CandleInterval};
+use crate::connector::errors::Error::InternalError;
 use crate::connector::errors::{Error, ErrorHandler};
 use crate::connector::Event;
 use crate::level2::LevelUpdated;
 use crate::shared::{Exchange, Price, Quantity, Side};
 use crate::trade::TradeEvent;
-use std::sync::Arc;
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
 
-use crate::connector::config::ConnectorConfig;
+use crate::connector::config::{ConnectorConfig, NetworkConfig};
 use crate::connector::connector::{ConnectorInternal, StreamBuffer};
 use crate::connector::errors::ExchangeError::KrakenError;
-use crate::connector::errors::ParsingError::{ConvertingError, MessageParsingError};
+use crate::connector::errors::ParsingError::{ConvertingError, MessageParsingError, UnknownMessage};
 use crate::connector::services::parser::{
     model_from_serde_value, model_from_string, parse_serde_object, parse_timestamp_from_date_string,
 };
 use crate::connector::services::ticker_map::TickerMap;
+use crate::connector::services::control::{await_subscriptions, parse_kraken_control, ControlMessage};
+use crate::connector::services::http::HttpClient;
+use crate::connector::services::symbols::SymbolCache;
 use crate::connector::services::websocket::{connect_websocket, send_ws_message, Connection};
 use crate::shared::logger::Logger;
 use serde::Deserialize;
@@ -36,6 +43,7 @@ struct KrakenBookEntry {
 
 #[derive(Debug, Deserialize)]
 struct KrakenTrade {
+    trade_id: u64,
     price: f64,
     qty: f64,
     side: String,
@@ -43,11 +51,64 @@ struct KrakenTrade {
     symbol: String,
 }
 
-fn convert_ticker_into_kraken_symbol(raw: &str) -> String {
+#[derive(Debug, Deserialize)]
+struct KrakenTicker {
+    symbol: String,
+    bid: f64,
+    bid_qty: f64,
+    ask: f64,
+    ask_qty: f64,
+    #[serde(default)]
+    timestamp: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct KrakenOhlc {
+    symbol: String,
+    open: f64,
+    high: f64,
+    low: f64,
+    close: f64,
+    volume: f64,
+    interval_begin: String,
+    interval: u32,
+    timestamp: String,
+}
+
+pub(crate) fn convert_ticker_into_kraken_symbol(raw: &str) -> String {
     let result = raw.to_uppercase();
     result
 }
 
+/// Kraken reports legacy asset codes in `wsname`, the v2 websocket API uses the common ones
+fn normalize_kraken_wsname(wsname: &str) -> String {
+    wsname
+        .split('/')
+        .map(|asset| match asset {
+            "XBT" => "BTC",
+            "XDG" => "DOGE",
+            other => other,
+        })
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+pub(crate) async fn fetch_kraken_symbols(http: &HttpClient, rest_url: &str) -> Result<HashSet<String>, Error> {
+    let url = format!("{}/0/public/AssetPairs", rest_url);
+    let resp: Value = http.send(&Exchange::Kraken, 1, http.get(&url)).await?.json().await?;
+    if let Some(err) = resp["error"].as_array().and_then(|e| e.first()) {
+        Err(KrakenError(format!("AssetPairs: {}", err)))?;
+    }
+    let pairs = resp["result"]
+        .as_object()
+        .ok_or_else(|| MessageParsingError("AssetPairs: missing result".into()))?;
+    Ok(pairs
+        .values()
+        .filter_map(|p| p["wsname"].as_str())
+        .map(normalize_kraken_wsname)
+        .collect())
+}
+
 fn validate_depth(value: u8) -> Result<(), Error> {
     let available = [10, 25];
     if !available.contains(&value) {
@@ -68,6 +129,13 @@ pub struct KrakenConnector {
     exchange_name: Exchange,
     logger: Logger,
     error_handlers: Vec<ErrorHandler>,
+    network: NetworkConfig,
+    open_candles: Mutex<HashMap<String, Candle>>,
+    rest_url: String,
+    url: String,
+    pending: Mutex<Vec<String>>,
+    symbols: Arc<SymbolCache>,
+    http: Arc<HttpClient>,
 }
 
 impl KrakenConnector {
@@ -80,9 +148,46 @@ impl KrakenConnector {
             exchange_name: Exchange::Kraken,
             logger: Logger::new("kraken", config.log_level),
             error_handlers: config.error_handlers.clone(),
+            network: config.network.clone(),
+            open_candles: Mutex::new(HashMap::new()),
+            rest_url: "https://api.kraken.com".to_string(),
+            url: "wss://ws.kraken.com/v2".to_string(),
+            pending: Mutex::new(Vec::new()),
+            symbols: SymbolCache::global(),
+            http: Arc::clone(&config.http),
         }
     }
 
+    #[cfg(test)]
+    pub fn with_urls(mut self, rest_url: &str, url: &str) -> Self {
+        self.rest_url = rest_url.to_string();
+        self.url = url.to_string();
+        self.symbols = SymbolCache::temporary();
+        self.http = Arc::new(HttpClient::new());
+        self
+    }
+
+    async fn check_symbols(&self) -> Result<(), Error> {
+        self.logger.info("Check symbols");
+
+        let valid_symbols = self
+            .symbols
+            .get_or_fetch(&self.exchange_name, || fetch_kraken_symbols(&self.http, &self.rest_url), &self.logger)
+            .await?;
+        let symbols = self.configs.get_all_symbols();
+
+        if symbols.is_empty() {
+            Err(InternalError("Symbols are empty".to_string()))?;
+        }
+
+        for s in symbols {
+            if !valid_symbols.contains(&s) {
+                Err(KrakenError(format!("Symbol {} does not exist", s)))?;
+            }
+        }
+        Ok(())
+    }
+
     fn handle_depth(
         &self,
         data: &serde_json::Map<String, Value>,
@@ -112,6 +217,8 @@ impl KrakenConnector {
                     quantity: qty as Quantity,
                     timestamp: ts,
                     received: now_timestamp_ns(),
+                    sequence: 0,
+                    prev_sequence: 0,
                 };
                 result.push(Event::LevelUpdate(event));
             }
@@ -128,6 +235,8 @@ impl KrakenConnector {
                     quantity: qty as Quantity,
                     timestamp: ts,
                     received: now_timestamp_ns(),
+                    sequence: 0,
+                    prev_sequence: 0,
                 };
                 result.push(Event::LevelUpdate(event));
             }
@@ -165,6 +274,7 @@ impl KrakenConnector {
             let event = TradeEvent {
                 ticker: Arc::clone(&config.ticker),
                 exchange: self.exchange_name.clone(),
+                trade_id: tr.trade_id,
                 price: price_f as Price,
                 quantity: qty_f as Quantity,
                 timestamp: ts,
@@ -177,14 +287,109 @@ impl KrakenConnector {
 
         Ok(())
     }
+
+    fn handle_bbo(
+        &self,
+        obj: &serde_json::Map<String, Value>,
+        result: &StreamBuffer,
+    ) -> Result<(), Error> {
+        self.logger.debug("Handle ticker message");
+
+        let data = obj
+            .get("data")
+            .and_then(|d| d.as_array())
+            .ok_or_else(|| MessageParsingError("ticker: missing data array".into()))?;
+
+        for item in data {
+            let t: KrakenTicker = model_from_serde_value(item.clone())?;
+            let config = self.configs.get_by_symbol(&t.symbol)?;
+
+            let ts = match &t.timestamp {
+                Some(ts) => parse_timestamp_from_date_string(ts)?,
+                None => 0,
+            };
+
+            let event = BestBidAsk {
+                exchange: self.exchange_name.clone(),
+                ticker: Arc::clone(&config.ticker),
+                bid_price: (t.bid * config.price_multiply) as Price,
+                bid_quantity: (t.bid_qty * config.quantity_multiply) as Quantity,
+                ask_price: (t.ask * config.price_multiply) as Price,
+                ask_quantity: (t.ask_qty * config.quantity_multiply) as Quantity,
+                update_id: 0,
+                timestamp: ts,
+                received: now_timestamp_ns(),
+            };
+
+            result.push(Event::BestBidAsk(event));
+        }
+
+        Ok(())
+    }
+
+    /// Kraken has no closed flag, so a bar is reported as closed once the next one begins
+    fn handle_candle(
+        &self,
+        obj: &serde_json::Map<String, Value>,
+        result: &StreamBuffer,
+    ) -> Result<(), Error> {
+        self.logger.debug("Handle ohlc message");
+
+        let data = obj
+            .get("data")
+            .and_then(|d| d.as_array())
+            .ok_or_else(|| MessageParsingError("ohlc: missing data array".into()))?;
+
+        let mut open_candles = self
+            .open_candles
+            .lock()
+            .map_err(|e| InternalError(e.to_string()))?;
+
+        for item in data {
+            let bar: KrakenOhlc = model_from_serde_value(item.clone())?;
+            let config = self.configs.get_by_symbol(&bar.symbol)?;
+            let interval = CandleInterval::from_minutes(bar.interval)
+                .ok_or_else(|| ConvertingError(format!("Unexpected interval {}", bar.interval)))?;
+
+            let event = Candle {
+                exchange: self.exchange_name.clone(),
+                ticker: Arc::clone(&config.ticker),
+                interval,
+                open_time: parse_timestamp_from_date_string(&bar.interval_begin)?,
+                open: (bar.open * config.price_multiply) as Price,
+                high: (bar.high * config.price_multiply) as Price,
+                low: (bar.low * config.price_multiply) as Price,
+                close: (bar.close * config.price_multiply) as Price,
+                volume: (bar.volume * config.quantity_multiply) as Quantity,
+                closed: false,
+                timestamp: parse_timestamp_from_date_string(&bar.timestamp)?,
+                received: now_timestamp_ns(),
+            };
+
+            if let Some(prev) = open_candles.insert(bar.symbol.clone(), event.clone()) {
+                if prev.open_time < event.open_time {
+                    let closed = Candle {
+                        closed: true,
+                        received: now_timestamp_ns(),
+                        ..prev
+                    };
+                    result.push(Event::Candle(closed));
+                }
+            }
+            result.push(Event::Candle(event));
+        }
+
+        Ok(())
+    }
 }
 
 impl ConnectorInternal for KrakenConnector {
     async fn connect(&self) -> Result<Connection, Error> {
         self.logger.info("Connecting...");
+        self.check_symbols().await?;
 
-        let url = "wss://ws.kraken.com/v2";
-        let (mut write, read) = connect_websocket(url, &self.logger).await?;
+        let (mut write, mut read) = connect_websocket(&self.url, &self.network, &self.logger).await?;
+        let mut subscriptions = 0;
 
         for ticker_config in self.configs.get_all_configs() {
             let symbol = self.configs.get_symbol_from_ticker(&ticker_config.ticker);
@@ -198,6 +403,7 @@ impl ConnectorInternal for KrakenConnector {
                     }
                 });
                 send_ws_message(&mut write, Message::Text(sub_trade.to_string())).await?;
+                subscriptions += 1;
                 self.logger.info(&format!(
                     "Sent trade subscribe for {}",
                     ticker_config.ticker
@@ -216,21 +422,65 @@ impl ConnectorInternal for KrakenConnector {
                     }
                 });
                 send_ws_message(&mut write, Message::Text(sub_book.to_string())).await?;
+                subscriptions += 1;
                 self.logger.info(&format!(
                     "Sent book subscribe for {} with {} depth",
                     symbol, ticker_config.depth_value
                 ));
             }
+
+            if let Some(interval) = ticker_config.candle_interval {
+                let sub_ohlc = serde_json::json!({
+                    "method": "subscribe",
+                    "params": {
+                        "channel": "ohlc",
+                        "symbol": [ symbol ],
+                        "interval": interval.minutes(),
+                        "snapshot": false
+                    }
+                });
+                send_ws_message(&mut write, Message::Text(sub_ohlc.to_string())).await?;
+                subscriptions += 1;
+                self.logger.info(&format!(
+                    "Sent ohlc subscribe for {} with {} minutes interval",
+                    symbol,
+                    interval.minutes()
+                ));
+            }
+
+            if ticker_config.subscribe_bbo {
+                let sub_ticker = serde_json::json!({
+                    "method": "subscribe",
+                    "params": {
+                        "channel": "ticker",
+                        "symbol": [ symbol ],
+                        "event_trigger": "bbo"
+                    }
+                });
+                send_ws_message(&mut write, Message::Text(sub_ticker.to_string())).await?;
+                subscriptions += 1;
+                self.logger.info(&format!("Sent ticker subscribe for {}", symbol));
+            }
         }
 
+        let pending = await_subscriptions(&mut read, subscriptions, parse_kraken_control, &self.logger).await?;
+        *self.pending.lock().unwrap() = pending;
+
         Ok((write, read))
     }
 
+    fn take_pending(&self) -> Vec<String> {
+        std::mem::take(&mut self.pending.lock().unwrap())
+    }
+
     fn on_message(&self, msg: &str, buffer: &StreamBuffer) -> Result<(), Error> {
         let obj = parse_serde_object(msg)?;
 
-        if let Some(error) = obj.get("error") {
-            Err(KrakenError(error.to_string()))?;
+        if let Some(control) = parse_kraken_control(&obj) {
+            return match control {
+                ControlMessage::Rejected { reason, .. } => Err(KrakenError(reason))?,
+                ControlMessage::Subscribed { .. } => Ok(()),
+            };
         }
 
         let channel = obj
@@ -241,18 +491,17 @@ impl ConnectorInternal for KrakenConnector {
         match channel {
             "book" => self.handle_depth(&obj, buffer)?,
             "trade" => self.handle_trade(&obj, buffer)?,
+            "ticker" => self.handle_bbo(&obj, buffer)?,
+            "ohlc" => self.handle_candle(&obj, buffer)?,
             "status" => {}
             "heartbeat" => {}
-            _ => {
-                let msg = format!("Unexpected channel {}", channel);
-                self.logger.warn(&msg);
-            }
+            _ => Err(UnknownMessage(format!("Unexpected channel {}", channel)))?,
         };
         Ok(())
     }
 
     fn on_error(&self, err: &Error) {
-        self.logger.error(&format!("{:?}", err));
+        self.logger.error(&err.to_string());
         for handler in self.error_handlers.iter() {
             handler(err)
         }
@@ -261,4 +510,174 @@ impl ConnectorInternal for KrakenConnector {
     fn logger(&self) -> &Logger {
         &self.logger
     }
+
+    fn exchange(&self) -> &Exchange {
+        &self.exchange_name
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::connector::errors::ExchangeError;
+    use crate::connector::services::mock_server::{mock_http_server, mock_ws_server};
+    use crate::connector::Connector;
+    use crate::connector::config::TickerConfig;
+    use crate::shared::{InstrumentKind, TimestampMS};
+    use tracing::Level;
+
+    fn connector() -> KrakenConnector {
+        KrakenConnector::new(ConnectorConfig {
+            ticker_configs: vec![TickerConfig {
+                ticker: Arc::new("btc/usd".to_string()),
+                instrument: InstrumentKind::Spot,
+                price_multiply: 10.0,
+                quantity_multiply: 100.0,
+                subscribe_trades: false,
+                subscribe_depth: false,
+                subscribe_bbo: true,
+                candle_interval: None,
+                level3_depth: None,
+                depth_value: 0,
+            }],
+            error_handlers: vec![],
+            log_level: Level::ERROR,
+            kraken_token: None,
+            credentials: None,
+            network: NetworkConfig::default(),
+            http: Arc::new(HttpClient::new()),
+        })
+    }
+
+    fn asset_pairs() -> (u16, String) {
+        let body = r#"{"error":[],"result":{
+            "XXBTZUSD":{"altname":"XBTUSD","wsname":"XBT/USD"},
+            "XETHZUSD":{"altname":"ETHUSD","wsname":"ETH/USD"}}}"#;
+        (200, body.to_string())
+    }
+
+    #[test]
+    fn test_normalize_kraken_wsname() {
+        assert_eq!(normalize_kraken_wsname("XBT/USD"), "BTC/USD");
+        assert_eq!(normalize_kraken_wsname("XDG/EUR"), "DOGE/EUR");
+        assert_eq!(normalize_kraken_wsname("ETH/USDT"), "ETH/USDT");
+    }
+
+    #[tokio::test]
+    async fn test_fetch_kraken_symbols() {
+        let (rest_url, rest) = mock_http_server(vec![asset_pairs()]).await;
+        let symbols = fetch_kraken_symbols(&HttpClient::new(), &rest_url).await.unwrap();
+        let requests = rest.await.unwrap();
+        assert!(requests[0].starts_with("GET /0/public/AssetPairs"));
+        assert!(symbols.contains("BTC/USD"));
+        assert!(symbols.contains("ETH/USD"));
+    }
+
+    #[tokio::test]
+    async fn test_connect_fails_on_unknown_symbol() {
+        let body = r#"{"error":[],"result":{"XETHZUSD":{"altname":"ETHUSD","wsname":"ETH/USD"}}}"#;
+        let (rest_url, _rest) = mock_http_server(vec![(200, body.to_string())]).await;
+
+        let res = connector().with_urls(&rest_url, "ws://127.0.0.1:1").stream().await;
+        match res {
+            Err(Error::ExchangeError(ExchangeError::KrakenError(msg))) => {
+                assert!(msg.contains("BTC/USD"))
+            }
+            Err(other) => panic!("unexpected error {:?}", other),
+            Ok(_) => panic!("connect should fail"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_connect_fails_on_rejected_subscription() {
+        let (url, server) = mock_ws_server(
+            1,
+            vec![
+                r#"{"channel":"status","type":"update","data":[{"system":"online"}]}"#.to_string(),
+                r#"{"method":"subscribe","error":"Currency pair not supported BTC/USD","success":false}"#.to_string(),
+            ],
+        )
+        .await;
+
+        let (rest_url, _rest) = mock_http_server(vec![asset_pairs()]).await;
+        let res = connector().with_urls(&rest_url, &url).stream().await;
+        server.await.unwrap();
+        match res {
+            Err(Error::ExchangeError(ExchangeError::SubscriptionRejected(reason))) => {
+                assert!(reason.contains("not supported"))
+            }
+            Err(other) => panic!("unexpected error {:?}", other),
+            Ok(_) => panic!("connect should fail"),
+        }
+    }
+
+    #[test]
+    fn test_rejection_after_connect_is_an_error() {
+        let buffer = StreamBuffer::new();
+        let msg = r#"{"method":"unsubscribe","error":"Subscription not found","success":false}"#;
+        assert!(connector().on_message(msg, &buffer).is_err());
+
+        let msg = r#"{"method":"subscribe","result":{"channel":"ticker"},"success":true}"#;
+        connector().on_message(msg, &buffer).unwrap();
+        assert!(buffer.is_empty());
+    }
+
+    #[test]
+    fn test_handle_ticker() {
+        let buffer = StreamBuffer::new();
+        let msg = r#"{"channel":"ticker","type":"update","data":[{"symbol":"BTC/USD",
+            "bid":60000.5,"bid_qty":0.5,"ask":60001.0,"ask_qty":1.25,"last":60000.5,
+            "volume":100.0,"vwap":59000.0,"low":58000.0,"high":61000.0,"change":100.0,"change_pct":0.1,
+            "timestamp":"2024-01-01T00:00:00.000Z"}]}"#;
+        connector().on_message(msg, &buffer).unwrap();
+
+        match buffer.pop() {
+            Some(Event::BestBidAsk(bbo)) => {
+                assert_eq!(bbo.bid_price, 600_005);
+                assert_eq!(bbo.bid_quantity, 50);
+                assert_eq!(bbo.ask_price, 600_010);
+                assert_eq!(bbo.ask_quantity, 125);
+                assert_eq!(bbo.timestamp, 1_704_067_200_000);
+            }
+            other => panic!("unexpected event {:?}", other),
+        }
+    }
+
+    fn ohlc(interval_begin: &str, close: f64) -> String {
+        format!(
+            r#"{{"channel":"ohlc","type":"update","data":[{{"symbol":"BTC/USD","open":100.0,"high":102.0,
+                "low":99.0,"close":{close},"trades":3,"volume":1.5,"vwap":100.5,
+                "interval_begin":"{interval_begin}","interval":5,"timestamp":"2024-01-01T00:05:00.000000Z"}}]}}"#
+        )
+    }
+
+    fn candles(buffer: &StreamBuffer) -> Vec<(TimestampMS, Price, bool)> {
+        let mut result = vec![];
+        while let Some(ev) = buffer.pop() {
+            if let Event::Candle(c) = ev {
+                assert_eq!(c.interval, CandleInterval::M5);
+                result.push((c.open_time, c.close, c.closed));
+            }
+        }
+        result
+    }
+
+    #[test]
+    fn test_ohlc_closes_previous_bar() {
+        let c = connector();
+        let buffer = StreamBuffer::new();
+
+        c.on_message(&ohlc("2024-01-01T00:00:00.000000000Z", 100.5), &buffer).unwrap();
+        c.on_message(&ohlc("2024-01-01T00:00:00.000000000Z", 101.0), &buffer).unwrap();
+        assert_eq!(
+            candles(&buffer),
+            vec![(1_704_067_200_000, 1005, false), (1_704_067_200_000, 1010, false)]
+        );
+
+        c.on_message(&ohlc("2024-01-01T00:05:00.000000000Z", 101.5), &buffer).unwrap();
+        assert_eq!(
+            candles(&buffer),
+            vec![(1_704_067_200_000, 1010, true), (1_704_067_500_000, 1015, false)]
+        );
+    }
 }
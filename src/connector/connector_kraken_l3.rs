@@ -0,0 +1,348 @@
+use crate::connector::config::{ConnectorConfig, NetworkConfig};
+use crate::connector::connector::{ConnectorInternal, StreamBuffer};
+use crate::connector::connector_kraken::convert_ticker_into_kraken_symbol;
+use crate::connector::errors::Error::BuilderError;
+use crate::connector::errors::ExchangeError::KrakenError;
+use crate::connector::errors::ParsingError::{ConvertingError, MessageParsingError, UnknownMessage};
+use crate::connector::errors::{Error, ErrorHandler};
+use crate::connector::services::parser::{
+    model_from_serde_value, parse_serde_object, parse_timestamp_from_date_string,
+};
+use crate::connector::services::ticker_map::TickerMap;
+use crate::connector::services::control::{await_subscriptions, parse_kraken_control, ControlMessage};
+use crate::connector::services::websocket::{connect_websocket, send_ws_message, Connection};
+use crate::connector::Event;
+use crate::level3::{OrderAction, OrderEvent};
+use crate::shared::logger::Logger;
+use crate::shared::utils::now_timestamp_ns;
+use crate::shared::{Exchange, Price, Quantity, Side};
+use serde::Deserialize;
+use serde_json::Value;
+use std::sync::{Arc, Mutex};
+use tokio_tungstenite::tungstenite::Message;
+
+#[derive(Debug, Deserialize)]
+struct KrakenOrder {
+    // Snapshot entries come without an event
+    #[serde(default)]
+    event: Option<String>,
+    order_id: String,
+    limit_price: f64,
+    order_qty: f64,
+    timestamp: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct KrakenLevel3Entry {
+    symbol: String,
+    bids: Vec<KrakenOrder>,
+    asks: Vec<KrakenOrder>,
+}
+
+fn validate_level3_depth(value: u16) -> Result<(), Error> {
+    let available = [10, 100, 1000];
+    if !available.contains(&value) {
+        Err(KrakenError(format!(
+            "Level3 depth value must be one of the following numbers: {}",
+            available
+                .iter()
+                .map(|v| v.to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        )))?;
+    }
+    Ok(())
+}
+
+/// Authenticated order-by-order feed. Lives on its own endpoint, separate from `KrakenConnector`.
+pub struct KrakenL3Connector {
+    configs: TickerMap,
+    exchange: Exchange,
+    logger: Logger,
+    error_handlers: Vec<ErrorHandler>,
+    network: NetworkConfig,
+    token: Option<String>,
+    url: String,
+    pending: Mutex<Vec<String>>,
+}
+
+impl KrakenL3Connector {
+    pub fn new(config: ConnectorConfig) -> Self {
+        Self {
+            configs: TickerMap::from_configs(
+                config.ticker_configs,
+                convert_ticker_into_kraken_symbol,
+            ),
+            exchange: Exchange::Kraken,
+            logger: Logger::new("kraken-l3", config.log_level),
+            error_handlers: config.error_handlers,
+            network: config.network.clone(),
+            token: config.kraken_token,
+            url: "wss://ws-l3.kraken.com/v2".to_string(),
+            pending: Mutex::new(Vec::new()),
+        }
+    }
+
+    #[cfg(test)]
+    pub fn with_url(mut self, url: &str) -> Self {
+        self.url = url.to_string();
+        self
+    }
+
+    fn handle_orders(
+        &self,
+        obj: &serde_json::Map<String, Value>,
+        result: &StreamBuffer,
+    ) -> Result<(), Error> {
+        self.logger.debug("Handle level3 message");
+
+        let data = obj
+            .get("data")
+            .and_then(|d| d.as_array())
+            .ok_or_else(|| MessageParsingError("level3: missing data array".into()))?;
+        let is_snapshot = obj.get("type").and_then(|t| t.as_str()) == Some("snapshot");
+
+        for item in data {
+            let entry: KrakenLevel3Entry = model_from_serde_value(item.clone())?;
+            let config = self.configs.get_by_symbol(&entry.symbol)?;
+
+            // A snapshot replaces every order known so far, e.g. after a reconnect
+            if is_snapshot {
+                result.push(Event::Order(OrderEvent {
+                    exchange: self.exchange.clone(),
+                    ticker: Arc::clone(&config.ticker),
+                    order_id: Arc::new(String::new()),
+                    action: OrderAction::Clear,
+                    side: Side::Buy,
+                    price: 0,
+                    quantity: 0,
+                    timestamp: 0,
+                    received: now_timestamp_ns(),
+                }));
+            }
+
+            let sides = [(Side::Buy, entry.bids), (Side::Sell, entry.asks)];
+            for (side, orders) in sides {
+                for order in orders {
+                    let action = match order.event.as_deref() {
+                        None | Some("add") => OrderAction::Add,
+                        Some("modify") => OrderAction::Modify,
+                        Some("delete") => OrderAction::Delete,
+                        Some(other) => {
+                            Err(ConvertingError(format!("Unexpected order event {}", other)))?
+                        }
+                    };
+                    let event = OrderEvent {
+                        exchange: self.exchange.clone(),
+                        ticker: Arc::clone(&config.ticker),
+                        order_id: Arc::new(order.order_id),
+                        action,
+                        side,
+                        price: (order.limit_price * config.price_multiply) as Price,
+                        quantity: (order.order_qty * config.quantity_multiply) as Quantity,
+                        timestamp: parse_timestamp_from_date_string(&order.timestamp)?,
+                        received: now_timestamp_ns(),
+                    };
+                    result.push(Event::Order(event));
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl ConnectorInternal for KrakenL3Connector {
+    async fn connect(&self) -> Result<Connection, Error> {
+        self.logger.info("Connecting...");
+
+        let token = self
+            .token
+            .as_ref()
+            .ok_or_else(|| BuilderError("Kraken level3 requires a websocket token".to_string()))?;
+
+        let (mut write, mut read) = connect_websocket(&self.url, &self.network, &self.logger).await?;
+        let mut subscriptions = 0;
+
+        for ticker_config in self.configs.get_all_configs() {
+            let Some(depth) = ticker_config.level3_depth else {
+                continue;
+            };
+            validate_level3_depth(depth)?;
+
+            let symbol = self.configs.get_symbol_from_ticker(&ticker_config.ticker);
+            let sub = serde_json::json!({
+                "method": "subscribe",
+                "params": {
+                    "channel": "level3",
+                    "symbol": [ symbol ],
+                    "depth": depth,
+                    "snapshot": true,
+                    "token": token
+                }
+            });
+            send_ws_message(&mut write, Message::Text(sub.to_string())).await?;
+            subscriptions += 1;
+            self.logger.info(&format!(
+                "Sent level3 subscribe for {} with {} depth",
+                symbol, depth
+            ));
+        }
+
+        let pending = await_subscriptions(&mut read, subscriptions, parse_kraken_control, &self.logger).await?;
+        *self.pending.lock().unwrap() = pending;
+
+        Ok((write, read))
+    }
+
+    fn take_pending(&self) -> Vec<String> {
+        std::mem::take(&mut self.pending.lock().unwrap())
+    }
+
+    fn on_message(&self, msg: &str, buffer: &StreamBuffer) -> Result<(), Error> {
+        let obj = parse_serde_object(msg)?;
+
+        if let Some(control) = parse_kraken_control(&obj) {
+            return match control {
+                ControlMessage::Rejected { reason, .. } => Err(KrakenError(reason))?,
+                ControlMessage::Subscribed { .. } => Ok(()),
+            };
+        }
+
+        // Subscription results carry no channel
+        let Some(channel) = obj.get("channel").and_then(|c| c.as_str()) else {
+            return Ok(());
+        };
+
+        match channel {
+            "level3" => self.handle_orders(&obj, buffer)?,
+            "status" => {}
+            "heartbeat" => {}
+            _ => Err(UnknownMessage(format!("Unexpected channel {}", channel)))?,
+        };
+        Ok(())
+    }
+
+    fn on_error(&self, err: &Error) {
+        self.logger.error(&err.to_string());
+        for handler in self.error_handlers.iter() {
+            handler(err)
+        }
+    }
+
+    fn logger(&self) -> &Logger {
+        &self.logger
+    }
+
+    fn exchange(&self) -> &Exchange {
+        &self.exchange
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::connector::services::http::HttpClient;
+    use crate::connector::config::TickerConfig;
+    use crate::connector::services::mock_server::mock_ws_server;
+    use crate::connector::Connector;
+    use crate::level3::OrderByOrderBook;
+    use crate::shared::InstrumentKind;
+    use futures_util::StreamExt;
+    use tracing::Level;
+
+    fn connector(url: &str, token: Option<&str>) -> KrakenL3Connector {
+        let config = ConnectorConfig {
+            ticker_configs: vec![TickerConfig {
+                ticker: Arc::new("btc/usd".to_string()),
+                instrument: InstrumentKind::Spot,
+                price_multiply: 10.0,
+                quantity_multiply: 100.0,
+                subscribe_trades: false,
+                subscribe_depth: false,
+                subscribe_bbo: false,
+                candle_interval: None,
+                level3_depth: Some(10),
+                depth_value: 0,
+            }],
+            error_handlers: vec![],
+            log_level: Level::ERROR,
+            kraken_token: token.map(|t| t.to_string()),
+            credentials: None,
+            network: NetworkConfig::default(),
+            http: Arc::new(HttpClient::new()),
+        };
+        KrakenL3Connector::new(config).with_url(url)
+    }
+
+    #[tokio::test]
+    async fn test_requires_token() {
+        let res = connector("ws://127.0.0.1:1", None).stream().await;
+        assert!(matches!(res, Err(BuilderError(_))));
+    }
+
+    #[tokio::test]
+    async fn test_snapshot_and_updates() {
+        let responses = vec![
+            r#"{"method":"subscribe","result":{"channel":"level3","symbol":"BTC/USD"},"success":true}"#.to_string(),
+            r#"{"channel":"level3","type":"snapshot","data":[{"symbol":"BTC/USD","checksum":1,
+                "bids":[{"order_id":"OA","limit_price":100.5,"order_qty":1.5,"timestamp":"2024-01-01T00:00:00.000000000Z"}],
+                "asks":[]}]}"#.to_string(),
+            r#"{"channel":"level3","type":"update","data":[{"symbol":"BTC/USD","checksum":2,
+                "bids":[{"event":"modify","order_id":"OA","limit_price":100.5,"order_qty":0.5,"timestamp":"2024-01-01T00:00:01.000000000Z"}],
+                "asks":[{"event":"delete","order_id":"OB","limit_price":101.0,"order_qty":0.0,"timestamp":"2024-01-01T00:00:01.000000000Z"}]}]}"#.to_string(),
+        ];
+        let (url, server) = mock_ws_server(1, responses).await;
+
+        let stream = connector(&url, Some("secret")).stream().await.unwrap();
+        let events: Vec<Event> = stream.collect().await;
+
+        let requests = server.await.unwrap();
+        assert!(requests[0].contains(r#""channel":"level3""#));
+        assert!(requests[0].contains(r#""token":"secret""#));
+
+        let orders: Vec<(String, OrderAction, Side, Price, Quantity)> = events
+            .into_iter()
+            .filter_map(|ev| match ev {
+                Event::Order(o) => Some((o.order_id.to_string(), o.action, o.side, o.price, o.quantity)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            orders,
+            vec![
+                ("".to_string(), OrderAction::Clear, Side::Buy, 0, 0),
+                ("OA".to_string(), OrderAction::Add, Side::Buy, 1005, 150),
+                ("OA".to_string(), OrderAction::Modify, Side::Buy, 1005, 50),
+                ("OB".to_string(), OrderAction::Delete, Side::Sell, 1010, 0),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_snapshot_replaces_known_orders() {
+        let connector = connector("ws://127.0.0.1:1", Some("secret"));
+        let buffer = StreamBuffer::new();
+        let snapshot = |order_id: &str, price: f64| {
+            format!(
+                r#"{{"channel":"level3","type":"snapshot","data":[{{"symbol":"BTC/USD","checksum":1,
+                "bids":[{{"order_id":"{}","limit_price":{},"order_qty":1.0,"timestamp":"2024-01-01T00:00:00.000000000Z"}}],
+                "asks":[]}}]}}"#,
+                order_id, price
+            )
+        };
+        connector.on_message(&snapshot("OA", 100.5), &buffer).unwrap();
+        // Resubscription after a reconnect, OA is gone by now
+        connector.on_message(&snapshot("OC", 100.0), &buffer).unwrap();
+
+        let mut book = OrderByOrderBook::new(Exchange::Kraken, "btc/usd");
+        while let Some(event) = buffer.pop() {
+            if let Event::Order(order) = event {
+                book.update(&order).unwrap();
+            }
+        }
+        assert_eq!(book.order_count(), 1);
+        assert_eq!(book.order("OA"), None);
+        assert_eq!(book.level_quantity(Side::Buy, 1000), 100);
+    }
+}
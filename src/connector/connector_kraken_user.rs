@@ -0,0 +1,477 @@
+use crate::account::{BalanceUpdate, Fill, OrderStatus, OrderUpdate};
+use crate::connector::config::{ApiCredentials, ConnectorConfig, NetworkConfig};
+use crate::connector::connector::{ConnectorInternal, StreamBuffer};
+use crate::connector::connector_kraken::convert_ticker_into_kraken_symbol;
+use crate::connector::errors::Error::BuilderError;
+use crate::connector::errors::ExchangeError::KrakenError;
+use crate::connector::errors::ParsingError::{ConvertingError, MessageParsingError, UnknownMessage};
+use crate::connector::errors::{Error, ErrorHandler};
+use crate::connector::services::http::HttpClient;
+use crate::connector::services::parser::{
+    model_from_serde_value, parse_serde_object, parse_timestamp_from_date_string,
+};
+use crate::connector::services::signing::kraken_signature;
+use crate::connector::services::ticker_map::TickerMap;
+use crate::connector::services::control::{await_subscriptions, parse_kraken_control, ControlMessage};
+use crate::connector::services::websocket::{connect_websocket, send_ws_message, Connection};
+use crate::connector::Event;
+use crate::shared::logger::Logger;
+use crate::shared::utils::{now_timestamp, now_timestamp_ns};
+use crate::shared::{Exchange, Price, Quantity, Side};
+use serde::Deserialize;
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tokio_tungstenite::tungstenite::Message;
+
+const TOKEN_PATH: &str = "/0/private/GetWebSocketsToken";
+
+#[derive(Debug, Deserialize)]
+struct KrakenFee {
+    asset: String,
+    qty: f64,
+}
+
+#[derive(Debug, Deserialize)]
+struct KrakenExecution {
+    exec_type: String,
+    order_id: String,
+    cl_ord_id: Option<String>,
+    // Status-only updates (e.g. "canceled") omit the order description
+    symbol: Option<String>,
+    side: Option<String>,
+    order_status: Option<String>,
+    limit_price: Option<f64>,
+    order_qty: Option<f64>,
+    cum_qty: Option<f64>,
+    exec_id: Option<String>,
+    last_qty: Option<f64>,
+    last_price: Option<f64>,
+    liquidity_ind: Option<String>,
+    #[serde(default)]
+    fees: Vec<KrakenFee>,
+    timestamp: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct KrakenBalance {
+    asset: String,
+    balance: f64,
+    timestamp: Option<String>,
+}
+
+fn parse_side(raw: &str) -> Result<Side, Error> {
+    match raw {
+        "buy" => Ok(Side::Buy),
+        "sell" => Ok(Side::Sell),
+        _ => Err(ConvertingError(format!("Unexpected side {}", raw)))?,
+    }
+}
+
+fn parse_status(raw: &str) -> Result<OrderStatus, Error> {
+    let status = match raw {
+        "pending_new" | "new" => OrderStatus::New,
+        "partially_filled" => OrderStatus::PartiallyFilled,
+        "filled" => OrderStatus::Filled,
+        "canceled" => OrderStatus::Canceled,
+        "expired" => OrderStatus::Expired,
+        _ => Err(ConvertingError(format!("Unexpected order status {}", raw)))?,
+    };
+    Ok(status)
+}
+
+/// Private account stream on the authenticated endpoint: `executions` and `balances` channels.
+/// The websocket token is requested over REST with the API key on every connect.
+pub struct KrakenUserConnector {
+    configs: TickerMap,
+    exchange: Exchange,
+    logger: Logger,
+    error_handlers: Vec<ErrorHandler>,
+    network: NetworkConfig,
+    credentials: Option<ApiCredentials>,
+    rest_url: String,
+    ws_url: String,
+    pending: Mutex<Vec<String>>,
+    http: Arc<HttpClient>,
+    /// Symbol and side of open orders, status-only updates carry neither
+    orders: Mutex<HashMap<String, (String, Side)>>,
+}
+
+impl KrakenUserConnector {
+    pub fn new(config: ConnectorConfig) -> Self {
+        Self {
+            configs: TickerMap::from_configs(
+                config.ticker_configs,
+                convert_ticker_into_kraken_symbol,
+            ),
+            exchange: Exchange::Kraken,
+            logger: Logger::new("kraken-user", config.log_level),
+            error_handlers: config.error_handlers,
+            network: config.network.clone(),
+            credentials: config.credentials,
+            rest_url: "https://api.kraken.com".to_string(),
+            ws_url: "wss://ws-auth.kraken.com/v2".to_string(),
+            pending: Mutex::new(Vec::new()),
+            http: Arc::clone(&config.http),
+            orders: Mutex::new(HashMap::new()),
+        }
+    }
+
+    #[cfg(test)]
+    pub fn with_urls(mut self, rest_url: &str, ws_url: &str) -> Self {
+        self.rest_url = rest_url.to_string();
+        self.ws_url = ws_url.to_string();
+        self.http = Arc::new(HttpClient::new());
+        self
+    }
+
+    async fn fetch_token(&self, credentials: &ApiCredentials) -> Result<String, Error> {
+        let nonce = now_timestamp();
+        let body = format!("nonce={}", nonce);
+        let sign = kraken_signature(TOKEN_PATH, nonce, &body, &credentials.api_secret)?;
+
+        let request = self
+            .http
+            .post(&format!("{}{}", self.rest_url, TOKEN_PATH))
+            .header("API-Key", &credentials.api_key)
+            .header("API-Sign", sign)
+            .header("Content-Type", "application/x-www-form-urlencoded")
+            .body(body);
+        let resp: Value = self.http.send_once(&self.exchange, 1, request).await?.json().await?;
+
+        if let Some(errors) = resp["error"].as_array().filter(|e| !e.is_empty()) {
+            Err(KrakenError(format!("{:?}", errors)))?;
+        }
+        let token = resp["result"]["token"]
+            .as_str()
+            .ok_or_else(|| KrakenError("Response has no websocket token".to_string()))?;
+        Ok(token.to_string())
+    }
+
+    fn handle_executions(&self, obj: &Map<String, Value>, buffer: &StreamBuffer) -> Result<(), Error> {
+        self.logger.debug("Handle executions message");
+
+        let data = obj
+            .get("data")
+            .and_then(|d| d.as_array())
+            .ok_or_else(|| MessageParsingError("executions: missing data array".into()))?;
+
+        for item in data {
+            let exec: KrakenExecution = model_from_serde_value(item.clone())?;
+            let Some(status) = exec.order_status.as_deref() else {
+                self.logger.debug(&format!("Skip execution without status for {}", exec.order_id));
+                continue;
+            };
+            let status = parse_status(status)?;
+
+            let mut orders = self.orders.lock().unwrap();
+            let (symbol, side) = match (&exec.symbol, &exec.side) {
+                (Some(symbol), Some(side)) => (symbol.clone(), parse_side(side)?),
+                _ => match orders.get(&exec.order_id) {
+                    Some(known) => known.clone(),
+                    None => {
+                        self.logger.debug(&format!("Skip execution for unknown order {}", exec.order_id));
+                        continue;
+                    }
+                },
+            };
+            if matches!(status, OrderStatus::New | OrderStatus::PartiallyFilled) {
+                orders.insert(exec.order_id.clone(), (symbol.clone(), side));
+            } else {
+                orders.remove(&exec.order_id);
+            }
+            drop(orders);
+
+            let Ok(config) = self.configs.get_by_symbol(&symbol) else {
+                self.logger.debug(&format!("Skip execution for untracked symbol {}", symbol));
+                continue;
+            };
+
+            let timestamp = parse_timestamp_from_date_string(&exec.timestamp)?;
+            let received = now_timestamp_ns();
+
+            buffer.push(Event::OrderUpdate(OrderUpdate {
+                exchange: self.exchange.clone(),
+                ticker: Arc::clone(&config.ticker),
+                order_id: exec.order_id.clone(),
+                client_order_id: exec.cl_ord_id.clone(),
+                side,
+                status,
+                price: (exec.limit_price.unwrap_or(0.0) * config.price_multiply) as Price,
+                quantity: (exec.order_qty.unwrap_or(0.0) * config.quantity_multiply) as Quantity,
+                filled_quantity: (exec.cum_qty.unwrap_or(0.0) * config.quantity_multiply) as Quantity,
+                timestamp,
+                received,
+            }));
+
+            if exec.exec_type == "trade" {
+                let fee = exec.fees.first();
+                buffer.push(Event::Fill(Fill {
+                    exchange: self.exchange.clone(),
+                    ticker: Arc::clone(&config.ticker),
+                    order_id: exec.order_id,
+                    trade_id: exec.exec_id.unwrap_or_default(),
+                    side,
+                    price: (exec.last_price.unwrap_or(0.0) * config.price_multiply) as Price,
+                    quantity: (exec.last_qty.unwrap_or(0.0) * config.quantity_multiply) as Quantity,
+                    fee: fee.map(|f| f.qty).unwrap_or(0.0),
+                    fee_asset: fee.map(|f| f.asset.clone()).unwrap_or_default(),
+                    is_maker: exec.liquidity_ind.as_deref() == Some("m"),
+                    timestamp,
+                    received,
+                }));
+            }
+        }
+        Ok(())
+    }
+
+    fn handle_balances(&self, obj: &Map<String, Value>, buffer: &StreamBuffer) -> Result<(), Error> {
+        self.logger.debug("Handle balances message");
+
+        let data = obj
+            .get("data")
+            .and_then(|d| d.as_array())
+            .ok_or_else(|| MessageParsingError("balances: missing data array".into()))?;
+
+        let received = now_timestamp_ns();
+        for item in data {
+            let balance: KrakenBalance = model_from_serde_value(item.clone())?;
+            // Snapshot entries have no timestamp
+            let timestamp = match balance.timestamp {
+                Some(ts) => parse_timestamp_from_date_string(&ts)?,
+                None => now_timestamp(),
+            };
+            // Kraken reports only the total, funds reserved by open orders are not split out
+            buffer.push(Event::BalanceUpdate(BalanceUpdate {
+                exchange: self.exchange.clone(),
+                asset: balance.asset,
+                free: balance.balance,
+                locked: 0.0,
+                timestamp,
+                received,
+            }));
+        }
+        Ok(())
+    }
+}
+
+impl ConnectorInternal for KrakenUserConnector {
+    async fn connect(&self) -> Result<Connection, Error> {
+        self.logger.info("Connecting...");
+
+        let credentials = self
+            .credentials
+            .as_ref()
+            .ok_or_else(|| BuilderError("Kraken user stream requires an API key".to_string()))?;
+        let token = self.fetch_token(credentials).await?;
+
+        let (mut write, mut read) = connect_websocket(&self.ws_url, &self.network, &self.logger).await?;
+        let mut subscriptions = 0;
+
+        let executions = serde_json::json!({
+            "method": "subscribe",
+            "params": {
+                "channel": "executions",
+                "token": token,
+                "snap_orders": true,
+                "snap_trades": false
+            }
+        });
+        send_ws_message(&mut write, Message::Text(executions.to_string())).await?;
+        subscriptions += 1;
+
+        let balances = serde_json::json!({
+            "method": "subscribe",
+            "params": {
+                "channel": "balances",
+                "token": token,
+                "snapshot": true
+            }
+        });
+        send_ws_message(&mut write, Message::Text(balances.to_string())).await?;
+        subscriptions += 1;
+        self.logger.info("Sent executions and balances subscribe");
+
+        let pending = await_subscriptions(&mut read, subscriptions, parse_kraken_control, &self.logger).await?;
+        *self.pending.lock().unwrap() = pending;
+
+        Ok((write, read))
+    }
+
+    fn take_pending(&self) -> Vec<String> {
+        std::mem::take(&mut self.pending.lock().unwrap())
+    }
+
+    fn on_message(&self, msg: &str, buffer: &StreamBuffer) -> Result<(), Error> {
+        let obj = parse_serde_object(msg)?;
+
+        if let Some(control) = parse_kraken_control(&obj) {
+            return match control {
+                ControlMessage::Rejected { reason, .. } => Err(KrakenError(reason))?,
+                ControlMessage::Subscribed { .. } => Ok(()),
+            };
+        }
+
+        let Some(channel) = obj.get("channel").and_then(|c| c.as_str()) else {
+            return Ok(());
+        };
+
+        match channel {
+            "executions" => self.handle_executions(&obj, buffer)?,
+            "balances" => self.handle_balances(&obj, buffer)?,
+            "status" => {}
+            "heartbeat" => {}
+            _ => Err(UnknownMessage(format!("Unexpected channel {}", channel)))?,
+        };
+        Ok(())
+    }
+
+    fn on_error(&self, err: &Error) {
+        self.logger.error(&err.to_string());
+        for handler in self.error_handlers.iter() {
+            handler(err)
+        }
+    }
+
+    fn logger(&self) -> &Logger {
+        &self.logger
+    }
+
+    fn exchange(&self) -> &Exchange {
+        &self.exchange
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::connector::config::TickerConfig;
+    use crate::connector::services::mock_server::{mock_http_server, mock_ws_server};
+    use crate::connector::Connector;
+    use crate::shared::InstrumentKind;
+    use futures_util::StreamExt;
+    use tracing::Level;
+
+    fn connector(rest_url: &str, ws_url: &str) -> KrakenUserConnector {
+        let config = ConnectorConfig {
+            ticker_configs: vec![TickerConfig {
+                ticker: Arc::new("btc/usd".to_string()),
+                instrument: InstrumentKind::Spot,
+                price_multiply: 10.0,
+                quantity_multiply: 100.0,
+                subscribe_trades: false,
+                subscribe_depth: false,
+                subscribe_bbo: false,
+                candle_interval: None,
+                level3_depth: None,
+                depth_value: 0,
+            }],
+            error_handlers: vec![],
+            log_level: Level::ERROR,
+            kraken_token: None,
+            credentials: Some(ApiCredentials {
+                api_key: "key".to_string(),
+                api_secret: "c2VjcmV0".to_string(),
+            }),
+            network: NetworkConfig::default(),
+            http: Arc::new(HttpClient::new()),
+        };
+        KrakenUserConnector::new(config).with_urls(rest_url, ws_url)
+    }
+
+    const EXECUTION: &str = r#"{"channel":"executions","type":"update","data":[{"exec_type":"trade",
+        "order_id":"OABC","cl_ord_id":"my-1","symbol":"BTC/USD","side":"sell","order_status":"filled",
+        "limit_price":100.5,"order_qty":1.0,"cum_qty":1.0,"exec_id":"TX1","last_qty":1.0,"last_price":100.6,
+        "liquidity_ind":"t","fees":[{"asset":"USD","qty":0.25}],"timestamp":"2024-01-01T00:00:00.000000Z"}]}"#;
+
+    const BALANCES: &str = r#"{"channel":"balances","type":"snapshot","data":[{"asset":"BTC","balance":2.5,
+        "wallets":[]}]}"#;
+
+    #[tokio::test]
+    async fn test_user_stream() {
+        let (rest_url, rest) = mock_http_server(vec![(
+            200,
+            r#"{"error":[],"result":{"token":"tkn","expires":900}}"#.to_string(),
+        )])
+        .await;
+        let (ws_url, ws) = mock_ws_server(
+            2,
+            vec![
+                r#"{"method":"subscribe","result":{"channel":"executions"},"success":true}"#.to_string(),
+                r#"{"method":"subscribe","result":{"channel":"balances"},"success":true}"#.to_string(),
+                EXECUTION.to_string(),
+                BALANCES.to_string(),
+            ],
+        )
+        .await;
+
+        let stream = connector(&rest_url, &ws_url).stream().await.unwrap();
+        let events: Vec<Event> = stream.collect().await;
+
+        let rest_requests = rest.await.unwrap();
+        assert!(rest_requests[0].starts_with("POST /0/private/GetWebSocketsToken"));
+        assert!(rest_requests[0].to_lowercase().contains("api-sign: "));
+        let ws_requests = ws.await.unwrap();
+        assert!(ws_requests[0].contains(r#""channel":"executions""#));
+        assert!(ws_requests[0].contains(r#""token":"tkn""#));
+        assert!(ws_requests[1].contains(r#""channel":"balances""#));
+
+        assert_eq!(events.len(), 3);
+        match &events[0] {
+            Event::OrderUpdate(u) => {
+                assert_eq!(u.status, OrderStatus::Filled);
+                assert_eq!((u.side, u.price, u.filled_quantity), (Side::Sell, 1005, 100));
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+        match &events[1] {
+            Event::Fill(f) => {
+                assert_eq!((f.price, f.quantity, f.is_maker), (1006, 100, false));
+                assert_eq!((f.fee, f.fee_asset.as_str()), (0.25, "USD"));
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+        match &events[2] {
+            Event::BalanceUpdate(b) => assert_eq!((b.asset.as_str(), b.free), ("BTC", 2.5)),
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_status_only_update_uses_known_order() {
+        let connector = connector("http://127.0.0.1:1", "ws://127.0.0.1:1");
+        let buffer = StreamBuffer::new();
+        let new = r#"{"channel":"executions","type":"snapshot","data":[{"exec_type":"new","order_id":"OABC",
+            "symbol":"BTC/USD","side":"buy","order_status":"new","limit_price":100.0,"order_qty":1.0,"cum_qty":0.0,
+            "timestamp":"2024-01-01T00:00:00.000000Z"}]}"#;
+        let canceled = r#"{"channel":"executions","type":"update","data":[{"exec_type":"canceled",
+            "order_id":"OABC","order_status":"canceled","timestamp":"2024-01-01T00:00:01.000000Z"}]}"#;
+
+        connector.on_message(new, &buffer).unwrap();
+        connector.on_message(canceled, &buffer).unwrap();
+        // Forgotten once terminal
+        connector.on_message(canceled, &buffer).unwrap();
+
+        assert_eq!(buffer.len(), 2);
+        buffer.pop();
+        match buffer.pop() {
+            Some(Event::OrderUpdate(u)) => {
+                assert_eq!(u.status, OrderStatus::Canceled);
+                assert_eq!((u.ticker.as_str(), u.side), ("btc/usd", Side::Buy));
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_token_error() {
+        let (rest_url, _rest) = mock_http_server(vec![(
+            200,
+            r#"{"error":["EAPI:Invalid key"]}"#.to_string(),
+        )])
+        .await;
+
+        let res = connector(&rest_url, "ws://127.0.0.1:1").stream().await;
+        assert!(matches!(res, Err(Error::ExchangeError(KrakenError(_)))));
+    }
+}
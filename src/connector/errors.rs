@@ -1,8 +1,12 @@
+use crate::shared::Exchange;
+use std::fmt;
 use std::sync::Arc;
 
+const RAW_EXCERPT_LEN: usize = 256;
+
 #[derive(Debug, thiserror::Error)]
 pub enum ParsingError {
-    #[error("Level2 parsing error")]
+    #[error("Level2 parsing error: {0}")]
     SerdeError(#[from] serde_json::Error),
 
     #[error("URL parsing error: {0}")]
@@ -13,6 +17,9 @@ pub enum ParsingError {
 
     #[error("Converting error: {0}")]
     ConvertingError(String),
+
+    #[error("Unknown message: {0}")]
+    UnknownMessage(String),
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -22,15 +29,51 @@ pub enum WebsocketError {
 
     #[error("Send message failed")]
     SendMessageFailed,
+
+    #[error("No subscription acknowledgement in time")]
+    AckTimeout,
+
+    #[error("Proxy failed: {0}")]
+    ProxyFailed(String),
+
+    #[error("TLS failed: {0}")]
+    TlsFailed(String),
 }
 
 #[derive(Debug, thiserror::Error)]
 pub enum ExchangeError {
-    #[error("KrakenError")]
+    #[error("KrakenError: {0}")]
     KrakenError(String),
 
-    #[error("BinanceError")]
+    #[error("BinanceError: {0}")]
     BinanceError(String),
+
+    #[error("DeribitError: {0}")]
+    DeribitError(String),
+
+    #[error("HyperliquidError: {0}")]
+    HyperliquidError(String),
+
+    #[error("Insufficient balance: {0}")]
+    InsufficientBalance(String),
+
+    #[error("Order not found: {0}")]
+    OrderNotFound(String),
+
+    #[error("Invalid order: {0}")]
+    InvalidOrder(String),
+
+    #[error("Order rejected: {0}")]
+    OrderRejected(String),
+
+    #[error("Rate limited: {0}")]
+    RateLimited(String),
+
+    #[error("Authentication failed: {0}")]
+    AuthenticationFailed(String),
+
+    #[error("Subscription rejected: {0}")]
+    SubscriptionRejected(String),
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -38,10 +81,10 @@ pub enum Error {
     #[error("Parsing failed: {0}")]
     ParsingError(#[from] ParsingError),
 
-    #[error("Websocket disconnected")]
+    #[error("Websocket disconnected: {0}")]
     WebsocketError(#[from] WebsocketError),
 
-    #[error("Builder Error")]
+    #[error("Builder Error: {0}")]
     BuilderError(String),
 
     #[error("HTTP request error: {0}")]
@@ -50,8 +93,161 @@ pub enum Error {
     #[error("Exchange Error: {0}")]
     ExchangeError(#[from] ExchangeError),
 
-    #[error("InternalError")]
+    #[error("InternalError: {0}")]
     InternalError(String),
+
+    #[error("{context}: {source}")]
+    WithContext {
+        context: ErrorContext,
+        source: Box<Error>,
+    },
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum ErrorClass {
+    Parsing,
+    UnknownMessage,
+    Exchange,
+    Connection,
+    Internal,
+}
+
+impl ErrorClass {
+    pub fn to_str(self) -> &'static str {
+        match self {
+            ErrorClass::Parsing => "parsing",
+            ErrorClass::UnknownMessage => "unknown_message",
+            ErrorClass::Exchange => "exchange",
+            ErrorClass::Connection => "connection",
+            ErrorClass::Internal => "internal",
+        }
+    }
+
+    /// Errors caused by the payload itself, worth keeping the message for an audit
+    pub fn is_feed_format(self) -> bool {
+        matches!(self, ErrorClass::Parsing | ErrorClass::UnknownMessage)
+    }
+}
+
+/// Where an error happened: filled by the connector stream around `on_message`
+#[derive(Debug, Clone)]
+pub struct ErrorContext {
+    pub exchange: Exchange,
+    pub connection_id: u64,
+    pub class: ErrorClass,
+    pub raw_excerpt: String,
+}
+
+impl ErrorContext {
+    pub fn new(exchange: Exchange, connection_id: u64, class: ErrorClass, raw: &str) -> Self {
+        Self {
+            exchange,
+            connection_id,
+            class,
+            raw_excerpt: excerpt(raw, RAW_EXCERPT_LEN),
+        }
+    }
+}
+
+impl fmt::Display for ErrorContext {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "[{} #{} {}]",
+            self.exchange.to_str(),
+            self.connection_id,
+            self.class.to_str()
+        )?;
+        if !self.raw_excerpt.is_empty() {
+            write!(f, " raw: {}", self.raw_excerpt)?;
+        }
+        Ok(())
+    }
+}
+
+fn excerpt(raw: &str, max_chars: usize) -> String {
+    match raw.char_indices().nth(max_chars) {
+        Some((idx, _)) => format!("{}...", &raw[..idx]),
+        None => raw.to_string(),
+    }
+}
+
+impl Error {
+    pub fn class(&self) -> ErrorClass {
+        match self {
+            Error::ParsingError(ParsingError::UnknownMessage(_)) => ErrorClass::UnknownMessage,
+            Error::ParsingError(_) => ErrorClass::Parsing,
+            Error::ExchangeError(_) => ErrorClass::Exchange,
+            Error::WebsocketError(_) | Error::RequestError(_) => ErrorClass::Connection,
+            Error::BuilderError(_) | Error::InternalError(_) => ErrorClass::Internal,
+            Error::WithContext { context, .. } => context.class,
+        }
+    }
+
+    pub fn context(&self) -> Option<&ErrorContext> {
+        match self {
+            Error::WithContext { context, .. } => Some(context),
+            _ => None,
+        }
+    }
+
+    pub fn with_context(self, exchange: Exchange, connection_id: u64, raw: &str) -> Self {
+        if let Error::WithContext { .. } = self {
+            return self;
+        }
+        let context = ErrorContext::new(exchange, connection_id, self.class(), raw);
+        Error::WithContext {
+            context,
+            source: Box::new(self),
+        }
+    }
 }
 
 pub type ErrorHandler = Arc<dyn Fn(&Error) + Send + Sync>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_class() {
+        let err: Error = ParsingError::UnknownMessage("x".to_string()).into();
+        assert_eq!(err.class(), ErrorClass::UnknownMessage);
+        let err: Error = ExchangeError::KrakenError("x".to_string()).into();
+        assert_eq!(err.class(), ErrorClass::Exchange);
+        let err: Error = WebsocketError::ConnectionFailed.into();
+        assert_eq!(err.class(), ErrorClass::Connection);
+    }
+
+    #[test]
+    fn test_messages_carry_payload() {
+        let err: Error = ExchangeError::KrakenError("EOrder:Unknown order".to_string()).into();
+        assert_eq!(err.to_string(), "Exchange Error: KrakenError: EOrder:Unknown order");
+        let err = Error::BuilderError("missing url".to_string());
+        assert_eq!(err.to_string(), "Builder Error: missing url");
+    }
+
+    #[test]
+    fn test_with_context() {
+        let raw = "x".repeat(1000);
+        let err: Error = ParsingError::MessageParsingError("bad".to_string()).into();
+        let err = err.with_context(Exchange::Kraken, 7, &raw);
+
+        let context = err.context().unwrap();
+        assert_eq!(context.connection_id, 7);
+        assert_eq!(context.class, ErrorClass::Parsing);
+        assert_eq!(context.raw_excerpt.len(), RAW_EXCERPT_LEN + 3);
+        assert!(err.to_string().starts_with("[kraken #7 parsing] raw: xxx"));
+        assert!(err.to_string().ends_with("MessageParsingError error: bad"));
+
+        // Context is attached once
+        let err = err.with_context(Exchange::Binance, 8, "");
+        assert_eq!(err.context().unwrap().connection_id, 7);
+    }
+
+    #[test]
+    fn test_excerpt_keeps_char_boundaries() {
+        assert_eq!(excerpt("привет", 3), "при...");
+        assert_eq!(excerpt("abc", 3), "abc");
+    }
+}
@@ -1,13 +1,23 @@
 mod connector;
 mod connector_binance;
-mod errors;
+mod connector_binance_user;
+pub(crate) mod errors;
 mod connector_kraken;
+mod connector_kraken_l3;
+mod connector_kraken_user;
+mod connector_deribit;
+mod connector_hyperliquid;
 mod builder;
-mod config;
+pub(crate) mod config;
 
-mod services;
+pub(crate) mod services;
 
 pub use connector::{Connector, Event};
-pub(crate) use connector_binance::{BinanceConnector};
+pub(crate) use connector_binance::{convert_ticker_into_binance_symbol, BinanceConnector, SymbolCase};
+pub(crate) use connector_binance_user::{BinanceUserConnector};
 pub(crate) use connector_kraken::{KrakenConnector};
+pub(crate) use connector_kraken_l3::{KrakenL3Connector};
+pub(crate) use connector_kraken_user::{KrakenUserConnector};
+pub(crate) use connector_deribit::{DeribitConnector};
+pub(crate) use connector_hyperliquid::{HyperliquidConnector};
 pub use builder::{StreamConnector};
\ No newline at end of file
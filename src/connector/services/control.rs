@@ -0,0 +1,170 @@
+use crate::connector::errors::ExchangeError::SubscriptionRejected;
+use crate::connector::errors::{Error, WebsocketError};
+use crate::connector::services::parser::parse_serde_object;
+use crate::connector::services::websocket::ConnStream;
+use crate::shared::logger::Logger;
+use futures_util::StreamExt;
+use serde_json::{Map, Value};
+use std::time::Duration;
+use tokio::time::{timeout_at, Instant};
+use tokio_tungstenite::tungstenite::Message;
+
+const ACK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Exchange replies to our own requests, as opposed to market data
+#[derive(Debug, Clone, PartialEq)]
+pub enum ControlMessage {
+    Subscribed { id: Option<u64>, channel: Option<String> },
+    Rejected { id: Option<u64>, reason: String },
+}
+
+pub type ControlParser = fn(&Map<String, Value>) -> Option<ControlMessage>;
+
+/// `{"result":null,"id":1}` or `{"error":{"code":2,"msg":"..."},"id":1}`
+pub fn parse_binance_control(obj: &Map<String, Value>) -> Option<ControlMessage> {
+    let id = obj.get("id")?.as_u64();
+    if let Some(error) = obj.get("error") {
+        let reason = error
+            .get("msg")
+            .and_then(|m| m.as_str())
+            .map(|m| m.to_string())
+            .unwrap_or_else(|| error.to_string());
+        return Some(ControlMessage::Rejected { id, reason });
+    }
+    obj.contains_key("result")
+        .then_some(ControlMessage::Subscribed { id, channel: None })
+}
+
+/// `{"method":"subscribe","result":{"channel":"book",..},"success":true,..}`
+pub fn parse_kraken_control(obj: &Map<String, Value>) -> Option<ControlMessage> {
+    obj.get("method")?;
+    let id = obj.get("req_id").and_then(|v| v.as_u64());
+    if obj.get("success").and_then(|s| s.as_bool()) == Some(true) {
+        let channel = obj
+            .get("result")
+            .and_then(|r| r.get("channel"))
+            .and_then(|c| c.as_str())
+            .map(|c| c.to_string());
+        return Some(ControlMessage::Subscribed { id, channel });
+    }
+    let reason = obj
+        .get("error")
+        .and_then(|e| e.as_str())
+        .unwrap_or("unknown reason")
+        .to_string();
+    Some(ControlMessage::Rejected { id, reason })
+}
+
+/// Reads the socket until `expected` subscriptions are confirmed.
+/// Fails on the first rejection. Data that arrives in between is returned to be processed later.
+pub async fn await_subscriptions(
+    read: &mut ConnStream,
+    expected: usize,
+    parser: ControlParser,
+    logger: &Logger,
+) -> Result<Vec<String>, Error> {
+    let deadline = Instant::now() + ACK_TIMEOUT;
+    let mut pending = Vec::new();
+    let mut confirmed = 0;
+
+    while confirmed < expected {
+        let msg = timeout_at(deadline, read.next())
+            .await
+            .map_err(|_| WebsocketError::AckTimeout)?;
+        let txt = match msg {
+            Some(Ok(Message::Text(txt))) => txt,
+            Some(Ok(_)) => continue,
+            Some(Err(_)) | None => Err(WebsocketError::ConnectionFailed)?,
+        };
+
+        let control = parse_serde_object(&txt).ok().and_then(|obj| parser(&obj));
+        match control {
+            Some(ControlMessage::Subscribed { channel, .. }) => {
+                confirmed += 1;
+                logger.debug(&format!("Subscription confirmed {}", channel.unwrap_or_default()));
+            }
+            Some(ControlMessage::Rejected { reason, .. }) => Err(SubscriptionRejected(reason))?,
+            None => pending.push(txt),
+        }
+    }
+
+    Ok(pending)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::connector::services::mock_server::mock_ws_server;
+    use crate::connector::config::NetworkConfig;
+    use crate::connector::services::websocket::connect_websocket;
+    use crate::connector::errors::ExchangeError;
+    use tracing::Level;
+
+    fn obj(raw: &str) -> Map<String, Value> {
+        parse_serde_object(raw).unwrap()
+    }
+
+    #[test]
+    fn test_parse_binance() {
+        assert_eq!(
+            parse_binance_control(&obj(r#"{"result":null,"id":1}"#)),
+            Some(ControlMessage::Subscribed { id: Some(1), channel: None })
+        );
+        assert_eq!(
+            parse_binance_control(&obj(r#"{"error":{"code":2,"msg":"Invalid request"},"id":3}"#)),
+            Some(ControlMessage::Rejected { id: Some(3), reason: "Invalid request".to_string() })
+        );
+        assert_eq!(parse_binance_control(&obj(r#"{"stream":"x","data":{}}"#)), None);
+    }
+
+    #[test]
+    fn test_parse_kraken() {
+        assert_eq!(
+            parse_kraken_control(&obj(
+                r#"{"method":"subscribe","result":{"channel":"book","symbol":"BTC/USD"},"success":true,"req_id":5}"#
+            )),
+            Some(ControlMessage::Subscribed { id: Some(5), channel: Some("book".to_string()) })
+        );
+        assert_eq!(
+            parse_kraken_control(&obj(
+                r#"{"method":"subscribe","error":"Currency pair not supported XXX/USD","success":false}"#
+            )),
+            Some(ControlMessage::Rejected { id: None, reason: "Currency pair not supported XXX/USD".to_string() })
+        );
+        assert_eq!(parse_kraken_control(&obj(r#"{"channel":"heartbeat"}"#)), None);
+    }
+
+    #[tokio::test]
+    async fn test_await_keeps_early_data() {
+        let logger = Logger::new("test", Level::ERROR);
+        let (url, _server) = mock_ws_server(
+            0,
+            vec![
+                r#"{"channel":"status","data":[]}"#.to_string(),
+                r#"{"method":"subscribe","result":{"channel":"trade"},"success":true}"#.to_string(),
+            ],
+        )
+        .await;
+        let (_write, mut read) = connect_websocket(&url, &NetworkConfig::default(), &logger).await.unwrap();
+
+        let pending = await_subscriptions(&mut read, 1, parse_kraken_control, &logger).await.unwrap();
+        assert_eq!(pending, vec![r#"{"channel":"status","data":[]}"#.to_string()]);
+    }
+
+    #[tokio::test]
+    async fn test_await_fails_on_rejection() {
+        let logger = Logger::new("test", Level::ERROR);
+        let (url, _server) = mock_ws_server(
+            0,
+            vec![r#"{"error":{"code":2,"msg":"Invalid request: unknown stream"},"id":1}"#.to_string()],
+        )
+        .await;
+        let (_write, mut read) = connect_websocket(&url, &NetworkConfig::default(), &logger).await.unwrap();
+
+        let res = await_subscriptions(&mut read, 1, parse_binance_control, &logger).await;
+        assert!(matches!(
+            res,
+            Err(Error::ExchangeError(ExchangeError::SubscriptionRejected(reason))) if reason.contains("unknown stream")
+        ));
+    }
+}
@@ -0,0 +1,396 @@
+use crate::connector::config::{NetworkConfig, ProxyKind, TlsBackend};
+use crate::connector::errors::Error;
+use crate::connector::errors::Error::InternalError;
+use crate::connector::errors::WebsocketError::TlsFailed;
+use crate::connector::services::network::native_roots;
+use crate::shared::logger::Logger;
+use crate::shared::Exchange;
+use reqwest::header::{HeaderMap, RETRY_AFTER};
+use reqwest::{Method, RequestBuilder, Response, StatusCode};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+use tracing::Level;
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+const MAX_RETRIES: u32 = 3;
+const RETRY_BASE: Duration = Duration::from_millis(250);
+// A longer Retry-After is returned to the caller instead of being slept through
+const MAX_RETRY_WAIT: Duration = Duration::from_secs(30);
+const BINANCE_USED_WEIGHT: &str = "x-mbx-used-weight-1m";
+
+/// Token bucket: `capacity` weight units, refilled continuously at `refill_per_sec`
+#[derive(Debug, Clone, Copy)]
+pub struct RateLimit {
+    pub capacity: f64,
+    pub refill_per_sec: f64,
+}
+
+impl RateLimit {
+    /// Binance spot REQUEST_WEIGHT: 6000 per minute
+    pub fn binance() -> Self {
+        Self { capacity: 6000.0, refill_per_sec: 100.0 }
+    }
+
+    /// Kraken call counter: 15 calls, one released every two seconds
+    pub fn kraken() -> Self {
+        Self { capacity: 15.0, refill_per_sec: 0.5 }
+    }
+
+    /// Deribit non-matching-engine credits: a pool of 100 requests, 20 per second
+    pub fn deribit() -> Self {
+        Self { capacity: 100.0, refill_per_sec: 20.0 }
+    }
+
+    /// Hyperliquid REST weight: 1200 per minute per IP
+    pub fn hyperliquid() -> Self {
+        Self { capacity: 1200.0, refill_per_sec: 20.0 }
+    }
+
+    pub fn for_exchange(exchange: &Exchange) -> Self {
+        match exchange {
+            Exchange::Binance => Self::binance(),
+            Exchange::Kraken => Self::kraken(),
+            Exchange::Deribit => Self::deribit(),
+            Exchange::Hyperliquid => Self::hyperliquid(),
+        }
+    }
+}
+
+struct Bucket {
+    limit: RateLimit,
+    tokens: f64,
+    updated: Instant,
+    blocked_until: Option<Instant>,
+}
+
+impl Bucket {
+    fn new(limit: RateLimit, now: Instant) -> Self {
+        Self {
+            limit,
+            tokens: limit.capacity,
+            updated: now,
+            blocked_until: None,
+        }
+    }
+
+    fn refill(&mut self, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.limit.refill_per_sec).min(self.limit.capacity);
+        self.updated = now;
+    }
+
+    /// Spends `weight` and returns zero, or returns how long to wait before trying again
+    fn take(&mut self, weight: f64, now: Instant) -> Duration {
+        if let Some(until) = self.blocked_until {
+            if until > now {
+                return until - now;
+            }
+            self.blocked_until = None;
+        }
+        self.refill(now);
+        // A request heavier than the whole bucket waits for a full one
+        let weight = weight.min(self.limit.capacity);
+        if self.tokens >= weight {
+            self.tokens -= weight;
+            return Duration::ZERO;
+        }
+        Duration::from_secs_f64((weight - self.tokens) / self.limit.refill_per_sec)
+    }
+
+    /// The exchange reported how much of the window is already used, e.g. by another process on the same IP
+    fn sync_used(&mut self, used: f64, now: Instant) {
+        self.refill(now);
+        self.tokens = self.tokens.min((self.limit.capacity - used).max(0.0));
+    }
+
+    fn block(&mut self, until: Instant) {
+        self.blocked_until = Some(self.blocked_until.map_or(until, |prev| prev.max(until)));
+        self.tokens = 0.0;
+    }
+}
+
+fn used_weight(headers: &HeaderMap) -> Option<f64> {
+    headers.get(BINANCE_USED_WEIGHT)?.to_str().ok()?.parse().ok()
+}
+
+fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+    let secs: u64 = headers.get(RETRY_AFTER)?.to_str().ok()?.trim().parse().ok()?;
+    Some(Duration::from_secs(secs))
+}
+
+fn backoff(attempt: u32) -> Duration {
+    RETRY_BASE * 2u32.pow(attempt)
+}
+
+/// HTTP client shared by every REST call. Requests wait for their weight in the exchange's token bucket,
+/// Binance `X-MBX-USED-WEIGHT-1M` headers keep the bucket in sync, and 429/418 answers block the whole exchange
+/// for `Retry-After`. Only GET requests are retried: a repeated order could be executed twice.
+/// Signed requests go through `send_once`, their timestamp or nonce would not survive a retry anyway.
+pub struct HttpClient {
+    client: reqwest::Client,
+    buckets: Mutex<HashMap<&'static str, Bucket>>,
+    logger: Logger,
+}
+
+fn certificates(pem: &[u8]) -> Result<Vec<reqwest::Certificate>, Error> {
+    reqwest::Certificate::from_pem_bundle(pem).map_err(|e| TlsFailed(e.to_string()).into())
+}
+
+type SharedClients = Vec<(NetworkConfig, Arc<HttpClient>)>;
+
+impl HttpClient {
+    pub fn new() -> Self {
+        Self::with_network(&NetworkConfig::default()).expect("default HTTP client configuration is valid")
+    }
+
+    /// Client with the proxy and TLS settings of `network`
+    pub fn with_network(network: &NetworkConfig) -> Result<Self, Error> {
+        let mut builder = reqwest::Client::builder()
+            .timeout(REQUEST_TIMEOUT)
+            .connect_timeout(CONNECT_TIMEOUT);
+
+        builder = match network.tls_backend {
+            TlsBackend::NativeTls => builder.use_native_tls(),
+            TlsBackend::Rustls => {
+                let mut builder = builder.use_rustls_tls();
+                // This build of reqwest has no bundled roots for rustls
+                for cert in native_roots() {
+                    if let Ok(cert) = reqwest::Certificate::from_der(&cert) {
+                        builder = builder.add_root_certificate(cert);
+                    }
+                }
+                builder
+            }
+        };
+        for pem in &network.ca_certificates {
+            for cert in certificates(pem)? {
+                builder = builder.add_root_certificate(cert);
+            }
+        }
+
+        if let Some(proxy) = &network.proxy {
+            // socks5h: the proxy resolves host names, same as the WebSocket dial
+            let scheme = match proxy.kind {
+                ProxyKind::Http => "http",
+                ProxyKind::Socks5 => "socks5h",
+            };
+            let mut reqwest_proxy = reqwest::Proxy::all(format!("{}://{}:{}", scheme, proxy.host, proxy.port))?;
+            if let Some(user) = &proxy.username {
+                reqwest_proxy = reqwest_proxy.basic_auth(user, proxy.password.as_deref().unwrap_or(""));
+            }
+            builder = builder.proxy(reqwest_proxy);
+        }
+
+        Ok(Self {
+            client: builder.build()?,
+            buckets: Mutex::new(HashMap::new()),
+            logger: Logger::new("http", Level::INFO),
+        })
+    }
+
+    /// Process-wide client for `network`, so all connectors and services behind the same egress
+    /// spend from the same buckets
+    pub fn shared(network: &NetworkConfig) -> Result<Arc<HttpClient>, Error> {
+        static SHARED: OnceLock<Mutex<SharedClients>> = OnceLock::new();
+        let mut clients = SHARED.get_or_init(|| Mutex::new(Vec::new())).lock().unwrap();
+        if let Some((_, client)) = clients.iter().find(|(n, _)| n == network) {
+            return Ok(Arc::clone(client));
+        }
+        let client = Arc::new(HttpClient::with_network(network)?);
+        clients.push((network.clone(), Arc::clone(&client)));
+        Ok(client)
+    }
+
+    /// Shared client without proxy and custom roots
+    pub fn global() -> Arc<HttpClient> {
+        Self::shared(&NetworkConfig::default()).expect("default HTTP client configuration is valid")
+    }
+
+    pub fn request(&self, method: Method, url: &str) -> RequestBuilder {
+        self.client.request(method, url)
+    }
+
+    pub fn get(&self, url: &str) -> RequestBuilder {
+        self.request(Method::GET, url)
+    }
+
+    pub fn post(&self, url: &str) -> RequestBuilder {
+        self.request(Method::POST, url)
+    }
+
+    pub fn put(&self, url: &str) -> RequestBuilder {
+        self.request(Method::PUT, url)
+    }
+
+    fn with_bucket<R>(&self, exchange: &Exchange, f: impl FnOnce(&mut Bucket, Instant) -> R) -> R {
+        let now = Instant::now();
+        let mut buckets = self.buckets.lock().unwrap();
+        let bucket = buckets
+            .entry(exchange.to_str())
+            .or_insert_with(|| Bucket::new(RateLimit::for_exchange(exchange), now));
+        f(bucket, now)
+    }
+
+    async fn acquire(&self, exchange: &Exchange, weight: u32) {
+        loop {
+            let wait = self.with_bucket(exchange, |bucket, now| bucket.take(weight as f64, now));
+            if wait.is_zero() {
+                return;
+            }
+            self.logger.debug(&format!("{} rate limit, waiting {:?}", exchange.to_str(), wait));
+            tokio::time::sleep(wait).await;
+        }
+    }
+
+    /// Sends `request` once `weight` is available. 429/418 and 5xx answers are returned as they are
+    /// when they cannot be retried, so callers keep mapping exchange error bodies themselves.
+    pub async fn send(&self, exchange: &Exchange, weight: u32, request: RequestBuilder) -> Result<Response, Error> {
+        let request = request.build()?;
+        let retryable = request.method() == Method::GET;
+        self.execute(exchange, weight, request, retryable).await
+    }
+
+    /// Same limits as `send`, without retries
+    pub async fn send_once(&self, exchange: &Exchange, weight: u32, request: RequestBuilder) -> Result<Response, Error> {
+        self.execute(exchange, weight, request.build()?, false).await
+    }
+
+    async fn execute(
+        &self,
+        exchange: &Exchange,
+        weight: u32,
+        request: reqwest::Request,
+        retryable: bool,
+    ) -> Result<Response, Error> {
+        let mut attempt = 0;
+
+        loop {
+            let attempt_request = request
+                .try_clone()
+                .ok_or_else(|| InternalError("Request body cannot be cloned".to_string()))?;
+            self.acquire(exchange, weight).await;
+
+            let resp = match self.client.execute(attempt_request).await {
+                Ok(resp) => resp,
+                Err(err) if retryable && attempt < MAX_RETRIES && (err.is_timeout() || err.is_connect()) => {
+                    self.logger.warn(&format!("{} {}: {}, retrying", exchange.to_str(), request.url().path(), err));
+                    tokio::time::sleep(backoff(attempt)).await;
+                    attempt += 1;
+                    continue;
+                }
+                Err(err) => return Err(err.into()),
+            };
+
+            if let Some(used) = used_weight(resp.headers()) {
+                self.with_bucket(exchange, |bucket, now| bucket.sync_used(used, now));
+            }
+
+            let status = resp.status();
+            let wait = if status == StatusCode::TOO_MANY_REQUESTS || status.as_u16() == 418 {
+                let wait = retry_after(resp.headers()).unwrap_or_else(|| backoff(attempt));
+                self.with_bucket(exchange, |bucket, now| bucket.block(now + wait));
+                self.logger.warn(&format!("{} answered {}, blocked for {:?}", exchange.to_str(), status, wait));
+                wait
+            } else if status.is_server_error() {
+                backoff(attempt)
+            } else {
+                return Ok(resp);
+            };
+
+            if !retryable || attempt >= MAX_RETRIES || wait > MAX_RETRY_WAIT {
+                return Ok(resp);
+            }
+            // The bucket already holds rate limited requests back
+            if status.is_server_error() {
+                tokio::time::sleep(wait).await;
+            }
+            attempt += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::connector::config::ProxyConfig;
+    use crate::connector::services::mock_server::{mock_http_server, mock_socks5_proxy};
+    use reqwest::header::HeaderValue;
+
+    #[test]
+    fn test_bucket_take_and_refill() {
+        let start = Instant::now();
+        let mut bucket = Bucket::new(RateLimit { capacity: 10.0, refill_per_sec: 2.0 }, start);
+
+        assert_eq!(bucket.take(8.0, start), Duration::ZERO);
+        assert_eq!(bucket.take(4.0, start), Duration::from_secs(1));
+        assert_eq!(bucket.take(4.0, start + Duration::from_secs(1)), Duration::ZERO);
+        // Never more than the capacity
+        assert_eq!(bucket.take(10.0, start + Duration::from_secs(100)), Duration::ZERO);
+    }
+
+    #[test]
+    fn test_bucket_sync_and_block() {
+        let start = Instant::now();
+        let mut bucket = Bucket::new(RateLimit { capacity: 100.0, refill_per_sec: 1.0 }, start);
+
+        bucket.sync_used(95.0, start);
+        assert_eq!(bucket.take(10.0, start), Duration::from_secs(5));
+
+        bucket.block(start + Duration::from_secs(30));
+        assert_eq!(bucket.take(1.0, start + Duration::from_secs(10)), Duration::from_secs(20));
+        assert_eq!(bucket.take(1.0, start + Duration::from_secs(30)), Duration::ZERO);
+    }
+
+    #[test]
+    fn test_headers() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(used_weight(&headers), None);
+        headers.insert("X-MBX-USED-WEIGHT-1M", HeaderValue::from_static("1200"));
+        headers.insert(RETRY_AFTER, HeaderValue::from_static("7"));
+        assert_eq!(used_weight(&headers), Some(1200.0));
+        assert_eq!(retry_after(&headers), Some(Duration::from_secs(7)));
+    }
+
+    #[tokio::test]
+    async fn test_get_is_retried() {
+        let (url, server) = mock_http_server(vec![(503, "{}".to_string()), (200, "[]".to_string())]).await;
+        let http = HttpClient::new();
+
+        let resp = http.send(&Exchange::Binance, 1, http.get(&url)).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(server.await.unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_post_is_not_retried() {
+        let (url, server) = mock_http_server(vec![(429, "{}".to_string())]).await;
+        let http = HttpClient::new();
+
+        let resp = http.send(&Exchange::Kraken, 1, http.post(&url)).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(server.await.unwrap().len(), 1);
+
+        // Other requests to the exchange wait out the block
+        let wait = http.with_bucket(&Exchange::Kraken, |bucket, now| bucket.take(1.0, now));
+        assert!(wait > Duration::ZERO);
+    }
+
+    #[tokio::test]
+    async fn test_request_through_socks5() {
+        let (url, server) = mock_http_server(vec![(200, "[]".to_string())]).await;
+        let (proxy, requests) = mock_socks5_proxy().await;
+        let network = NetworkConfig {
+            proxy: Some(ProxyConfig::parse(&format!("socks5://user:secret@{}", proxy)).unwrap()),
+            ..NetworkConfig::default()
+        };
+        let http = HttpClient::with_network(&network).unwrap();
+
+        let resp = http.send(&Exchange::Binance, 1, http.get(&format!("{}/api/v3/time", url))).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert!(server.await.unwrap()[0].starts_with("GET /api/v3/time HTTP/1.1"));
+        let target = url.trim_start_matches("http://");
+        assert_eq!(requests.await.unwrap(), vec![format!("user:secret {}", target)]);
+    }
+}
@@ -0,0 +1,108 @@
+use crate::connector::errors::Error;
+use crate::connector::errors::Error::InternalError;
+use crate::shared::{Exchange, Price, Quantity, Side};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, OnceLock};
+
+#[derive(Default)]
+struct Levels {
+    bids: HashMap<Price, Quantity>,
+    asks: HashMap<Price, Quantity>,
+}
+
+impl Levels {
+    fn side(&mut self, side: Side) -> &mut HashMap<Price, Quantity> {
+        match side {
+            Side::Buy => &mut self.bids,
+            Side::Sell => &mut self.asks,
+        }
+    }
+}
+
+/// New and changed levels with their quantity, vanished levels with zero quantity
+fn diff_levels(previous: &HashMap<Price, Quantity>, current: &HashMap<Price, Quantity>) -> Vec<(Price, Quantity)> {
+    let mut result: Vec<(Price, Quantity)> = current
+        .iter()
+        .filter(|(price, qty)| previous.get(price) != Some(qty))
+        .map(|(price, qty)| (*price, *qty))
+        .collect();
+    result.extend(
+        previous
+            .keys()
+            .filter(|price| !current.contains_key(price))
+            .map(|price| (*price, 0)),
+    );
+    result.sort_unstable();
+    result
+}
+
+/// Levels already sent downstream per symbol of one exchange. The store outlives connections,
+/// so the first snapshot after a reconnect is diffed against the book consumers still hold and
+/// levels that vanished while disconnected are deleted
+#[derive(Default)]
+pub struct KnownLevels {
+    books: Mutex<HashMap<String, Levels>>,
+}
+
+impl KnownLevels {
+    /// Process-wide store of `exchange`
+    pub fn shared(exchange: &Exchange) -> Arc<KnownLevels> {
+        static SHARED: OnceLock<Mutex<HashMap<&'static str, Arc<KnownLevels>>>> = OnceLock::new();
+        let mut stores = SHARED.get_or_init(|| Mutex::new(HashMap::new())).lock().unwrap();
+        Arc::clone(stores.entry(exchange.to_str()).or_default())
+    }
+
+    /// Replaces the book of `symbol` with a full snapshot and returns what to send downstream,
+    /// bids first, each side by price
+    pub fn replace(
+        &self,
+        symbol: &str,
+        bids: HashMap<Price, Quantity>,
+        asks: HashMap<Price, Quantity>,
+    ) -> Result<Vec<(Side, Price, Quantity)>, Error> {
+        let mut books = self.books.lock().map_err(|e| InternalError(e.to_string()))?;
+        let book = books.entry(symbol.to_string()).or_default();
+
+        let mut result: Vec<(Side, Price, Quantity)> = Vec::new();
+        result.extend(diff_levels(&book.bids, &bids).into_iter().map(|(p, q)| (Side::Buy, p, q)));
+        result.extend(diff_levels(&book.asks, &asks).into_iter().map(|(p, q)| (Side::Sell, p, q)));
+        book.bids = bids;
+        book.asks = asks;
+        Ok(result)
+    }
+
+    /// Records one incremental update of `symbol`, zero quantity removes the level
+    pub fn apply(&self, symbol: &str, side: Side, price: Price, quantity: Quantity) -> Result<(), Error> {
+        let mut books = self.books.lock().map_err(|e| InternalError(e.to_string()))?;
+        let levels = books.entry(symbol.to_string()).or_default().side(side);
+        if quantity == 0 {
+            levels.remove(&price);
+        } else {
+            levels.insert(price, quantity);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_snapshot_diff_covers_incremental_updates() {
+        let levels = KnownLevels::default();
+        let first = levels
+            .replace("BTC", HashMap::from([(100, 1), (99, 2)]), HashMap::from([(101, 1)]))
+            .unwrap();
+        assert_eq!(first, vec![(Side::Buy, 99, 2), (Side::Buy, 100, 1), (Side::Sell, 101, 1)]);
+
+        levels.apply("BTC", Side::Sell, 102, 4).unwrap();
+        levels.apply("BTC", Side::Buy, 99, 0).unwrap();
+
+        // 102 was only known from the incremental update, 99 is already gone downstream
+        let second = levels
+            .replace("BTC", HashMap::from([(100, 1)]), HashMap::from([(101, 3)]))
+            .unwrap();
+        assert_eq!(second, vec![(Side::Sell, 101, 3), (Side::Sell, 102, 0)]);
+    }
+}
@@ -0,0 +1,224 @@
+use futures_util::{SinkExt, StreamExt};
+use rustls_pki_types::pem::PemObject;
+use rustls_pki_types::{CertificateDer, PrivateKeyDer};
+use std::sync::Arc;
+use tokio::io::{copy_bidirectional, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::task::JoinHandle;
+use tokio_rustls::rustls::ServerConfig;
+use tokio_rustls::TlsAcceptor;
+use tokio_tungstenite::accept_async;
+use tokio_tungstenite::tungstenite::Message;
+
+/// CA that signed `LOCALHOST_CERT`, for TLS tests with a custom root
+pub const TEST_CA: &[u8] = include_bytes!("testdata/ca.pem");
+const LOCALHOST_CERT: &[u8] = include_bytes!("testdata/localhost.pem");
+const LOCALHOST_KEY: &[u8] = include_bytes!("testdata/localhost.key");
+
+async fn serve_ws<S: AsyncRead + AsyncWrite + Unpin>(stream: S, expected_requests: usize, responses: Vec<String>) -> Vec<String> {
+    let mut ws = accept_async(stream).await.unwrap();
+
+    let mut requests = Vec::new();
+    while requests.len() < expected_requests {
+        match ws.next().await {
+            Some(Ok(Message::Text(txt))) => requests.push(txt),
+            Some(Ok(_)) => continue,
+            _ => break,
+        }
+    }
+
+    for msg in responses {
+        ws.send(Message::Text(msg)).await.unwrap();
+    }
+    let _ = ws.close(None).await;
+    requests
+}
+
+/// Local WebSocket server for connector tests.
+/// Accepts one connection, waits for `expected_requests` text messages from the client,
+/// replies with `responses` and closes the socket. The handle returns the received requests.
+pub async fn mock_ws_server(
+    expected_requests: usize,
+    responses: Vec<String>,
+) -> (String, JoinHandle<Vec<String>>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("ws://{}", listener.local_addr().unwrap());
+
+    let handle = tokio::spawn(async move {
+        let (tcp, _) = listener.accept().await.unwrap();
+        serve_ws(tcp, expected_requests, responses).await
+    });
+
+    (url, handle)
+}
+
+/// `mock_ws_server` behind TLS with a certificate for `localhost` signed by `TEST_CA`
+pub async fn mock_wss_server(
+    expected_requests: usize,
+    responses: Vec<String>,
+) -> (String, JoinHandle<Vec<String>>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("wss://localhost:{}", listener.local_addr().unwrap().port());
+
+    let certs = CertificateDer::pem_slice_iter(LOCALHOST_CERT)
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+    let key = PrivateKeyDer::from_pem_slice(LOCALHOST_KEY).unwrap();
+    let config = ServerConfig::builder()
+        .with_no_client_auth()
+        .with_single_cert(certs, key)
+        .unwrap();
+    let acceptor = TlsAcceptor::from(Arc::new(config));
+
+    let handle = tokio::spawn(async move {
+        loop {
+            let (tcp, _) = listener.accept().await.unwrap();
+            // A client that does not trust the certificate aborts the handshake, wait for the next one
+            if let Ok(tls) = acceptor.accept(tcp).await {
+                return serve_ws(tls, expected_requests, responses).await;
+            }
+        }
+    });
+
+    (url, handle)
+}
+
+async fn read_until_blank_line(tcp: &mut TcpStream) -> String {
+    let mut head = Vec::new();
+    while !head.ends_with(b"\r\n\r\n") {
+        head.push(tcp.read_u8().await.unwrap());
+    }
+    String::from_utf8_lossy(&head).to_string()
+}
+
+/// Local HTTP CONNECT proxy for one tunnel. The handle returns the CONNECT request
+pub async fn mock_http_proxy() -> (String, JoinHandle<Vec<String>>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap().to_string();
+
+    let handle = tokio::spawn(async move {
+        let (mut client, _) = listener.accept().await.unwrap();
+        let head = read_until_blank_line(&mut client).await;
+        let target = head.split_whitespace().nth(1).unwrap().to_string();
+
+        let mut upstream = TcpStream::connect(&target).await.unwrap();
+        client.write_all(b"HTTP/1.1 200 Connection established\r\n\r\n").await.unwrap();
+        let _ = copy_bidirectional(&mut client, &mut upstream).await;
+        vec![head]
+    });
+
+    (addr, handle)
+}
+
+/// Local SOCKS5 proxy for one tunnel, with optional username/password auth.
+/// The handle returns `"user:pass host:port"`, or `"host:port"` without auth
+pub async fn mock_socks5_proxy() -> (String, JoinHandle<Vec<String>>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap().to_string();
+
+    let handle = tokio::spawn(async move {
+        let (mut client, _) = listener.accept().await.unwrap();
+        let mut record = String::new();
+
+        let mut greeting = [0u8; 2];
+        client.read_exact(&mut greeting).await.unwrap();
+        let mut methods = vec![0u8; greeting[1] as usize];
+        client.read_exact(&mut methods).await.unwrap();
+
+        if methods.contains(&0x02) {
+            client.write_all(&[0x05, 0x02]).await.unwrap();
+            let mut read_field = [0u8; 2];
+            client.read_exact(&mut read_field).await.unwrap();
+            let mut user = vec![0u8; read_field[1] as usize];
+            client.read_exact(&mut user).await.unwrap();
+            let mut pass = vec![0u8; client.read_u8().await.unwrap() as usize];
+            client.read_exact(&mut pass).await.unwrap();
+            client.write_all(&[0x01, 0x00]).await.unwrap();
+            record = format!("{}:{} ", String::from_utf8_lossy(&user), String::from_utf8_lossy(&pass));
+        } else {
+            client.write_all(&[0x05, 0x00]).await.unwrap();
+        }
+
+        let mut request = [0u8; 4];
+        client.read_exact(&mut request).await.unwrap();
+        let host = match request[3] {
+            0x03 => {
+                let mut host = vec![0u8; client.read_u8().await.unwrap() as usize];
+                client.read_exact(&mut host).await.unwrap();
+                String::from_utf8_lossy(&host).into_owned()
+            }
+            0x01 => {
+                let mut ip = [0u8; 4];
+                client.read_exact(&mut ip).await.unwrap();
+                std::net::Ipv4Addr::from(ip).to_string()
+            }
+            other => panic!("mock expects a domain name or IPv4, got address type {}", other),
+        };
+        let port = client.read_u16().await.unwrap();
+        let target = format!("{}:{}", host, port);
+        record.push_str(&target);
+
+        let mut upstream = TcpStream::connect(&target).await.unwrap();
+        client.write_all(&[0x05, 0x00, 0x00, 0x01, 127, 0, 0, 1, 0, 0]).await.unwrap();
+        let _ = copy_bidirectional(&mut client, &mut upstream).await;
+        vec![record]
+    });
+
+    (addr, handle)
+}
+
+/// Local HTTP server for REST calls in connector tests.
+/// Answers one request per entry of `responses` (status, JSON body), closing the connection
+/// after each reply. The handle returns the raw requests, headers and body included.
+pub async fn mock_http_server(responses: Vec<(u16, String)>) -> (String, JoinHandle<Vec<String>>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+
+    let handle = tokio::spawn(async move {
+        let mut requests = Vec::new();
+        for (status, body) in responses {
+            let (mut tcp, _) = listener.accept().await.unwrap();
+            requests.push(read_http_request(&mut tcp).await);
+
+            let reply = format!(
+                "HTTP/1.1 {} OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                status,
+                body.len(),
+                body
+            );
+            tcp.write_all(reply.as_bytes()).await.unwrap();
+            let _ = tcp.shutdown().await;
+        }
+        requests
+    });
+
+    (url, handle)
+}
+
+async fn read_http_request(tcp: &mut TcpStream) -> String {
+    let mut raw = Vec::new();
+    let mut chunk = [0u8; 4096];
+    loop {
+        let n = tcp.read(&mut chunk).await.unwrap();
+        if n == 0 {
+            break;
+        }
+        raw.extend_from_slice(&chunk[..n]);
+
+        let text = String::from_utf8_lossy(&raw);
+        if let Some(end) = text.find("\r\n\r\n") {
+            let content_length = text[..end]
+                .lines()
+                .find_map(|l| {
+                    let (name, value) = l.split_once(':')?;
+                    name.eq_ignore_ascii_case("content-length")
+                        .then(|| value.trim().parse::<usize>().ok())?
+                })
+                .unwrap_or(0);
+            if raw.len() >= end + 4 + content_length {
+                break;
+            }
+        }
+    }
+    String::from_utf8_lossy(&raw).to_string()
+}
@@ -1,4 +1,13 @@
+pub mod control;
+pub mod http;
+pub mod known_levels;
+pub mod network;
 pub mod parser;
 pub mod ticker_map;
 pub mod websocket;
-pub mod other;
\ No newline at end of file
+pub mod other;
+pub mod signing;
+pub mod symbols;
+
+#[cfg(test)]
+pub mod mock_server;
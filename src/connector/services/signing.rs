@@ -0,0 +1,66 @@
+use crate::connector::errors::Error;
+use crate::connector::errors::Error::BuilderError;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use hmac::{Hmac, Mac};
+use sha2::{Digest, Sha256, Sha512};
+
+/// Binance `signature` parameter: hex encoded HMAC-SHA256 of the query string
+pub fn binance_signature(query: &str, secret: &str) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes())
+        .expect("HMAC accepts keys of any length");
+    mac.update(query.as_bytes());
+    mac.finalize()
+        .into_bytes()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// Kraken `API-Sign`: HMAC-SHA512 of `path + SHA256(nonce + body)` keyed with the base64 decoded secret
+pub fn kraken_signature(path: &str, nonce: u64, body: &str, secret: &str) -> Result<String, Error> {
+    let key = STANDARD
+        .decode(secret)
+        .map_err(|_| BuilderError("Kraken API secret must be base64".to_string()))?;
+
+    let sha = Sha256::digest(format!("{}{}", nonce, body).as_bytes());
+    let mut mac = Hmac::<Sha512>::new_from_slice(&key)
+        .map_err(|e| BuilderError(e.to_string()))?;
+    mac.update(path.as_bytes());
+    mac.update(&sha);
+
+    Ok(STANDARD.encode(mac.finalize().into_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_binance_signature() {
+        // Example from the Binance SIGNED endpoint docs
+        let secret = "NhqPtmdSJYdKjVHjA7PZj4Mge3R5YNiP1e3UZjInClVN65XAbvqqM6A7H5fATj0j";
+        let query = "symbol=LTCBTC&side=BUY&type=LIMIT&timeInForce=GTC&quantity=1&price=0.1&recvWindow=5000&timestamp=1499827319559";
+        assert_eq!(
+            binance_signature(query, secret),
+            "c8db56825ae71d6d79447849e617115f4a920fa2acdcab2b053c4b2838bd6b71"
+        );
+    }
+
+    #[test]
+    fn test_kraken_signature() {
+        // Example from the Kraken REST authentication docs
+        let secret = "kQH5HW/8p1uGOVjbgWA7FunAmGO8lsSUXNsu3eow76sz84Q18fWxnyRzBHCd3pd5nE9qa99HAZtuZuj6F1huXg==";
+        let body = "nonce=1616492376594&ordertype=limit&pair=XBTUSD&price=37500&type=buy&volume=1.25";
+        let sign = kraken_signature("/0/private/AddOrder", 1616492376594, body, secret).unwrap();
+        assert_eq!(
+            sign,
+            "4/dpxb3iT4tp/ZCVEwSnEsLxx0bqyhLpdfOpc6fn7OR8+UClSV5n9E6aSS8MPtnRfp32bAb0nmbRn6H8ndwLUQ=="
+        );
+    }
+
+    #[test]
+    fn test_kraken_signature_bad_secret() {
+        assert!(kraken_signature("/0/private/AddOrder", 1, "nonce=1", "not base64!").is_err());
+    }
+}
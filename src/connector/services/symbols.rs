@@ -0,0 +1,157 @@
+use crate::connector::errors::Error;
+use crate::shared::logger::Logger;
+use crate::shared::Exchange;
+use std::collections::{HashMap, HashSet};
+use std::future::Future;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+const DEFAULT_TTL: Duration = Duration::from_secs(60 * 60);
+
+pub type SymbolSet = Arc<HashSet<String>>;
+
+/// Tradable symbols per exchange. Lists are kept in memory for `ttl` and mirrored to `dir`,
+/// so a restart without access to the REST API still validates against the last known list.
+pub struct SymbolCache {
+    dir: PathBuf,
+    ttl: Duration,
+    lists: Mutex<HashMap<&'static str, (Instant, SymbolSet)>>,
+}
+
+impl SymbolCache {
+    pub fn new(dir: PathBuf, ttl: Duration) -> Self {
+        Self {
+            dir,
+            ttl,
+            lists: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Process-wide cache shared by all connectors
+    pub fn global() -> Arc<SymbolCache> {
+        static GLOBAL: OnceLock<Arc<SymbolCache>> = OnceLock::new();
+        GLOBAL
+            .get_or_init(|| {
+                let dir = std::env::temp_dir().join("spoofer-symbols");
+                Arc::new(SymbolCache::new(dir, DEFAULT_TTL))
+            })
+            .clone()
+    }
+
+    #[cfg(test)]
+    pub fn temporary() -> Arc<SymbolCache> {
+        use std::sync::atomic::{AtomicU64, Ordering};
+        static COUNTER: AtomicU64 = AtomicU64::new(0);
+        let dir = std::env::temp_dir().join(format!(
+            "spoofer-symbols-test-{}-{}",
+            std::process::id(),
+            COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        Arc::new(SymbolCache::new(dir, DEFAULT_TTL))
+    }
+
+    fn file(&self, exchange: &Exchange) -> PathBuf {
+        self.dir.join(format!("{}.json", exchange.to_str()))
+    }
+
+    fn read_file(&self, exchange: &Exchange) -> Option<HashSet<String>> {
+        let raw = std::fs::read_to_string(self.file(exchange)).ok()?;
+        serde_json::from_str(&raw).ok()
+    }
+
+    fn write_file(&self, exchange: &Exchange, symbols: &HashSet<String>) -> std::io::Result<()> {
+        std::fs::create_dir_all(&self.dir)?;
+        let mut sorted: Vec<&String> = symbols.iter().collect();
+        sorted.sort();
+        std::fs::write(self.file(exchange), serde_json::to_string(&sorted)?)
+    }
+
+    fn remember(&self, exchange: &Exchange, symbols: HashSet<String>) -> SymbolSet {
+        let symbols = Arc::new(symbols);
+        self.lists
+            .lock()
+            .unwrap()
+            .insert(exchange.to_str(), (Instant::now(), symbols.clone()));
+        symbols
+    }
+
+    pub async fn get_or_fetch<F, Fut>(&self, exchange: &Exchange, fetch: F, logger: &Logger) -> Result<SymbolSet, Error>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<HashSet<String>, Error>>,
+    {
+        if let Some((at, symbols)) = self.lists.lock().unwrap().get(exchange.to_str()) {
+            if at.elapsed() < self.ttl {
+                return Ok(symbols.clone());
+            }
+        }
+
+        match fetch().await {
+            Ok(symbols) => {
+                if let Err(err) = self.write_file(exchange, &symbols) {
+                    logger.warn(&format!("Cannot save symbol list: {}", err));
+                }
+                Ok(self.remember(exchange, symbols))
+            }
+            Err(err) => match self.read_file(exchange) {
+                Some(symbols) => {
+                    logger.warn(&format!("Symbol list request failed, using the saved one: {}", err));
+                    Ok(self.remember(exchange, symbols))
+                }
+                None => Err(err),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::connector::errors::Error::InternalError;
+    use tracing::Level;
+
+    fn set(items: &[&str]) -> HashSet<String> {
+        items.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[tokio::test]
+    async fn test_cached_in_memory() {
+        let cache = SymbolCache::temporary();
+        let logger = Logger::new("test", Level::ERROR);
+
+        let first = cache
+            .get_or_fetch(&Exchange::Kraken, || async { Ok(set(&["BTC/USD"])) }, &logger)
+            .await
+            .unwrap();
+        let second = cache
+            .get_or_fetch(&Exchange::Kraken, || async { panic!("must not refetch") }, &logger)
+            .await
+            .unwrap();
+        assert_eq!(first, second);
+    }
+
+    #[tokio::test]
+    async fn test_offline_fallback() {
+        let cache = SymbolCache::temporary();
+        let logger = Logger::new("test", Level::ERROR);
+        cache
+            .get_or_fetch(&Exchange::Binance, || async { Ok(set(&["btcusdt"])) }, &logger)
+            .await
+            .unwrap();
+
+        // Fresh process, same directory, no network
+        let offline = SymbolCache::new(cache.dir.clone(), DEFAULT_TTL);
+        let symbols = offline
+            .get_or_fetch(&Exchange::Binance, || async { Err(InternalError("offline".to_string())) }, &logger)
+            .await
+            .unwrap();
+        assert!(symbols.contains("btcusdt"));
+
+        let res = offline
+            .get_or_fetch(&Exchange::Kraken, || async { Err(InternalError("offline".to_string())) }, &logger)
+            .await;
+        assert!(res.is_err());
+        let _ = std::fs::remove_dir_all(&cache.dir);
+    }
+}
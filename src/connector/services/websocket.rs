@@ -1,28 +1,40 @@
+use crate::connector::config::NetworkConfig;
 use crate::connector::errors::Error::InternalError;
 use crate::connector::errors::{Error, ParsingError, WebsocketError};
+use crate::connector::services::network::{dial, wrap_tls, BoxedStream};
 use async_stream::try_stream;
 use futures::Stream;
 use futures_util::stream::{SplitSink, SplitStream};
 use futures_util::{SinkExt, StreamExt};
 use std::time::Duration;
-use tokio::net::TcpStream;
 use tokio::time::sleep;
 use tokio_tungstenite::tungstenite::Message;
-use tokio_tungstenite::{connect_async, MaybeTlsStream, WebSocketStream};
+use tokio_tungstenite::{client_async, WebSocketStream};
 use url::Url;
 use crate::shared::logger::Logger;
 
 pub type Connection = (
-    SplitSink<WebSocketStream<MaybeTlsStream<TcpStream>>, Message>,
-    SplitStream<WebSocketStream<MaybeTlsStream<TcpStream>>>,
+    SplitSink<WebSocketStream<BoxedStream>, Message>,
+    SplitStream<WebSocketStream<BoxedStream>>,
 );
 
-pub async fn connect_websocket(url: &str, logger: &Logger) -> Result<Connection, Error> {
+pub async fn connect_websocket(url: &str, network: &NetworkConfig, logger: &Logger) -> Result<Connection, Error> {
     logger.info(&format!("Connecting to WS: {}", url));
 
     let parsed_url = Url::parse(url).map_err(|e| Error::from(ParsingError::UrlParseError(e)))?;
+    let host = parsed_url
+        .host_str()
+        .ok_or_else(|| InternalError(format!("No host in {}", url)))?;
+    let port = parsed_url
+        .port_or_known_default()
+        .ok_or_else(|| InternalError(format!("No port in {}", url)))?;
 
-    let (ws_stream, _) = connect_async(parsed_url)
+    let tcp = dial(network, host, port).await?;
+    let stream: BoxedStream = match parsed_url.scheme() {
+        "wss" => wrap_tls(network, host, tcp).await?,
+        _ => Box::new(tcp),
+    };
+    let (ws_stream, _) = client_async(url, stream)
         .await
         .map_err(|_| WebsocketError::ConnectionFailed)?;
 
@@ -30,8 +42,8 @@ pub async fn connect_websocket(url: &str, logger: &Logger) -> Result<Connection,
     Ok(ws_stream.split())
 }
 
-pub type ConnSink = SplitSink<WebSocketStream<MaybeTlsStream<TcpStream>>, Message>;
-pub type ConnStream = SplitStream<WebSocketStream<MaybeTlsStream<TcpStream>>>;
+pub type ConnSink = SplitSink<WebSocketStream<BoxedStream>, Message>;
+pub type ConnStream = SplitStream<WebSocketStream<BoxedStream>>;
 
 pub async fn send_ws_message(sink: &mut ConnSink, msg: Message) -> Result<(), Error> {
     sink.send(msg)
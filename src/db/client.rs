@@ -1,5 +1,8 @@
+use crate::bbo::create_best_bid_ask_table;
+use crate::candle::create_candles_table;
+use crate::dead_letter::create_dead_letters_table;
 use crate::db::errors::Error;
-use crate::level2::create_level_updates_table;
+use crate::level2::{create_book_metrics_table, create_level_updates_table, create_order_book_snapshots_table};
 use crate::shared::logger::Logger;
 use crate::signal::arbitrage_monitor::create_arbitrage_signals_table;
 use crate::trade::create_trade_event_table;
@@ -53,7 +56,12 @@ async fn init_database(client: &Client, db_name: &str, recreate: bool) -> Result
         drop_all_tables(client, &logger, db_name).await?;
     }
     create_level_updates_table(client, &logger, db_name).await?;
+    create_book_metrics_table(client, &logger, db_name).await?;
+    create_order_book_snapshots_table(client, &logger, db_name).await?;
     create_trade_event_table(client, &logger, db_name).await?;
+    create_best_bid_ask_table(client, &logger, db_name).await?;
+    create_candles_table(client, &logger, db_name).await?;
+    create_dead_letters_table(client, &logger, db_name).await?;
     create_arbitrage_signals_table(&client, &logger, db_name).await?;
     logger.info("Successful database initialisation");
     Ok(())
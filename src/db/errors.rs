@@ -9,6 +9,15 @@ pub enum Error {
     #[error("TradeError: {0}")]
     TradeError(#[from] crate::trade::TradeError),
 
+    #[error("BboError: {0}")]
+    BboError(#[from] crate::bbo::BboError),
+
+    #[error("CandleError: {0}")]
+    CandleError(#[from] crate::candle::CandleError),
+
+    #[error("DeadLetterError: {0}")]
+    DeadLetterError(#[from] crate::dead_letter::DeadLetterError),
+
 
     #[error("SignalError: {0}")]
     SignalError(#[from] crate::signal::error::Error),
@@ -0,0 +1,13 @@
+use crate::connector::errors::ErrorClass;
+use crate::shared::{Exchange, TimestampNS};
+
+/// Message a connector could not parse or did not recognise, kept whole for an audit
+#[derive(Debug, Clone)]
+pub struct DeadLetter {
+    pub exchange: Exchange,
+    pub connection_id: u64,
+    pub class: ErrorClass,
+    pub error: String,
+    pub raw: String,
+    pub received: TimestampNS,
+}
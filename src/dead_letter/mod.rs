@@ -0,0 +1,7 @@
+mod errors;
+mod events;
+mod repo;
+
+pub use errors::DeadLetterError;
+pub use events::DeadLetter;
+pub use repo::{create_dead_letters_table, DeadLetterRepo};
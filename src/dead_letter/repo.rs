@@ -0,0 +1,86 @@
+use clickhouse::{insert::Insert, Client, Row};
+use serde::Serialize;
+
+use crate::dead_letter::{DeadLetter, DeadLetterError};
+use crate::shared::logger::Logger;
+use crate::shared::utils::buffer_service::Callback;
+
+#[derive(Row, Serialize)]
+struct DeadLetterRow {
+    exchange: u8,
+    connection_id: u64,
+    class: String,
+    error: String,
+    raw: String,
+    received: u64,
+}
+
+impl DeadLetterRow {
+    pub fn from_dead_letter(ev: &DeadLetter) -> Self {
+        Self {
+            exchange: ev.exchange.clone() as u8,
+            connection_id: ev.connection_id,
+            class: ev.class.to_str().to_string(),
+            error: ev.error.clone(),
+            raw: ev.raw.clone(),
+            received: ev.received,
+        }
+    }
+}
+
+pub struct DeadLetterRepo<'a> {
+    client: &'a Client,
+}
+
+impl<'a> DeadLetterRepo<'a> {
+    pub fn new(client: &'a Client) -> Self {
+        Self { client }
+    }
+
+    pub async fn save(&self, events: &[DeadLetter]) -> Result<(), DeadLetterError> {
+        if events.is_empty() {
+            return Ok(());
+        }
+
+        let mut insert: Insert<DeadLetterRow> = self.client.insert("dead_letters").await?;
+
+        for ev in events {
+            insert.write(&DeadLetterRow::from_dead_letter(ev)).await?;
+        }
+
+        insert.end().await?;
+        Ok(())
+    }
+}
+
+impl<'a> Callback<DeadLetter, DeadLetterError> for DeadLetterRepo<'a> {
+    async fn on_buffer_flush(&self, data: &[DeadLetter]) -> Result<(), DeadLetterError> {
+        self.save(data).await
+    }
+}
+
+pub async fn create_dead_letters_table(
+    client: &Client,
+    logger: &Logger,
+    db_name: &str,
+) -> Result<(), DeadLetterError> {
+    logger.info("Creating dead letters table");
+
+    let query = format!(
+        r#"
+        CREATE TABLE IF NOT EXISTS {}.dead_letters (
+            exchange UInt8,
+            connection_id UInt64,
+            class LowCardinality(String),
+            error String,
+            raw String,
+            received UInt64
+        ) ENGINE = MergeTree()
+        ORDER BY (exchange, class, received)
+    "#,
+        db_name
+    );
+
+    client.query(&query).execute().await?;
+    Ok(())
+}
@@ -0,0 +1,32 @@
+use crate::shared::{Exchange, Price, Quantity, TimestampMS, TimestampNS};
+use std::sync::Arc;
+
+#[derive(Debug, Clone)]
+pub struct OptionGreeks {
+    pub delta: f64,
+    pub gamma: f64,
+    pub vega: f64,
+    pub theta: f64,
+    pub rho: f64,
+}
+
+#[derive(Debug, Clone)]
+pub struct DerivativeTicker {
+    pub exchange: Exchange,
+    pub ticker: Arc<String>,
+    pub mark_price: Price,
+    pub index_price: Price,
+    pub best_bid_price: Price,
+    pub best_bid_quantity: Quantity,
+    pub best_ask_price: Price,
+    pub best_ask_quantity: Quantity,
+    pub open_interest: f64,
+    // Options only
+    pub underlying_price: Option<Price>,
+    pub mark_iv: Option<f64>,
+    pub bid_iv: Option<f64>,
+    pub ask_iv: Option<f64>,
+    pub greeks: Option<OptionGreeks>,
+    pub timestamp: TimestampMS,
+    pub received: TimestampNS,
+}
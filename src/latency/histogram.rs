@@ -0,0 +1,167 @@
+/// Exact buckets below this many microseconds, log-linear above
+const LINEAR: u64 = 64;
+/// Sub-buckets per power of two: values are kept with at most 1/32 (~3%) relative error
+const SUB_BITS: u32 = 5;
+const SUB_BUCKETS: usize = 1 << SUB_BITS;
+/// Up to 2^36 us, about 19 hours. Larger values land in the last bucket
+const MAX_EXPONENT: u32 = 36;
+const BUCKETS: usize = LINEAR as usize + (MAX_EXPONENT as usize - 6) * SUB_BUCKETS;
+
+/// Counts of absolute values in microseconds
+#[derive(Clone)]
+struct Buckets {
+    counts: Vec<u64>,
+}
+
+impl Buckets {
+    fn new() -> Self {
+        Self { counts: vec![0; BUCKETS] }
+    }
+
+    fn index(us: u64) -> usize {
+        if us < LINEAR {
+            return us as usize;
+        }
+        let exponent = (63 - us.leading_zeros()).min(MAX_EXPONENT - 1);
+        let sub = ((us >> (exponent - SUB_BITS)) as usize) & (SUB_BUCKETS - 1);
+        LINEAR as usize + (exponent as usize - 6) * SUB_BUCKETS + sub
+    }
+
+    /// Middle of the bucket
+    fn value(index: usize) -> u64 {
+        if index < LINEAR as usize {
+            return index as u64;
+        }
+        let exponent = ((index - LINEAR as usize) / SUB_BUCKETS) as u32 + 6;
+        let sub = ((index - LINEAR as usize) % SUB_BUCKETS) as u64;
+        let width = 1u64 << (exponent - SUB_BITS);
+        (1u64 << exponent) + sub * width + width / 2
+    }
+}
+
+/// Histogram of signed delays in microseconds. A delay is negative when the exchange clock is
+/// ahead of ours, so negative values get their own buckets.
+#[derive(Clone)]
+pub struct LatencyHistogram {
+    negative: Buckets,
+    positive: Buckets,
+    count: u64,
+    sum: i128,
+    min: i64,
+    max: i64,
+}
+
+impl LatencyHistogram {
+    pub fn new() -> Self {
+        Self {
+            negative: Buckets::new(),
+            positive: Buckets::new(),
+            count: 0,
+            sum: 0,
+            min: i64::MAX,
+            max: i64::MIN,
+        }
+    }
+
+    pub fn record(&mut self, delay_us: i64) {
+        if delay_us < 0 {
+            self.negative.counts[Buckets::index(delay_us.unsigned_abs())] += 1;
+        } else {
+            self.positive.counts[Buckets::index(delay_us as u64)] += 1;
+        }
+        self.count += 1;
+        self.sum += delay_us as i128;
+        self.min = self.min.min(delay_us);
+        self.max = self.max.max(delay_us);
+    }
+
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    pub fn min(&self) -> Option<i64> {
+        (self.count > 0).then_some(self.min)
+    }
+
+    pub fn max(&self) -> Option<i64> {
+        (self.count > 0).then_some(self.max)
+    }
+
+    pub fn mean(&self) -> Option<f64> {
+        (self.count > 0).then(|| self.sum as f64 / self.count as f64)
+    }
+
+    /// Value below which `q` (0.0..=1.0) of the delays fall, clamped to the observed min and max
+    pub fn percentile(&self, q: f64) -> Option<i64> {
+        if self.count == 0 {
+            return None;
+        }
+        let rank = ((q.clamp(0.0, 1.0) * self.count as f64).ceil() as u64).max(1);
+        // The extremes are known exactly
+        if rank == 1 {
+            return Some(self.min);
+        }
+        if rank == self.count {
+            return Some(self.max);
+        }
+        let mut seen = 0;
+        // Most negative first
+        for (i, n) in self.negative.counts.iter().enumerate().rev() {
+            seen += n;
+            if seen >= rank {
+                return Some((-(Buckets::value(i) as i64)).clamp(self.min, self.max));
+            }
+        }
+        for (i, n) in self.positive.counts.iter().enumerate() {
+            seen += n;
+            if seen >= rank {
+                return Some((Buckets::value(i) as i64).clamp(self.min, self.max));
+            }
+        }
+        Some(self.max)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_buckets_round_trip() {
+        for us in [0, 1, 63, 64, 65, 1_000, 12_345, 250_000, 3_600_000_000] {
+            let value = Buckets::value(Buckets::index(us));
+            let error = (value as f64 - us as f64).abs() / (us.max(1) as f64);
+            assert!(error <= 1.0 / 32.0, "{} -> {}", us, value);
+        }
+        assert_eq!(Buckets::index(u64::MAX), BUCKETS - 1);
+    }
+
+    #[test]
+    fn test_percentiles() {
+        let mut histogram = LatencyHistogram::new();
+        assert_eq!(histogram.percentile(0.5), None);
+
+        for ms in 1..=100 {
+            histogram.record(ms * 1_000);
+        }
+        let p50 = histogram.percentile(0.5).unwrap();
+        let p99 = histogram.percentile(0.99).unwrap();
+        assert!((48_500..=51_500).contains(&p50), "{}", p50);
+        assert!((96_000..=100_000).contains(&p99), "{}", p99);
+        assert_eq!(histogram.percentile(1.0), Some(100_000));
+        assert_eq!(histogram.mean(), Some(50_500.0));
+    }
+
+    #[test]
+    fn test_negative_delays() {
+        let mut histogram = LatencyHistogram::new();
+        for us in [-5_000, -2_000, 1_000, 3_000] {
+            histogram.record(us);
+        }
+        assert_eq!(histogram.min(), Some(-5_000));
+        assert_eq!(histogram.percentile(0.0), Some(-5_000));
+        let p50 = histogram.percentile(0.5).unwrap();
+        assert!((-2_100..=-1_900).contains(&p50), "{}", p50);
+        assert_eq!(histogram.percentile(1.0), Some(3_000));
+    }
+}
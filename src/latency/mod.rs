@@ -0,0 +1,5 @@
+mod histogram;
+mod tracker;
+
+pub use histogram::LatencyHistogram;
+pub use tracker::{Channel, LatencyStats, LatencyTracker};
@@ -0,0 +1,242 @@
+use crate::connector::Event;
+use crate::latency::LatencyHistogram;
+use crate::level3::OrderAction;
+use crate::shared::logger::Logger;
+use crate::shared::{Exchange, TimestampMS, TimestampNS};
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use tracing::Level;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Channel {
+    Trade,
+    Level2,
+    BestBidAsk,
+    Candle,
+    Level3,
+    Derivatives,
+    Account,
+}
+
+impl Channel {
+    pub fn to_str(self) -> &'static str {
+        match self {
+            Channel::Trade => "trade",
+            Channel::Level2 => "level2",
+            Channel::BestBidAsk => "bbo",
+            Channel::Candle => "candle",
+            Channel::Level3 => "level3",
+            Channel::Derivatives => "derivatives",
+            Channel::Account => "account",
+        }
+    }
+}
+
+/// Exchange, channel and both timestamps of an event, `None` when the event has no exchange time
+fn sample(event: &Event) -> Option<(&Exchange, Channel, TimestampMS, TimestampNS)> {
+    match event {
+        Event::Trade(v) => Some((&v.exchange, Channel::Trade, v.timestamp, v.received)),
+        Event::LevelUpdate(v) => Some((&v.exchange, Channel::Level2, v.timestamp, v.received)),
+        // Binance bookTicker has no event time and Kraken tickers may lack one
+        Event::BestBidAsk(v) if v.timestamp == 0 => None,
+        Event::BestBidAsk(v) => Some((&v.exchange, Channel::BestBidAsk, v.timestamp, v.received)),
+        Event::Candle(v) => Some((&v.exchange, Channel::Candle, v.timestamp, v.received)),
+        Event::Order(v) if v.action == OrderAction::Clear => None,
+        Event::Order(v) => Some((&v.exchange, Channel::Level3, v.timestamp, v.received)),
+        Event::DerivativeTicker(v) => Some((&v.exchange, Channel::Derivatives, v.timestamp, v.received)),
+        Event::OrderUpdate(v) => Some((&v.exchange, Channel::Account, v.timestamp, v.received)),
+        Event::Fill(v) => Some((&v.exchange, Channel::Account, v.timestamp, v.received)),
+        Event::BalanceUpdate(v) => Some((&v.exchange, Channel::Account, v.timestamp, v.received)),
+        Event::DeadLetter(_) => None,
+    }
+}
+
+/// Receive delay of one exchange channel in milliseconds
+#[derive(Debug, Clone, PartialEq)]
+pub struct LatencyStats {
+    pub exchange: Exchange,
+    pub channel: Channel,
+    pub count: u64,
+    pub min_ms: f64,
+    pub p50_ms: f64,
+    pub p90_ms: f64,
+    pub p99_ms: f64,
+    pub max_ms: f64,
+    /// Clock skew estimate of the exchange, see `LatencyTracker::clock_skew_ms`
+    pub skew_ms: f64,
+}
+
+/// Measures `received - timestamp` of every event per exchange and channel.
+/// With `report_every` the histograms are logged and cleared periodically, so each log line
+/// and `stats()` cover the events since the previous report.
+pub struct LatencyTracker {
+    channels: HashMap<(&'static str, Channel), (Exchange, LatencyHistogram)>,
+    interval: Option<Duration>,
+    last_report: Instant,
+    logger: Logger,
+}
+
+fn ms(us: i64) -> f64 {
+    us as f64 / 1_000.0
+}
+
+impl LatencyTracker {
+    pub fn new() -> Self {
+        Self {
+            channels: HashMap::new(),
+            interval: None,
+            last_report: Instant::now(),
+            logger: Logger::new("latency", Level::INFO),
+        }
+    }
+
+    pub fn report_every(mut self, interval: Duration) -> Self {
+        self.interval = Some(interval);
+        self
+    }
+
+    pub fn observe(&mut self, event: &Event) {
+        if let Some((exchange, channel, timestamp, received)) = sample(event) {
+            self.record(exchange, channel, timestamp, received);
+        }
+    }
+
+    pub fn record(&mut self, exchange: &Exchange, channel: Channel, timestamp: TimestampMS, received: TimestampNS) {
+        let delay_us = (received / 1_000) as i64 - (timestamp * 1_000) as i64;
+        self.channels
+            .entry((exchange.to_str(), channel))
+            .or_insert_with(|| (exchange.clone(), LatencyHistogram::new()))
+            .1
+            .record(delay_us);
+    }
+
+    /// Delay percentile in milliseconds, `q` from 0.0 to 1.0
+    pub fn percentile(&self, exchange: &Exchange, channel: Channel, q: f64) -> Option<f64> {
+        let (_, histogram) = self.channels.get(&(exchange.to_str(), channel))?;
+        histogram.percentile(q).map(ms)
+    }
+
+    /// The smallest delay seen on the trade and level2 channels of the exchange, in milliseconds.
+    /// It is the local clock minus the exchange clock plus the fastest network path: negative
+    /// means our clock is behind by at least that much, and delays above it are network and
+    /// queueing jitter. Other channels may carry events stamped locally when the exchange sends
+    /// no time, which would pull the estimate to zero.
+    pub fn clock_skew_ms(&self, exchange: &Exchange) -> Option<f64> {
+        self.channels
+            .iter()
+            .filter(|((name, channel), _)| {
+                *name == exchange.to_str() && matches!(channel, Channel::Trade | Channel::Level2)
+            })
+            .filter_map(|(_, (_, histogram))| histogram.min())
+            .min()
+            .map(ms)
+    }
+
+    /// One entry per exchange and channel with events, ordered by exchange and channel
+    pub fn stats(&self) -> Vec<LatencyStats> {
+        let mut stats: Vec<LatencyStats> = self
+            .channels
+            .iter()
+            .filter(|(_, (_, histogram))| histogram.count() > 0)
+            .map(|((_, channel), (exchange, histogram))| LatencyStats {
+                exchange: exchange.clone(),
+                channel: *channel,
+                count: histogram.count(),
+                min_ms: ms(histogram.min().unwrap_or_default()),
+                p50_ms: ms(histogram.percentile(0.5).unwrap_or_default()),
+                p90_ms: ms(histogram.percentile(0.9).unwrap_or_default()),
+                p99_ms: ms(histogram.percentile(0.99).unwrap_or_default()),
+                max_ms: ms(histogram.max().unwrap_or_default()),
+                skew_ms: self.clock_skew_ms(exchange).unwrap_or_default(),
+            })
+            .collect();
+        stats.sort_by_key(|s| (s.exchange.clone() as u8, s.channel));
+        stats
+    }
+
+    /// Logs `stats()` and starts a new window once the report interval has passed
+    pub fn report_if_due(&mut self) {
+        let Some(interval) = self.interval else { return };
+        if self.last_report.elapsed() < interval {
+            return;
+        }
+        for s in self.stats() {
+            self.logger.info(&format!(
+                "{} {}: n={} p50={:.1}ms p90={:.1}ms p99={:.1}ms max={:.1}ms skew={:.1}ms",
+                s.exchange.to_str(),
+                s.channel.to_str(),
+                s.count,
+                s.p50_ms,
+                s.p90_ms,
+                s.p99_ms,
+                s.max_ms,
+                s.skew_ms
+            ));
+        }
+        self.channels.clear();
+        self.last_report = Instant::now();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::trade::TradeEvent;
+    use crate::shared::Side;
+    use std::sync::Arc;
+
+    fn trade(exchange: Exchange, timestamp: TimestampMS, delay_ms: u64) -> Event {
+        Event::Trade(TradeEvent {
+            exchange,
+            ticker: Arc::new("btc/usdt".to_string()),
+            trade_id: 1,
+            price: 1,
+            quantity: 1,
+            timestamp,
+            received: (timestamp + delay_ms) * 1_000_000,
+            market_maker: Side::Buy,
+        })
+    }
+
+    #[test]
+    fn test_stats_per_exchange_and_channel() {
+        let mut tracker = LatencyTracker::new();
+        for delay in 10..=50 {
+            tracker.observe(&trade(Exchange::Binance, 1_700_000_000_000, delay));
+        }
+        tracker.record(&Exchange::Kraken, Channel::Level2, 1_700_000_000_000, 1_700_000_000_120 * 1_000_000);
+
+        let stats = tracker.stats();
+        assert_eq!(stats.len(), 2);
+        assert_eq!((stats[0].exchange.clone(), stats[0].channel, stats[0].count), (Exchange::Binance, Channel::Trade, 41));
+        assert_eq!((stats[0].min_ms, stats[0].max_ms), (10.0, 50.0));
+        assert!((29.0..=31.0).contains(&stats[0].p50_ms), "{}", stats[0].p50_ms);
+        assert_eq!((stats[1].exchange.clone(), stats[1].channel), (Exchange::Kraken, Channel::Level2));
+        assert_eq!(tracker.percentile(&Exchange::Kraken, Channel::Level2, 0.5), Some(120.0));
+        assert_eq!(tracker.percentile(&Exchange::Kraken, Channel::Trade, 0.5), None);
+    }
+
+    #[test]
+    fn test_clock_skew() {
+        let mut tracker = LatencyTracker::new();
+        // Exchange clock 30ms ahead of ours: the fastest event seems to arrive before it was sent
+        tracker.record(&Exchange::Kraken, Channel::Trade, 1_000_030, 1_000_000 * 1_000_000);
+        tracker.record(&Exchange::Kraken, Channel::Level2, 1_000_030, 1_000_015 * 1_000_000);
+        tracker.record(&Exchange::Binance, Channel::Trade, 1_000_000, 1_000_005 * 1_000_000);
+        // Locally stamped events show no delay and are left out
+        tracker.record(&Exchange::Binance, Channel::Account, 1_000_000, 1_000_000 * 1_000_000);
+
+        assert_eq!(tracker.clock_skew_ms(&Exchange::Kraken), Some(-30.0));
+        assert_eq!(tracker.clock_skew_ms(&Exchange::Binance), Some(5.0));
+        assert_eq!(tracker.clock_skew_ms(&Exchange::Deribit), None);
+        assert!(tracker.stats().iter().filter(|s| s.exchange == Exchange::Kraken).all(|s| s.skew_ms == -30.0));
+    }
+
+    #[test]
+    fn test_report_starts_new_window() {
+        let mut tracker = LatencyTracker::new().report_every(Duration::ZERO);
+        tracker.observe(&trade(Exchange::Binance, 1_000, 5));
+        tracker.report_if_due();
+        assert!(tracker.stats().is_empty());
+    }
+}
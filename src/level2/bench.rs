@@ -0,0 +1,141 @@
+use crate::level2::levels::LADDER_SLOTS;
+use crate::level2::{BookHistory, DepthLevel, LadderLevels, Level2Error, LevelUpdated, OrderBook, PriceLevels, TreeLevels};
+use crate::shared::{Exchange, Price, Side, TimestampNS};
+use std::time::{Duration, Instant};
+
+const PAGE: usize = 10_000;
+
+/// Fastest of the rounds one book implementation took to apply the recorded updates
+#[derive(Debug, Clone)]
+pub struct BenchResult {
+    pub name: &'static str,
+    pub updates: usize,
+    pub elapsed: Duration,
+}
+
+impl BenchResult {
+    pub fn ns_per_update(&self) -> f64 {
+        self.elapsed.as_nanos() as f64 / self.updates.max(1) as f64
+    }
+}
+
+/// Every stored update with `from < received <= until`, oldest first
+pub async fn load_updates<H: BookHistory>(
+    history: &H,
+    exchange: &Exchange,
+    ticker: &str,
+    from: TimestampNS,
+    until: TimestampNS,
+) -> Result<Vec<LevelUpdated>, Level2Error> {
+    let mut updates = Vec::new();
+    let mut after = from;
+    loop {
+        let page = history.updates_after(exchange, ticker, after, until, PAGE).await?;
+        let done = page.len() < PAGE;
+        if let Some(last) = page.last() {
+            after = last.received;
+        }
+        updates.extend(page);
+        if done {
+            return Ok(updates);
+        }
+    }
+}
+
+/// Largest price step all updates share, the ladder needs one slot per tick
+pub fn tick_of(updates: &[LevelUpdated]) -> Price {
+    fn gcd(a: Price, b: Price) -> Price {
+        if b == 0 { a } else { gcd(b, a % b) }
+    }
+    updates.iter().fold(0, |tick, u| gcd(tick, u.price)).max(1)
+}
+
+fn run<L: PriceLevels>(
+    name: &'static str,
+    updates: &[LevelUpdated],
+    rounds: usize,
+    make: impl Fn() -> OrderBook<L>,
+) -> (BenchResult, Vec<DepthLevel>) {
+    let mut best = Duration::MAX;
+    let mut depth = Vec::new();
+    for _ in 0..rounds.max(1) {
+        let mut book = make();
+        let started = Instant::now();
+        for update in updates {
+            book.update_or_miss(update);
+        }
+        best = best.min(started.elapsed());
+        depth = book.depth(Side::Buy, usize::MAX);
+        depth.extend(book.depth(Side::Sell, usize::MAX));
+    }
+    (BenchResult { name, updates: updates.len(), elapsed: best }, depth)
+}
+
+/// Applies `updates` to a tree book and a ladder book `rounds` times each. Fails when the two
+/// end up with different levels
+pub fn compare_levels(
+    updates: &[LevelUpdated],
+    max_depth: usize,
+    buffer: usize,
+    rounds: usize,
+) -> Result<Vec<BenchResult>, String> {
+    let Some(first) = updates.first() else {
+        return Ok(Vec::new());
+    };
+    let (exchange, ticker) = (first.exchange.clone(), first.ticker.as_str());
+    let tick = tick_of(updates);
+
+    let (tree, tree_depth) = run("tree", updates, rounds, || {
+        let (bids, asks) = (TreeLevels::new(Side::Buy), TreeLevels::new(Side::Sell));
+        OrderBook::with_levels(exchange.clone(), ticker, max_depth, bids, asks).with_buffer(buffer)
+    });
+    let (ladder, ladder_depth) = run("ladder", updates, rounds, || {
+        let bids = LadderLevels::with_tick(Side::Buy, tick, LADDER_SLOTS);
+        let asks = LadderLevels::with_tick(Side::Sell, tick, LADDER_SLOTS);
+        OrderBook::with_levels(exchange.clone(), ticker, max_depth, bids, asks).with_buffer(buffer)
+    });
+    if tree_depth != ladder_depth {
+        return Err("tree and ladder books ended up with different levels".to_string());
+    }
+    Ok(vec![tree, ladder])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_compare_on_random_walk() {
+        let ticker = Arc::new("btc/usdt".to_string());
+        let mut seed: u64 = 3;
+        let updates: Vec<LevelUpdated> = (0..20_000u64)
+            .map(|i| {
+                seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+                let mid = 50_000 + i / 100;
+                let side = if seed >> 63 == 0 { Side::Buy } else { Side::Sell };
+                let offset = (seed >> 40) % 200;
+                let price = match side {
+                    Side::Buy => mid - 1 - offset,
+                    Side::Sell => mid + 1 + offset,
+                };
+                LevelUpdated {
+                    exchange: Exchange::Binance,
+                    ticker: Arc::clone(&ticker),
+                    side,
+                    price: price * 10,
+                    quantity: (seed >> 20) % 4,
+                    timestamp: 0,
+                    received: i + 1,
+                    sequence: 0,
+                    prev_sequence: 0,
+                }
+            })
+            .collect();
+
+        assert_eq!(tick_of(&updates), 10);
+        let results = compare_levels(&updates, 20, 50, 1).unwrap();
+        assert_eq!(results.iter().map(|r| r.name).collect::<Vec<_>>(), vec!["tree", "ladder"]);
+        assert!(results.iter().all(|r| r.updates == 20_000));
+    }
+}
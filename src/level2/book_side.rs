@@ -1,70 +1,142 @@
+use crate::level2::levels::{PriceLevels, TreeLevels};
 use crate::level2::{Level2Error, LevelUpdated};
 use crate::shared::errors::check_side;
 use crate::shared::{Price, Quantity, Side};
-use either::Either;
-use std::collections::hash_map::Entry;
-use std::collections::{BTreeSet, HashMap};
-
-pub struct BookSide {
-    levels: HashMap<Price, Quantity>,
-    sorted_prices: BTreeSet<Price>,
-    best_price: Option<Price>,
+
+/// One level of a depth view. The cumulative figures include this level and every better one
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DepthLevel {
+    pub price: Price,
+    pub quantity: Quantity,
+    pub cumulative_quantity: Quantity,
+    /// Sum of price * quantity in the scaled units of the ticker config
+    pub cumulative_notional: u128,
+}
+
+pub struct BookSide<L: PriceLevels = TreeLevels> {
+    levels: L,
     side: Side,
+    /// Levels shown by the depth views
     max_depth: usize,
+    /// Levels kept, the ones past `max_depth` refill the view when better levels go away
+    buffer_depth: usize,
+    /// Worst price the side can vouch for once levels were evicted: every level from the best
+    /// price up to it is known. Levels past it may have changed unseen
+    boundary: Option<Price>,
+    /// Number of best levels summed into `top_quantity`, 0 when not tracked
+    top_depth: usize,
+    top_quantity: Quantity,
 }
 
 impl BookSide {
     pub fn new(side: Side, max_depth: usize) -> Self {
+        Self::with_levels(TreeLevels::new(side), side, max_depth)
+    }
+}
+
+impl<L: PriceLevels> BookSide<L> {
+    pub fn with_levels(levels: L, side: Side, max_depth: usize) -> Self {
         Self {
-            levels: HashMap::new(),
-            sorted_prices: BTreeSet::new(),
+            levels,
             side,
             max_depth,
-            best_price: None,
+            buffer_depth: max_depth,
+            boundary: None,
+            top_depth: 0,
+            top_quantity: 0,
+        }
+    }
+
+    /// Keeps up to `depth` levels (at least `max_depth`) of which only `max_depth` are shown
+    pub fn set_buffer(&mut self, depth: usize) {
+        self.buffer_depth = depth.max(self.max_depth);
+    }
+
+    /// Keeps the total quantity of the best `depth` levels up to date on every change
+    pub fn track_top(&mut self, depth: usize) {
+        let depth = depth.min(self.max_depth);
+        self.top_depth = depth;
+        self.top_quantity = self.levels.iter().take(depth).map(|(_, qty)| qty).sum();
+    }
+
+    pub fn top_quantity(&self) -> Quantity {
+        self.top_quantity
+    }
+
+    /// Whether `price` ranks among the tracked best levels, counting only levels strictly better
+    pub(crate) fn in_top(&self, price: Price) -> bool {
+        let better = self.levels.iter().take_while(|&(p, _)| p != price && self.at_or_better(p, price));
+        better.take(self.top_depth).count() < self.top_depth
+    }
+
+    /// Quantity of the level at 0-based `rank` from the best price
+    fn quantity_at_rank(&self, rank: usize) -> Option<Quantity> {
+        self.levels.iter().nth(rank).map(|(_, qty)| qty)
+    }
+
+    /// At or better than `limit`
+    fn at_or_better(&self, price: Price, limit: Price) -> bool {
+        match self.side {
+            Side::Buy => price >= limit,
+            Side::Sell => price <= limit,
         }
     }
 
     fn remove_level(&mut self, price: Price) {
-        self.sorted_prices.remove(&price);
-        self.levels.remove(&price);
-
-        if Some(price) == self.best_price {
-            self.best_price = match self.side {
-                Side::Buy => self.sorted_prices.iter().rev().next().copied(),
-                Side::Sell => self.sorted_prices.iter().next().copied(),
-            };
+        if let Some(qty) = self.levels.get(price) {
+            if self.in_top(price) {
+                // The level right behind the tracked ones moves up
+                let next = self.quantity_at_rank(self.top_depth).unwrap_or(0);
+                self.top_quantity = self.top_quantity - qty + next;
+            }
+            self.levels.remove(price);
         }
     }
 
     fn insert_or_update_level(&mut self, price: Price, qty: Quantity) {
-        match self.levels.entry(price) {
-            Entry::Vacant(e) => {
-                e.insert(qty);
-                self.sorted_prices.insert(price);
-                self.evict_extra_levels();
+        let known = self.levels.get(price);
+        if known.is_none() && self.levels.len() >= self.buffer_depth {
+            // A new level past a full buffer would be evicted right away
+            if let Some(worst) = self.levels.worst() {
+                if !self.at_or_better(price, worst) {
+                    self.narrow_boundary();
+                    return;
+                }
             }
-            Entry::Occupied(mut e) => {
-                e.insert(qty);
+        }
+        if self.in_top(price) {
+            match known {
+                Some(old) => self.top_quantity = self.top_quantity - old + qty,
+                None => {
+                    // The last tracked level is pushed out
+                    let last = self.quantity_at_rank(self.top_depth - 1).unwrap_or(0);
+                    self.top_quantity = self.top_quantity - last + qty;
+                }
             }
         }
-
-        self.best_price = match self.best_price {
-            Some(current) => match self.side {
-                Side::Buy => Some(current.max(price)),
-                Side::Sell => Some(current.min(price)),
-            },
-            None => Some(price),
-        };
+        if self.levels.insert(price, qty).is_none() {
+            self.evict_extra_levels();
+        }
     }
 
     fn evict_extra_levels(&mut self) {
-        while self.sorted_prices.len() > self.max_depth {
-            let remove_price = match self.side {
-                Side::Buy => *self.sorted_prices.iter().next().unwrap(),
-                Side::Sell => *self.sorted_prices.iter().next_back().unwrap(),
-            };
+        if self.levels.len() <= self.buffer_depth {
+            return;
+        }
+        while self.levels.len() > self.buffer_depth {
+            let remove_price = self.levels.worst().unwrap();
             self.remove_level(remove_price);
         }
+        self.narrow_boundary();
+    }
+
+    /// The known range only ever narrows
+    fn narrow_boundary(&mut self) {
+        let worst_kept = self.levels.worst();
+        self.boundary = match (self.boundary, worst_kept) {
+            (Some(old), Some(new)) if self.at_or_better(old, new) => Some(old),
+            (_, new) => new,
+        };
     }
 
     pub(crate) fn update(&mut self, event: &LevelUpdated) -> Result<(), Level2Error> {
@@ -84,15 +156,68 @@ impl BookSide {
     }
 
     pub fn best_price(&self) -> Option<Price> {
-        self.best_price
+        self.levels.best()
     }
 
-    pub fn best_prices(&self, depth: usize) -> impl Iterator<Item = &Price> {
-        let iter = match self.side {
-            Side::Buy => Either::Left(self.sorted_prices.iter().rev()),
-            Side::Sell => Either::Right(self.sorted_prices.iter()),
-        };
-        iter.take(depth)
+    /// Up to `depth` of the shown levels, best first
+    pub fn best_prices(&self, depth: usize) -> impl Iterator<Item = Price> + '_ {
+        self.levels.iter().take(depth.min(self.max_depth)).map(|(price, _)| price)
+    }
+
+    pub fn boundary(&self) -> Option<Price> {
+        self.boundary
+    }
+
+    /// Shown levels inside the known range
+    pub fn known_depth(&self) -> usize {
+        match self.boundary {
+            Some(limit) => self.best_prices(self.max_depth).take_while(|&p| self.at_or_better(p, limit)).count(),
+            None => self.best_prices(self.max_depth).count(),
+        }
+    }
+
+    /// Fewer than `max_depth` levels can be vouched for since levels were evicted: the hidden
+    /// region past the boundary may hold levels the book no longer knows
+    pub fn is_truncated(&self) -> bool {
+        self.boundary.is_some() && self.known_depth() < self.max_depth
+    }
+
+    /// Up to `depth` levels from the best price with running totals
+    pub fn depth(&self, depth: usize) -> impl Iterator<Item = DepthLevel> + '_ {
+        let mut cumulative_quantity: Quantity = 0;
+        let mut cumulative_notional: u128 = 0;
+        self.levels.iter().take(depth.min(self.max_depth)).map(move |(price, quantity)| {
+            cumulative_quantity += quantity;
+            cumulative_notional += price as u128 * quantity as u128;
+            DepthLevel { price, quantity, cumulative_quantity, cumulative_notional }
+        })
+    }
+
+    /// Every kept level, hidden ones included, best first
+    pub fn levels(&self) -> impl Iterator<Item = (Price, Quantity)> + '_ {
+        self.levels.iter()
+    }
+
+    /// Total quantity and notional of the levels from the best price up to `limit` inclusive:
+    /// bids priced at or above it, asks at or below it
+    pub fn liquidity_to(&self, limit: Price) -> (Quantity, u128) {
+        self.levels
+            .iter()
+            .take(self.max_depth)
+            .take_while(|&(price, _)| self.at_or_better(price, limit))
+            .fold((0, 0), |(quantity, notional), (price, qty)| {
+                (quantity + qty, notional + price as u128 * qty as u128)
+            })
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.boundary = None;
+        self.levels.clear();
+        self.top_quantity = 0;
+    }
+
+    pub fn quantity_at(&self, price: Price) -> Option<Quantity> {
+        self.levels.get(price)
     }
 
     pub fn side(&self) -> &Side {
@@ -100,7 +225,12 @@ impl BookSide {
     }
 
     pub fn is_empty(&self) -> bool {
-        self.sorted_prices.len() == 0
+        self.levels.is_empty()
+    }
+
+    /// Every kept level, hidden ones included
+    pub fn len(&self) -> usize {
+        self.levels.len()
     }
 }
 
@@ -121,6 +251,8 @@ mod tests {
             quantity: qty,
             timestamp: 0,
             received: now_timestamp_ns(),
+            sequence: 0,
+            prev_sequence: 0,
         }
     }
 
@@ -133,11 +265,11 @@ mod tests {
 
         // Проверка уровня и best_price
         assert_eq!(book.best_price().unwrap(), 105);
-        assert_eq!(book.levels[&100], 10);
-        assert_eq!(book.levels[&105], 20);
+        assert_eq!(book.quantity_at(100).unwrap(), 10);
+        assert_eq!(book.quantity_at(105).unwrap(), 20);
 
         // Проверка итератора best_prices
-        let prices: Vec<Price> = book.best_prices(2).copied().collect();
+        let prices: Vec<Price> = book.best_prices(2).collect();
         assert_eq!(prices, vec![105, 100]);
     }
 
@@ -151,7 +283,7 @@ mod tests {
         // Обновление существующего уровня
         book.update(&event(Side::Sell, 200, 60)).unwrap();
 
-        assert_eq!(book.levels[&200], 60);
+        assert_eq!(book.quantity_at(200).unwrap(), 60);
         assert_eq!(book.best_price().unwrap(), 150); // минимальная цена для Sell
     }
 
@@ -165,12 +297,12 @@ mod tests {
         // Удаляем лучший уровень
         book.update(&event(Side::Buy, 105, 0)).unwrap();
         assert_eq!(book.best_price().unwrap(), 100);
-        assert!(!book.levels.contains_key(&105));
+        assert!(book.quantity_at(105).is_none());
 
         // Удаляем последний уровень
         book.update(&event(Side::Buy, 100, 0)).unwrap();
         assert_eq!(book.best_price(), None);
-        assert!(book.levels.is_empty());
+        assert!(book.is_empty());
     }
 
     #[test]
@@ -182,9 +314,9 @@ mod tests {
         book.update(&event(Side::Sell, 110, 10)).unwrap();
         book.update(&event(Side::Sell, 115, 10)).unwrap(); // должен вызвать eviction
 
-        assert_eq!(book.sorted_prices.len(), 3);
+        assert_eq!(book.len(), 3);
         // Для Sell удаляется самый дорогой
-        assert!(!book.levels.contains_key(&115));
+        assert!(book.quantity_at(115).is_none());
         assert_eq!(book.best_price().unwrap(), 100);
     }
 
@@ -194,11 +326,11 @@ mod tests {
 
         // Сторона совпадает
         book.update_or_miss(&event(Side::Buy, 50, 5));
-        assert_eq!(book.levels[&50], 5);
+        assert_eq!(book.quantity_at(50).unwrap(), 5);
 
         // Сторона не совпадает — ничего не делаем
         book.update_or_miss(&event(Side::Sell, 60, 10));
-        assert!(!book.levels.contains_key(&60));
+        assert!(book.quantity_at(60).is_none());
     }
 
     #[test]
@@ -210,11 +342,11 @@ mod tests {
         book.update(&event(Side::Buy, 110, 5)).unwrap();
 
         // depth = 2
-        let prices: Vec<Price> = book.best_prices(2).copied().collect();
+        let prices: Vec<Price> = book.best_prices(2).collect();
         assert_eq!(prices, vec![110, 105]);
 
         // depth больше, чем количество уровней
-        let prices: Vec<Price> = book.best_prices(10).copied().collect();
+        let prices: Vec<Price> = book.best_prices(10).collect();
         assert_eq!(prices, vec![110, 105, 100]);
     }
 
@@ -275,7 +407,7 @@ mod tests {
         // Удаляем последний уровень
         book.update(&event(Side::Buy, 100, 0)).unwrap();
         assert_eq!(book.best_price(), None);
-        assert!(book.levels.is_empty());
+        assert!(book.is_empty());
     }
     #[test]
     fn test_best_price_sell_after_multiple_removes() {
@@ -300,6 +432,97 @@ mod tests {
         // Удаляем последний уровень
         book.update(&event(Side::Sell, 200, 0)).unwrap();
         assert_eq!(book.best_price(), None);
-        assert!(book.levels.is_empty());
+        assert!(book.is_empty());
+    }
+
+    #[test]
+    fn test_depth_cumulative() {
+        let mut book = BookSide::new(Side::Sell, 5);
+        book.update(&event(Side::Sell, 102, 3)).unwrap();
+        book.update(&event(Side::Sell, 100, 10)).unwrap();
+        book.update(&event(Side::Sell, 101, 5)).unwrap();
+
+        let depth: Vec<DepthLevel> = book.depth(2).collect();
+        assert_eq!(
+            depth,
+            vec![
+                DepthLevel { price: 100, quantity: 10, cumulative_quantity: 10, cumulative_notional: 1_000 },
+                DepthLevel { price: 101, quantity: 5, cumulative_quantity: 15, cumulative_notional: 1_505 },
+            ]
+        );
+        assert_eq!(book.depth(10).count(), 3);
+    }
+
+    #[test]
+    fn test_liquidity_to() {
+        let mut book = BookSide::new(Side::Buy, 5);
+        book.update(&event(Side::Buy, 100, 10)).unwrap();
+        book.update(&event(Side::Buy, 99, 5)).unwrap();
+        book.update(&event(Side::Buy, 95, 1)).unwrap();
+
+        assert_eq!(book.liquidity_to(99), (15, 1_495));
+        assert_eq!(book.liquidity_to(101), (0, 0));
+        assert_eq!(book.liquidity_to(0), (16, 1_590));
+    }
+
+    #[test]
+    fn test_top_quantity_matches_full_sum() {
+        for side in [Side::Buy, Side::Sell] {
+            let mut book = BookSide::new(side, 8);
+            book.track_top(3);
+            let mut seed: u64 = 7;
+            for _ in 0..2_000 {
+                seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+                let price = 100 + (seed >> 33) % 15;
+                let qty = (seed >> 20) % 4 * 10;
+                book.update(&event(side, price, qty)).unwrap();
+
+                let expected: Quantity = book.best_prices(3).map(|p| book.quantity_at(p).unwrap()).sum();
+                assert_eq!(book.top_quantity(), expected);
+            }
+        }
+    }
+
+    #[test]
+    fn test_boundary_after_eviction() {
+        let mut book = BookSide::new(Side::Buy, 3);
+        for price in [100, 99, 98] {
+            book.update(&event(Side::Buy, price, 1)).unwrap();
+        }
+        assert_eq!((book.boundary(), book.known_depth()), (None, 3));
+
+        book.update(&event(Side::Buy, 101, 1)).unwrap();
+        assert_eq!(book.boundary(), Some(99));
+        assert!(!book.is_truncated());
+
+        // 98 may still exist at the exchange but the book can no longer tell
+        book.update(&event(Side::Buy, 101, 0)).unwrap();
+        assert_eq!(book.known_depth(), 2);
+        assert!(book.is_truncated());
+
+        // A level past the boundary is kept but not vouched for
+        book.update(&event(Side::Buy, 97, 5)).unwrap();
+        assert_eq!(book.best_prices(3).collect::<Vec<Price>>(), vec![100, 99, 97]);
+        assert_eq!(book.known_depth(), 2);
+
+        book.clear();
+        assert_eq!(book.boundary(), None);
+    }
+
+    #[test]
+    fn test_hidden_buffer_refills_view() {
+        let mut book = BookSide::new(Side::Sell, 2);
+        book.set_buffer(4);
+        for price in [100, 101, 102, 103, 104] {
+            book.update(&event(Side::Sell, price, 1)).unwrap();
+        }
+        assert_eq!(book.best_prices(10).collect::<Vec<Price>>(), vec![100, 101]);
+        assert_eq!(book.boundary(), Some(103));
+        assert_eq!(book.liquidity_to(200), (2, 201));
+
+        book.update(&event(Side::Sell, 100, 0)).unwrap();
+        book.update(&event(Side::Sell, 101, 0)).unwrap();
+        assert_eq!(book.best_prices(10).collect::<Vec<Price>>(), vec![102, 103]);
+        assert!(!book.is_truncated());
     }
 }
@@ -0,0 +1,237 @@
+use crate::level2::fill::{walk, FillEstimate, FillTarget};
+use crate::level2::{DepthLevel, Level2Error, OrderBook, PriceLevels};
+use crate::shared::errors::check_ticker;
+use crate::shared::{Exchange, Price, Quantity, Side};
+use either::Either;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+/// One price of the consolidated book with what each venue shows there
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConsolidatedLevel {
+    pub price: Price,
+    pub quantity: Quantity,
+    /// In the order the venues were added
+    pub venues: Vec<(Exchange, Quantity)>,
+}
+
+/// Market order across venues: the estimate over the merged levels plus what each venue fills
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConsolidatedFill {
+    pub estimate: FillEstimate,
+    pub venues: Vec<(Exchange, Quantity)>,
+}
+
+/// Shown liquidity of one ticker across venues in the multipliers given to `new`. Books
+/// scaled differently are converted on `add`: bid prices round down and ask prices up so no
+/// venue looks better than it is, quantities round down
+pub struct ConsolidatedBook {
+    ticker: Arc<String>,
+    price_multiply: f64,
+    quantity_multiply: f64,
+    bids: BTreeMap<Price, ConsolidatedLevel>,
+    asks: BTreeMap<Price, ConsolidatedLevel>,
+}
+
+impl ConsolidatedBook {
+    pub fn new(ticker: &str, price_multiply: f64, quantity_multiply: f64) -> Self {
+        Self {
+            ticker: Arc::new(ticker.to_string()),
+            price_multiply,
+            quantity_multiply,
+            bids: BTreeMap::new(),
+            asks: BTreeMap::new(),
+        }
+    }
+
+    /// Merges the shown levels of `book`, whose prices and quantities use the given multipliers.
+    /// Books that are not `Valid` are left out and `false` is returned
+    pub fn add<L: PriceLevels>(
+        &mut self,
+        book: &OrderBook<L>,
+        price_multiply: f64,
+        quantity_multiply: f64,
+    ) -> Result<bool, Level2Error> {
+        check_ticker(&self.ticker, book.ticker())?;
+        if !book.is_valid() {
+            return Ok(false);
+        }
+        for side in [Side::Buy, Side::Sell] {
+            for level in book.get_side(side).depth(usize::MAX) {
+                let price = level.price as f64 * self.price_multiply / price_multiply;
+                let price = match side {
+                    Side::Buy => price.floor(),
+                    Side::Sell => price.ceil(),
+                } as Price;
+                let quantity = (level.quantity as f64 * self.quantity_multiply / quantity_multiply).floor() as Quantity;
+                if quantity == 0 {
+                    continue;
+                }
+                let levels = match side {
+                    Side::Buy => &mut self.bids,
+                    Side::Sell => &mut self.asks,
+                };
+                let merged = levels.entry(price).or_insert_with(|| ConsolidatedLevel { price, quantity: 0, venues: Vec::new() });
+                merged.quantity += quantity;
+                match merged.venues.iter_mut().find(|(exchange, _)| exchange == book.exchange()) {
+                    Some((_, venue_quantity)) => *venue_quantity += quantity,
+                    None => merged.venues.push((book.exchange().clone(), quantity)),
+                }
+            }
+        }
+        Ok(true)
+    }
+
+    /// Drops every level, e.g. before merging fresh books
+    pub fn clear(&mut self) {
+        self.bids.clear();
+        self.asks.clear();
+    }
+
+    pub fn ticker(&self) -> &Arc<String> {
+        &self.ticker
+    }
+
+    /// Levels of `side`, best first
+    pub fn levels(&self, side: Side) -> impl Iterator<Item = &ConsolidatedLevel> {
+        match side {
+            Side::Buy => Either::Left(self.bids.values().rev()),
+            Side::Sell => Either::Right(self.asks.values()),
+        }
+    }
+
+    pub fn best_bid(&self) -> Option<&ConsolidatedLevel> {
+        self.levels(Side::Buy).next()
+    }
+
+    pub fn best_ask(&self) -> Option<&ConsolidatedLevel> {
+        self.levels(Side::Sell).next()
+    }
+
+    /// Halfway between the best bid and ask across venues. The two may come from different
+    /// venues and cross
+    pub fn mid_price(&self) -> Option<f64> {
+        Some((self.best_bid()?.price + self.best_ask()?.price) as f64 / 2.0)
+    }
+
+    /// Up to `depth` levels of `side` from the best price with running totals
+    pub fn depth(&self, side: Side, depth: usize) -> Vec<DepthLevel> {
+        let mut cumulative_quantity: Quantity = 0;
+        let mut cumulative_notional: u128 = 0;
+        self.levels(side)
+            .take(depth)
+            .map(|level| {
+                cumulative_quantity += level.quantity;
+                cumulative_notional += level.price as u128 * level.quantity as u128;
+                DepthLevel { price: level.price, quantity: level.quantity, cumulative_quantity, cumulative_notional }
+            })
+            .collect()
+    }
+
+    /// Market order of `quantity` on `side` swept across every venue. Within a level the venues
+    /// fill in the order they were added
+    pub fn cost_to_fill(&self, side: Side, quantity: Quantity) -> ConsolidatedFill {
+        let opposite = match side {
+            Side::Buy => Side::Sell,
+            Side::Sell => Side::Buy,
+        };
+        let estimate = walk(side, self.depth(opposite, usize::MAX).into_iter(), FillTarget::Quantity(quantity), self.mid_price());
+
+        let mut venues: Vec<(Exchange, Quantity)> = Vec::new();
+        let mut remaining = estimate.filled_quantity;
+        for (exchange, available) in self.levels(opposite).flat_map(|level| level.venues.iter()) {
+            if remaining == 0 {
+                break;
+            }
+            let take = remaining.min(*available);
+            remaining -= take;
+            match venues.iter_mut().find(|(e, _)| e == exchange) {
+                Some((_, filled)) => *filled += take,
+                None => venues.push((exchange.clone(), take)),
+            }
+        }
+        ConsolidatedFill { estimate, venues }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::level2::LevelUpdated;
+
+    fn book(exchange: Exchange, levels: &[(Side, Price, Quantity)]) -> OrderBook {
+        let mut book = OrderBook::new(exchange.clone(), "btc/usdt", 10);
+        for &(side, price, quantity) in levels {
+            book.update(&LevelUpdated {
+                exchange: exchange.clone(),
+                ticker: Arc::clone(book.ticker()),
+                side,
+                price,
+                quantity,
+                timestamp: 0,
+                received: 1,
+                sequence: 0,
+                prev_sequence: 0,
+            })
+            .unwrap();
+        }
+        book
+    }
+
+    #[test]
+    fn test_merge_with_different_multipliers() {
+        // Prices in cents, quantities in 1e-6
+        let binance = book(Exchange::Binance, &[(Side::Buy, 10_000, 2_000_000), (Side::Sell, 10_002, 1_000_000)]);
+        // Prices in 1e-3, quantities in 1e-4
+        let kraken = book(Exchange::Kraken, &[(Side::Buy, 100_005, 10_000), (Side::Sell, 100_020, 30_000)]);
+
+        let mut consolidated = ConsolidatedBook::new("btc/usdt", 100.0, 1_000_000.0);
+        assert!(consolidated.add(&binance, 100.0, 1_000_000.0).unwrap());
+        assert!(consolidated.add(&kraken, 1_000.0, 10_000.0).unwrap());
+
+        // 100.005 rounds down on the bid side
+        assert_eq!(
+            consolidated.best_bid(),
+            Some(&ConsolidatedLevel {
+                price: 10_000,
+                quantity: 3_000_000,
+                venues: vec![(Exchange::Binance, 2_000_000), (Exchange::Kraken, 1_000_000)],
+            })
+        );
+        assert_eq!(
+            consolidated.best_ask(),
+            Some(&ConsolidatedLevel {
+                price: 10_002,
+                quantity: 4_000_000,
+                venues: vec![(Exchange::Binance, 1_000_000), (Exchange::Kraken, 3_000_000)],
+            })
+        );
+        assert_eq!(consolidated.mid_price(), Some(10_001.0));
+    }
+
+    #[test]
+    fn test_cost_to_fill_across_venues() {
+        let binance = book(Exchange::Binance, &[(Side::Buy, 99, 5), (Side::Sell, 101, 2), (Side::Sell, 103, 10)]);
+        let kraken = book(Exchange::Kraken, &[(Side::Buy, 98, 5), (Side::Sell, 102, 3)]);
+        let mut consolidated = ConsolidatedBook::new("btc/usdt", 1.0, 1.0);
+        consolidated.add(&binance, 1.0, 1.0).unwrap();
+        consolidated.add(&kraken, 1.0, 1.0).unwrap();
+
+        let fill = consolidated.cost_to_fill(Side::Buy, 6);
+        assert!(fill.estimate.is_complete());
+        assert_eq!(fill.estimate.filled_notional, 2 * 101 + 3 * 102 + 103);
+        assert_eq!(fill.estimate.worst_price, Some(103));
+        assert_eq!(fill.venues, vec![(Exchange::Binance, 3), (Exchange::Kraken, 3)]);
+    }
+
+    #[test]
+    fn test_skips_invalid_and_foreign_books() {
+        let one_sided = book(Exchange::Binance, &[(Side::Buy, 99, 5)]);
+        let other = OrderBook::new(Exchange::Kraken, "eth/usdt", 10);
+        let mut consolidated = ConsolidatedBook::new("btc/usdt", 1.0, 1.0);
+
+        assert!(!consolidated.add(&one_sided, 1.0, 1.0).unwrap());
+        assert!(consolidated.add(&other, 1.0, 1.0).is_err());
+        assert_eq!(consolidated.best_bid(), None);
+    }
+}
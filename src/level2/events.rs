@@ -10,4 +10,8 @@ pub struct LevelUpdated {
     pub quantity: Quantity,
     pub timestamp: TimestampMS,
     pub received: TimestampNS,
+    /// Exchange sequence id of the message carrying the update, 0 when the feed has none
+    pub sequence: u64,
+    /// Sequence the previous message must have ended with, 0 when unknown
+    pub prev_sequence: u64,
 }
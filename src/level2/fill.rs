@@ -0,0 +1,169 @@
+use crate::level2::DepthLevel;
+use crate::shared::{Price, Quantity, Side};
+
+/// Size of a hypothetical market order
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FillTarget {
+    Quantity(Quantity),
+    /// Price * quantity in the scaled units of the ticker config
+    Notional(u128),
+}
+
+/// Result of walking the opposite side of the book with a market order
+#[derive(Debug, Clone, PartialEq)]
+pub struct FillEstimate {
+    /// Side of the order: a buy walks the asks, a sell the bids
+    pub side: Side,
+    pub filled_quantity: Quantity,
+    pub filled_notional: u128,
+    /// Average execution price, `None` when nothing was filled
+    pub vwap: Option<f64>,
+    /// Price of the last level touched
+    pub worst_price: Option<Price>,
+    /// What the displayed depth could not absorb, in the unit of the target. Notional targets fill
+    /// whole quantity units, so a remainder below one unit's price is left over as well
+    pub unfilled: FillTarget,
+    /// VWAP distance from the mid price in basis points, positive when worse than mid
+    pub slippage_bps: Option<f64>,
+}
+
+impl FillEstimate {
+    fn new(side: Side, filled_quantity: Quantity, filled_notional: u128, worst_price: Option<Price>, unfilled: FillTarget, mid: Option<f64>) -> Self {
+        let vwap = (filled_quantity > 0).then(|| filled_notional as f64 / filled_quantity as f64);
+        let slippage_bps = match (vwap, mid) {
+            (Some(vwap), Some(mid)) if mid > 0.0 => Some(match side {
+                Side::Buy => (vwap - mid) / mid * 10_000.0,
+                Side::Sell => (mid - vwap) / mid * 10_000.0,
+            }),
+            _ => None,
+        };
+        Self { side, filled_quantity, filled_notional, vwap, worst_price, unfilled, slippage_bps }
+    }
+
+    pub fn is_complete(&self) -> bool {
+        match self.unfilled {
+            FillTarget::Quantity(q) => q == 0,
+            FillTarget::Notional(n) => n == 0,
+        }
+    }
+}
+
+/// Takes `levels` best first until the target is filled
+pub(crate) fn walk(side: Side, levels: impl Iterator<Item = DepthLevel>, target: FillTarget, mid: Option<f64>) -> FillEstimate {
+    let (mut quantity, mut notional, mut worst) = (0, 0u128, None);
+    let mut unfilled = target;
+    for level in levels {
+        let take = match unfilled {
+            FillTarget::Quantity(q) => q.min(level.quantity),
+            FillTarget::Notional(n) => (n / level.price as u128).min(level.quantity as u128) as Quantity,
+        };
+        if take == 0 {
+            break;
+        }
+        quantity += take;
+        notional += level.price as u128 * take as u128;
+        worst = Some(level.price);
+        unfilled = match unfilled {
+            FillTarget::Quantity(q) => FillTarget::Quantity(q - take),
+            FillTarget::Notional(n) => FillTarget::Notional(n - level.price as u128 * take as u128),
+        };
+    }
+    FillEstimate::new(side, quantity, notional, worst, unfilled, mid)
+}
+
+/// Largest fill whose VWAP stays within `bps` of `mid`. Levels inside the limit are taken whole,
+/// the first level beyond it only as far as the average allows
+pub(crate) fn walk_within_slippage(side: Side, levels: impl Iterator<Item = DepthLevel>, mid: f64, bps: f64) -> FillEstimate {
+    let limit = match side {
+        Side::Buy => mid * (1.0 + bps / 10_000.0),
+        Side::Sell => mid * (1.0 - bps / 10_000.0),
+    };
+    let (mut quantity, mut notional, mut worst) = (0, 0u128, None);
+    for level in levels {
+        let price = level.price as f64;
+        let within = match side {
+            Side::Buy => price <= limit,
+            Side::Sell => price >= limit,
+        };
+        let take = if within {
+            level.quantity
+        } else {
+            // (notional + price * q) / (quantity + q) = limit
+            let room = (limit * quantity as f64 - notional as f64) / (price - limit);
+            (room.max(0.0).floor() as Quantity).min(level.quantity)
+        };
+        if take > 0 {
+            quantity += take;
+            notional += level.price as u128 * take as u128;
+            worst = Some(level.price);
+        }
+        if !within {
+            break;
+        }
+    }
+    FillEstimate::new(side, quantity, notional, worst, FillTarget::Quantity(0), Some(mid))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn levels(raw: &[(Price, Quantity)]) -> Vec<DepthLevel> {
+        raw.iter()
+            .map(|&(price, quantity)| DepthLevel { price, quantity, cumulative_quantity: 0, cumulative_notional: 0 })
+            .collect()
+    }
+
+    #[test]
+    fn test_walk_quantity() {
+        let asks = levels(&[(100, 10), (101, 10), (105, 10)]);
+        let fill = walk(Side::Buy, asks.into_iter(), FillTarget::Quantity(15), Some(99.0));
+
+        assert_eq!(fill.filled_quantity, 15);
+        assert_eq!(fill.filled_notional, 1_505);
+        assert_eq!(fill.worst_price, Some(101));
+        assert!(fill.is_complete());
+        assert!((fill.vwap.unwrap() - 100.333).abs() < 0.001);
+        assert!((fill.slippage_bps.unwrap() - 134.68).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_walk_beyond_depth() {
+        let bids = levels(&[(100, 1), (90, 1)]);
+        let fill = walk(Side::Sell, bids.into_iter(), FillTarget::Quantity(5), Some(101.0));
+
+        assert_eq!(fill.filled_quantity, 2);
+        assert_eq!(fill.unfilled, FillTarget::Quantity(3));
+        assert_eq!(fill.worst_price, Some(90));
+        assert!(!fill.is_complete());
+        assert!((fill.slippage_bps.unwrap() - 594.06).abs() < 0.01);
+
+        let empty = walk(Side::Sell, levels(&[]).into_iter(), FillTarget::Quantity(5), None);
+        assert_eq!((empty.vwap, empty.worst_price, empty.slippage_bps), (None, None, None));
+    }
+
+    #[test]
+    fn test_walk_notional() {
+        let asks = levels(&[(100, 10), (200, 10)]);
+        let fill = walk(Side::Buy, asks.into_iter(), FillTarget::Notional(1_450), None);
+
+        assert_eq!((fill.filled_quantity, fill.filled_notional), (12, 1_400));
+        assert_eq!(fill.unfilled, FillTarget::Notional(50));
+    }
+
+    #[test]
+    fn test_walk_within_slippage() {
+        // mid 100, 100 bps budget: average price up to 101
+        let asks = levels(&[(100, 10), (101, 10), (103, 10)]);
+        let fill = walk_within_slippage(Side::Buy, asks.into_iter(), 100.0, 100.0);
+
+        // 10 @ 100 + 10 @ 101 average 100.5, then 103 fits 5 more: (2010 + 515) / 25 = 101
+        assert_eq!(fill.filled_quantity, 25);
+        assert_eq!(fill.worst_price, Some(103));
+        assert!(fill.slippage_bps.unwrap() <= 100.0 + 1e-9);
+
+        let bids = levels(&[(98, 10)]);
+        let fill = walk_within_slippage(Side::Sell, bids.into_iter(), 100.0, 100.0);
+        assert_eq!(fill.filled_quantity, 0);
+    }
+}
@@ -0,0 +1,53 @@
+use crate::level2::{Level2Error, LevelUpdated, LevelUpdatedRepo, OrderBookSnapshot, OrderBookSnapshotRepo};
+use crate::shared::{Exchange, TimestampNS};
+use clickhouse::Client;
+
+/// Stored book data a replay reads from. Times are local receive times in ns
+pub trait BookHistory {
+    /// The latest snapshot with `received <= at`
+    async fn snapshot_before(&self, exchange: &Exchange, ticker: &str, at: TimestampNS) -> Result<Option<OrderBookSnapshot>, Level2Error>;
+
+    /// Up to `limit` updates with `after < received <= until`, oldest first. A full page also
+    /// holds every update sharing the receive time of its last one, so callers page on the last
+    /// `received` without skipping rows
+    async fn updates_after(
+        &self,
+        exchange: &Exchange,
+        ticker: &str,
+        after: TimestampNS,
+        until: TimestampNS,
+        limit: usize,
+    ) -> Result<Vec<LevelUpdated>, Level2Error>;
+}
+
+/// `order_book_snapshots` and `level_updates` in ClickHouse
+pub struct ClickhouseBookHistory<'a> {
+    snapshots: OrderBookSnapshotRepo<'a>,
+    updates: LevelUpdatedRepo<'a>,
+}
+
+impl<'a> ClickhouseBookHistory<'a> {
+    pub fn new(client: &'a Client) -> Self {
+        Self {
+            snapshots: OrderBookSnapshotRepo::new(client),
+            updates: LevelUpdatedRepo::new(client),
+        }
+    }
+}
+
+impl<'a> BookHistory for ClickhouseBookHistory<'a> {
+    async fn snapshot_before(&self, exchange: &Exchange, ticker: &str, at: TimestampNS) -> Result<Option<OrderBookSnapshot>, Level2Error> {
+        self.snapshots.latest_before(exchange, ticker, at).await
+    }
+
+    async fn updates_after(
+        &self,
+        exchange: &Exchange,
+        ticker: &str,
+        after: TimestampNS,
+        until: TimestampNS,
+        limit: usize,
+    ) -> Result<Vec<LevelUpdated>, Level2Error> {
+        self.updates.updates_after(exchange, ticker, after, until, limit).await
+    }
+}
@@ -62,6 +62,8 @@ mod tests {
             ticker: Arc::new("BTC/USDT".to_string()),
             exchange: Exchange::Binance,
             received:  now_timestamp_ns(),
+            sequence: 0,
+            prev_sequence: 0,
         }
     }
 
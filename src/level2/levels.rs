@@ -0,0 +1,448 @@
+use crate::shared::{Price, Quantity, Side};
+use either::Either;
+use std::collections::{btree_map, btree_set, BTreeMap, BTreeSet, HashMap};
+use std::iter::Rev;
+
+/// Storage of one side's price levels behind a `BookSide`
+pub trait PriceLevels {
+    type Iter<'a>: Iterator<Item = (Price, Quantity)>
+    where
+        Self: 'a;
+
+    fn new(side: Side) -> Self;
+
+    fn get(&self, price: Price) -> Option<Quantity>;
+
+    /// Sets a non-zero quantity, returns the previous one
+    fn insert(&mut self, price: Price, quantity: Quantity) -> Option<Quantity>;
+
+    fn remove(&mut self, price: Price) -> Option<Quantity>;
+
+    fn best(&self) -> Option<Price>;
+
+    fn worst(&self) -> Option<Price>;
+
+    fn len(&self) -> usize;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn clear(&mut self);
+
+    /// Every level, best first
+    fn iter(&self) -> Self::Iter<'_>;
+}
+
+/// Quantities in a hash map with the prices kept in an ordered set
+pub struct TreeLevels {
+    side: Side,
+    levels: HashMap<Price, Quantity>,
+    sorted_prices: BTreeSet<Price>,
+    best_price: Option<Price>,
+}
+
+pub struct TreeIter<'a> {
+    prices: Either<Rev<btree_set::Iter<'a, Price>>, btree_set::Iter<'a, Price>>,
+    levels: &'a HashMap<Price, Quantity>,
+}
+
+impl Iterator for TreeIter<'_> {
+    type Item = (Price, Quantity);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.prices.next().map(|&price| (price, self.levels[&price]))
+    }
+}
+
+impl PriceLevels for TreeLevels {
+    type Iter<'a> = TreeIter<'a>;
+
+    fn new(side: Side) -> Self {
+        Self {
+            side,
+            levels: HashMap::new(),
+            sorted_prices: BTreeSet::new(),
+            best_price: None,
+        }
+    }
+
+    fn get(&self, price: Price) -> Option<Quantity> {
+        self.levels.get(&price).copied()
+    }
+
+    fn insert(&mut self, price: Price, quantity: Quantity) -> Option<Quantity> {
+        let old = self.levels.insert(price, quantity);
+        if old.is_none() {
+            self.sorted_prices.insert(price);
+            self.best_price = match self.best_price {
+                Some(current) => match self.side {
+                    Side::Buy => Some(current.max(price)),
+                    Side::Sell => Some(current.min(price)),
+                },
+                None => Some(price),
+            };
+        }
+        old
+    }
+
+    fn remove(&mut self, price: Price) -> Option<Quantity> {
+        let old = self.levels.remove(&price)?;
+        self.sorted_prices.remove(&price);
+        if Some(price) == self.best_price {
+            self.best_price = match self.side {
+                Side::Buy => self.sorted_prices.last().copied(),
+                Side::Sell => self.sorted_prices.first().copied(),
+            };
+        }
+        Some(old)
+    }
+
+    fn best(&self) -> Option<Price> {
+        self.best_price
+    }
+
+    fn worst(&self) -> Option<Price> {
+        match self.side {
+            Side::Buy => self.sorted_prices.first().copied(),
+            Side::Sell => self.sorted_prices.last().copied(),
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.levels.len()
+    }
+
+    fn clear(&mut self) {
+        self.levels.clear();
+        self.sorted_prices.clear();
+        self.best_price = None;
+    }
+
+    fn iter(&self) -> TreeIter<'_> {
+        let prices = match self.side {
+            Side::Buy => Either::Left(self.sorted_prices.iter().rev()),
+            Side::Sell => Either::Right(self.sorted_prices.iter()),
+        };
+        TreeIter { prices, levels: &self.levels }
+    }
+}
+
+/// Slots of the ladder window unless told otherwise
+pub const LADDER_SLOTS: usize = 4096;
+
+/// Quantities in a contiguous array with one slot per tick. The window follows the best price
+/// and is re-centered when it runs off the better end; levels past its worse end go to a map
+pub struct LadderLevels {
+    side: Side,
+    tick: Price,
+    /// Price of slot 0, a multiple of `tick`
+    base: Price,
+    /// 0 marks an empty slot
+    slots: Vec<Quantity>,
+    filled: usize,
+    /// Slots of the best and worst levels in the window, meaningful while `filled > 0`
+    best: usize,
+    worst: usize,
+    /// Levels worse than every price the window covers. Empty whenever the window is empty
+    overflow: BTreeMap<Price, Quantity>,
+}
+
+pub struct LadderIter<'a> {
+    ladder: &'a LadderLevels,
+    next: Option<usize>,
+    overflow: Either<Rev<btree_map::Iter<'a, Price, Quantity>>, btree_map::Iter<'a, Price, Quantity>>,
+}
+
+impl Iterator for LadderIter<'_> {
+    type Item = (Price, Quantity);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.next {
+            Some(slot) => {
+                let ladder = self.ladder;
+                self.next = if slot == ladder.worst {
+                    None
+                } else {
+                    ladder.scan_worse(ladder.toward_worse(slot))
+                };
+                Some((ladder.price_of(slot), ladder.slots[slot]))
+            }
+            None => self.overflow.next().map(|(&price, &qty)| (price, qty)),
+        }
+    }
+}
+
+impl LadderLevels {
+    /// `tick` is the price step in scaled units, every price must be a multiple of it
+    pub fn with_tick(side: Side, tick: Price, slots: usize) -> Self {
+        assert!(tick > 0 && slots > 0, "ladder needs a positive tick and window");
+        Self {
+            side,
+            tick,
+            base: 0,
+            slots: vec![0; slots],
+            filled: 0,
+            best: 0,
+            worst: 0,
+            overflow: BTreeMap::new(),
+        }
+    }
+
+    fn slot(&self, price: Price) -> Option<usize> {
+        debug_assert!(price.is_multiple_of(self.tick), "price {price} is off the {} tick", self.tick);
+        let offset = price.checked_sub(self.base)? / self.tick;
+        (offset < self.slots.len() as u64).then_some(offset as usize)
+    }
+
+    fn price_of(&self, slot: usize) -> Price {
+        self.base + slot as Price * self.tick
+    }
+
+    fn window_end(&self) -> Price {
+        self.base.saturating_add(self.slots.len() as Price * self.tick)
+    }
+
+    /// Past the better end of the window
+    fn beyond_window(&self, price: Price) -> bool {
+        match self.side {
+            Side::Buy => price >= self.window_end(),
+            Side::Sell => price < self.base,
+        }
+    }
+
+    fn toward_worse(&self, slot: usize) -> usize {
+        match self.side {
+            Side::Buy => slot.wrapping_sub(1),
+            Side::Sell => slot + 1,
+        }
+    }
+
+    /// First filled slot from `from` on towards worse prices
+    fn scan_worse(&self, from: usize) -> Option<usize> {
+        match self.side {
+            Side::Buy if from < self.slots.len() => (0..=from).rev().find(|&i| self.slots[i] != 0),
+            Side::Buy => None,
+            Side::Sell => (from..self.slots.len()).find(|&i| self.slots[i] != 0),
+        }
+    }
+
+    /// First filled slot from `from` on towards better prices
+    fn scan_better(&self, from: usize) -> Option<usize> {
+        match self.side {
+            Side::Buy => (from..self.slots.len()).find(|&i| self.slots[i] != 0),
+            Side::Sell => (0..=from).rev().find(|&i| self.slots[i] != 0),
+        }
+    }
+
+    fn is_better(&self, a: usize, b: usize) -> bool {
+        match self.side {
+            Side::Buy => a > b,
+            Side::Sell => a < b,
+        }
+    }
+
+    fn overflow_best(&self) -> Option<Price> {
+        match self.side {
+            Side::Buy => self.overflow.last_key_value().map(|(&p, _)| p),
+            Side::Sell => self.overflow.first_key_value().map(|(&p, _)| p),
+        }
+    }
+
+    /// Moves the window so `best` sits a quarter of it away from the better end
+    fn recenter(&mut self, best: Price) {
+        if self.filled > 0 {
+            for slot in self.best.min(self.worst)..=self.best.max(self.worst) {
+                let qty = std::mem::take(&mut self.slots[slot]);
+                if qty != 0 {
+                    self.overflow.insert(self.price_of(slot), qty);
+                }
+            }
+        }
+        let len = self.slots.len() as Price;
+        let lead = match self.side {
+            Side::Buy => len - len / 4 - 1,
+            Side::Sell => len / 4,
+        };
+        self.base = best.saturating_sub(lead * self.tick) / self.tick * self.tick;
+
+        let mut inside = self.overflow.split_off(&self.base);
+        let mut past = inside.split_off(&self.window_end());
+        self.overflow.append(&mut past);
+        self.filled = inside.len();
+        for (price, qty) in inside {
+            let slot = ((price - self.base) / self.tick) as usize;
+            self.slots[slot] = qty;
+        }
+        if self.filled > 0 {
+            let (better_end, worse_end) = match self.side {
+                Side::Buy => (self.slots.len() - 1, 0),
+                Side::Sell => (0, self.slots.len() - 1),
+            };
+            self.best = self.scan_worse(better_end).unwrap();
+            self.worst = self.scan_better(worse_end).unwrap();
+        }
+    }
+}
+
+impl PriceLevels for LadderLevels {
+    type Iter<'a> = LadderIter<'a>;
+
+    fn new(side: Side) -> Self {
+        Self::with_tick(side, 1, LADDER_SLOTS)
+    }
+
+    fn get(&self, price: Price) -> Option<Quantity> {
+        match self.slot(price) {
+            Some(slot) => Some(self.slots[slot]).filter(|&qty| qty != 0),
+            None => self.overflow.get(&price).copied(),
+        }
+    }
+
+    fn insert(&mut self, price: Price, quantity: Quantity) -> Option<Quantity> {
+        debug_assert!(quantity != 0);
+        if self.filled == 0 || self.beyond_window(price) {
+            self.recenter(price);
+        }
+        let Some(slot) = self.slot(price) else {
+            return self.overflow.insert(price, quantity);
+        };
+        let old = std::mem::replace(&mut self.slots[slot], quantity);
+        if old != 0 {
+            return Some(old);
+        }
+        self.filled += 1;
+        if self.filled == 1 {
+            (self.best, self.worst) = (slot, slot);
+        } else if self.is_better(slot, self.best) {
+            self.best = slot;
+        } else if self.is_better(self.worst, slot) {
+            self.worst = slot;
+        }
+        None
+    }
+
+    fn remove(&mut self, price: Price) -> Option<Quantity> {
+        let Some(slot) = self.slot(price) else {
+            return self.overflow.remove(&price);
+        };
+        let old = std::mem::take(&mut self.slots[slot]);
+        if old == 0 {
+            return None;
+        }
+        self.filled -= 1;
+        if self.filled == 0 {
+            if let Some(next) = self.overflow_best() {
+                self.recenter(next);
+            }
+        } else if slot == self.best {
+            self.best = self.scan_worse(slot).unwrap();
+        } else if slot == self.worst {
+            self.worst = self.scan_better(slot).unwrap();
+        }
+        Some(old)
+    }
+
+    fn best(&self) -> Option<Price> {
+        (self.filled > 0).then(|| self.price_of(self.best))
+    }
+
+    fn worst(&self) -> Option<Price> {
+        let overflow_worst = match self.side {
+            Side::Buy => self.overflow.first_key_value(),
+            Side::Sell => self.overflow.last_key_value(),
+        };
+        match overflow_worst {
+            Some((&price, _)) => Some(price),
+            None => (self.filled > 0).then(|| self.price_of(self.worst)),
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.filled + self.overflow.len()
+    }
+
+    fn clear(&mut self) {
+        if self.filled > 0 {
+            self.slots[self.best.min(self.worst)..=self.best.max(self.worst)].fill(0);
+        }
+        self.filled = 0;
+        self.overflow.clear();
+    }
+
+    fn iter(&self) -> LadderIter<'_> {
+        let overflow = match self.side {
+            Side::Buy => Either::Left(self.overflow.iter().rev()),
+            Side::Sell => Either::Right(self.overflow.iter()),
+        };
+        LadderIter {
+            ladder: self,
+            next: (self.filled > 0).then_some(self.best),
+            overflow,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn levels<L: PriceLevels>(store: &L) -> Vec<(Price, Quantity)> {
+        store.iter().collect()
+    }
+
+    #[test]
+    fn test_ladder_recenters_on_better_price() {
+        let mut ladder = LadderLevels::with_tick(Side::Sell, 10, 8);
+        ladder.insert(1_000, 1);
+        ladder.insert(1_050, 2);
+        // Past the worse end of the window
+        ladder.insert(1_200, 3);
+        // Past the better end, everything shifts
+        ladder.insert(900, 4);
+
+        assert_eq!(levels(&ladder), vec![(900, 4), (1_000, 1), (1_050, 2), (1_200, 3)]);
+        assert_eq!((ladder.best(), ladder.worst(), ladder.len()), (Some(900), Some(1_200), 4));
+    }
+
+    #[test]
+    fn test_ladder_pulls_overflow_back() {
+        let mut ladder = LadderLevels::with_tick(Side::Buy, 1, 4);
+        for price in [100, 99, 50, 10] {
+            ladder.insert(price, price);
+        }
+        ladder.remove(100);
+        ladder.remove(99);
+        assert_eq!(ladder.best(), Some(50));
+        assert_eq!(ladder.get(50), Some(50));
+        assert_eq!(levels(&ladder), vec![(50, 50), (10, 10)]);
+
+        ladder.clear();
+        assert_eq!((ladder.best(), ladder.worst(), ladder.len()), (None, None, 0));
+    }
+
+    #[test]
+    fn test_ladder_matches_tree() {
+        for side in [Side::Buy, Side::Sell] {
+            let mut tree = TreeLevels::new(side);
+            let mut ladder = LadderLevels::with_tick(side, 5, 16);
+            let mut seed: u64 = 11;
+            for step in 0..20_000 {
+                seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+                // A drifting mid so the window has to follow
+                let mid = 1_000 + (step / 500) * 40;
+                let price = (mid + (seed >> 33) % 60 - 30) * 5;
+                let qty = (seed >> 20) % 3;
+                if qty == 0 {
+                    assert_eq!(tree.remove(price), ladder.remove(price));
+                } else {
+                    assert_eq!(tree.insert(price, qty), ladder.insert(price, qty));
+                }
+                assert_eq!(tree.best(), ladder.best());
+                assert_eq!(tree.worst(), ladder.worst());
+                assert_eq!(tree.len(), ladder.len());
+            }
+            assert_eq!(levels(&tree), levels(&ladder));
+        }
+    }
+}
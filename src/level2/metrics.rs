@@ -0,0 +1,75 @@
+use crate::level2::book_side::BookSide;
+use crate::level2::levels::PriceLevels;
+use crate::shared::{Exchange, Price, TimestampMS, TimestampNS};
+use std::sync::Arc;
+
+/// Top of book figures after an update, see `OrderBook::with_metrics`
+#[derive(Debug, Clone, PartialEq)]
+pub struct BookMetrics {
+    pub exchange: Exchange,
+    pub ticker: Arc<String>,
+    pub mid_price: f64,
+    /// Best ask minus best bid in price units
+    pub spread: Price,
+    pub spread_bps: f64,
+    /// Mid weighted by the opposite size at the top: leans towards the side that is about to be taken out
+    pub microprice: f64,
+    /// (bids - asks) / (bids + asks) over the quantity of the best N levels, from -1.0 to 1.0
+    pub imbalance: f64,
+    /// Same over the best N levels with linearly decreasing weights: N for the best level, 1 for the last
+    pub weighted_imbalance: f64,
+    /// Of the update that produced the metrics
+    pub timestamp: TimestampMS,
+    pub received: TimestampNS,
+}
+
+fn imbalance(bids: f64, asks: f64) -> f64 {
+    if bids + asks == 0.0 {
+        return 0.0;
+    }
+    (bids - asks) / (bids + asks)
+}
+
+fn weighted_quantity<L: PriceLevels>(side: &BookSide<L>, depth: usize) -> f64 {
+    side.depth(depth)
+        .enumerate()
+        .map(|(rank, level)| level.quantity as f64 * (depth - rank) as f64)
+        .sum()
+}
+
+/// Recomputes everything but the top-N sums, which the book sides keep incrementally.
+/// `None` while either side is empty
+pub(crate) fn compute<L: PriceLevels>(
+    bids: &BookSide<L>,
+    asks: &BookSide<L>,
+    depth: usize,
+    exchange: &Exchange,
+    ticker: &Arc<String>,
+    timestamp: TimestampMS,
+    received: TimestampNS,
+) -> Option<BookMetrics> {
+    let (bid, ask) = (bids.best_price()?, asks.best_price()?);
+    let bid_size = bids.quantity_at(bid)? as f64;
+    let ask_size = asks.quantity_at(ask)? as f64;
+
+    let mid_price = (bid + ask) as f64 / 2.0;
+    let spread = ask.saturating_sub(bid);
+    let microprice = if bid_size + ask_size > 0.0 {
+        (bid as f64 * ask_size + ask as f64 * bid_size) / (bid_size + ask_size)
+    } else {
+        mid_price
+    };
+
+    Some(BookMetrics {
+        exchange: exchange.clone(),
+        ticker: Arc::clone(ticker),
+        mid_price,
+        spread,
+        spread_bps: spread as f64 / mid_price * 10_000.0,
+        microprice,
+        imbalance: imbalance(bids.top_quantity() as f64, asks.top_quantity() as f64),
+        weighted_imbalance: imbalance(weighted_quantity(bids, depth), weighted_quantity(asks, depth)),
+        timestamp,
+        received,
+    })
+}
@@ -3,15 +3,37 @@ mod events;
 mod order_book;
 mod level_tick;
 mod book_side;
+mod levels;
+mod fill;
+mod metrics;
+mod snapshot;
+mod history;
+mod replay;
+mod bench;
+mod consolidated;
+mod registry;
 mod services;
 mod repo;
 
-pub use order_book::OrderBook;
+pub use order_book::{BookState, Liquidity, OrderBook};
+pub use book_side::{BookSide, DepthLevel};
+pub use levels::{LadderLevels, PriceLevels, TreeLevels};
+pub use fill::{FillEstimate, FillTarget};
+pub use metrics::BookMetrics;
+pub use snapshot::{OrderBookSnapshot, SnapshotWriter};
+pub use history::{BookHistory, ClickhouseBookHistory};
+pub use replay::BookReplay;
+pub use bench::{compare_levels, load_updates};
+pub use consolidated::{ConsolidatedBook, ConsolidatedFill, ConsolidatedLevel};
+pub use registry::BookRegistry;
 
 pub use events::LevelUpdated;
 
 pub use errors::Level2Error;
 
-pub use services::display_books;
+pub use services::{print_book, print_consolidated};
 
-pub use repo::{LevelUpdatedRepo, create_level_updates_table};
+pub use repo::{
+    BookMetricsRepo, LevelUpdatedRepo, OrderBookSnapshotRepo, create_book_metrics_table, create_level_updates_table,
+    create_order_book_snapshots_table,
+};
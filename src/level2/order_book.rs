@@ -1,27 +1,231 @@
 use std::sync::Arc;
-use crate::level2::book_side::BookSide;
+use crate::level2::book_side::{BookSide, DepthLevel};
+use crate::level2::levels::{PriceLevels, TreeLevels};
 use crate::level2::events::LevelUpdated;
-use crate::level2::Level2Error;
+use crate::level2::metrics::{compute, BookMetrics};
+use crate::level2::fill::{walk, walk_within_slippage, FillEstimate, FillTarget};
+use crate::level2::{Level2Error, OrderBookSnapshot};
 use crate::shared::errors::{check_exchange, check_ticker};
-use crate::shared::{Exchange, Side};
+use crate::shared::{Exchange, Price, Quantity, Side, TimestampNS};
+use std::time::Duration;
 
-pub struct OrderBook {
-    bids: BookSide,
-    asks: BookSide,
+/// Displayed liquidity of both sides within a band around the mid price
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Liquidity {
+    pub bid_quantity: Quantity,
+    pub bid_notional: u128,
+    pub ask_quantity: Quantity,
+    pub ask_notional: u128,
+}
+
+/// Whether the book can be trusted
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BookState {
+    /// A side is still empty
+    Initializing,
+    Valid,
+    /// Best bid at or above best ask. Clears itself once later updates uncross the book
+    Crossed,
+    /// A message was missed according to the exchange sequence ids. Sticks until `resync()`
+    Gapped,
+    /// No update within the `with_stale_after` window. Clears on the next update
+    Stale,
+}
+
+pub struct OrderBook<L: PriceLevels = TreeLevels> {
+    bids: BookSide<L>,
+    asks: BookSide<L>,
     exchange: Exchange,
     ticker: Arc<String>,
+    /// Levels per side the metrics cover, 0 when off
+    metrics_depth: usize,
+    metrics: Option<BookMetrics>,
+    metrics_changed: bool,
+    state: BookState,
+    gapped: bool,
+    last_sequence: u64,
+    last_received: TimestampNS,
+    stale_after: Option<Duration>,
 }
 
 impl OrderBook {
     pub fn new(exchange: Exchange, ticker: &str, max_depth: usize) -> Self {
+        Self::with_levels(exchange, ticker, max_depth, TreeLevels::new(Side::Buy), TreeLevels::new(Side::Sell))
+    }
+}
+
+impl<L: PriceLevels> OrderBook<L> {
+    /// Book over the given level storage, e.g. a `LadderLevels` with the tick of the ticker
+    pub fn with_levels(exchange: Exchange, ticker: &str, max_depth: usize, bids: L, asks: L) -> Self {
         Self {
-            bids: BookSide::new(Side::Buy, max_depth),
-            asks: BookSide::new(Side::Sell, max_depth),
+            bids: BookSide::with_levels(bids, Side::Buy, max_depth),
+            asks: BookSide::with_levels(asks, Side::Sell, max_depth),
             exchange,
             ticker: Arc::new(ticker.to_string()),
+            metrics_depth: 0,
+            metrics: None,
+            metrics_changed: false,
+            state: BookState::Initializing,
+            gapped: false,
+            last_sequence: 0,
+            last_received: 0,
+            stale_after: None,
         }
     }
 
+    /// Keeps up to `levels` per side so levels past `max_depth` can move into view when better
+    /// ones are removed
+    pub fn with_buffer(mut self, levels: usize) -> Self {
+        self.bids.set_buffer(levels);
+        self.asks.set_buffer(levels);
+        self
+    }
+
+    /// Either side can vouch for fewer than `max_depth` levels, see `BookSide::is_truncated`
+    pub fn is_truncated(&self) -> bool {
+        self.bids.is_truncated() || self.asks.is_truncated()
+    }
+
+    /// Lets `check_stale` mark the book stale after `window` without updates
+    pub fn with_stale_after(mut self, window: Duration) -> Self {
+        self.stale_after = Some(window);
+        self
+    }
+
+    pub fn state(&self) -> BookState {
+        self.state
+    }
+
+    pub fn is_valid(&self) -> bool {
+        self.state == BookState::Valid
+    }
+
+    /// Marks the book stale if the last update was received more than the window before `now`
+    pub fn check_stale(&mut self, now: TimestampNS) -> BookState {
+        if let Some(window) = self.stale_after {
+            let quiet = now.saturating_sub(self.last_received) >= window.as_nanos() as u64;
+            if quiet && self.last_received > 0 && self.state != BookState::Gapped {
+                self.state = BookState::Stale;
+            }
+        }
+        self.state
+    }
+
+    /// Drops every level and sequence so the book rebuilds from the following updates
+    pub fn resync(&mut self) {
+        self.bids.clear();
+        self.asks.clear();
+        self.gapped = false;
+        self.last_sequence = 0;
+        self.metrics = None;
+        self.metrics_changed = false;
+        self.state = BookState::Initializing;
+    }
+
+    /// Skips messages older than the last one applied and flags a gap when the message does
+    /// not continue from it. Updates of one message share the sequence and all pass.
+    fn check_sequence(&mut self, event: &LevelUpdated) -> bool {
+        if event.sequence == 0 {
+            return true;
+        }
+        if self.last_sequence != 0 {
+            if event.sequence < self.last_sequence {
+                return false;
+            }
+            if event.sequence > self.last_sequence && event.prev_sequence > self.last_sequence {
+                self.gapped = true;
+            }
+        }
+        self.last_sequence = event.sequence;
+        true
+    }
+
+    fn refresh_state(&mut self) {
+        self.state = match (self.bids.best_price(), self.asks.best_price()) {
+            _ if self.gapped => BookState::Gapped,
+            (Some(bid), Some(ask)) if bid >= ask => BookState::Crossed,
+            (Some(_), Some(_)) => BookState::Valid,
+            _ => BookState::Initializing,
+        };
+    }
+
+    fn apply(&mut self, event: &LevelUpdated) -> Result<(), Level2Error> {
+        if !self.check_sequence(event) {
+            return Ok(());
+        }
+        match event.side {
+            Side::Buy => self.bids.update(event)?,
+            Side::Sell => self.asks.update(event)?,
+        }
+        self.last_received = event.received;
+        self.refresh_state();
+        self.refresh_metrics(event);
+        Ok(())
+    }
+
+    /// Book holding the levels of `snapshot`, levels past `max_depth` go to the hidden buffer
+    pub fn from_snapshot(snapshot: &OrderBookSnapshot, max_depth: usize) -> Self {
+        let (bids, asks) = (L::new(Side::Buy), L::new(Side::Sell));
+        let buffer = snapshot.bids.len().max(snapshot.asks.len());
+        let mut book =
+            Self::with_levels(snapshot.exchange.clone(), &snapshot.ticker, max_depth, bids, asks).with_buffer(buffer);
+        let levels = snapshot.bids.iter().map(|l| (Side::Buy, l)).chain(snapshot.asks.iter().map(|l| (Side::Sell, l)));
+        for (side, &(price, quantity)) in levels {
+            let event = LevelUpdated {
+                exchange: snapshot.exchange.clone(),
+                ticker: Arc::clone(&book.ticker),
+                side,
+                price,
+                quantity,
+                timestamp: snapshot.timestamp,
+                received: snapshot.received,
+                sequence: 0,
+                prev_sequence: 0,
+            };
+            book.update_or_miss(&event);
+        }
+        book
+    }
+
+    /// Maintains `BookMetrics` on every update, with imbalances over the best `depth` levels
+    pub fn with_metrics(mut self, depth: usize) -> Self {
+        self.metrics_depth = depth;
+        self.bids.track_top(depth);
+        self.asks.track_top(depth);
+        self
+    }
+
+    pub fn metrics(&self) -> Option<&BookMetrics> {
+        self.metrics.as_ref()
+    }
+
+    /// The metrics if they changed since the last call, for emitting them as a stream
+    pub fn take_metrics(&mut self) -> Option<BookMetrics> {
+        if !self.metrics_changed {
+            return None;
+        }
+        self.metrics_changed = false;
+        self.metrics.clone()
+    }
+
+    /// Updates beyond the tracked levels leave the metrics as they are
+    fn refresh_metrics(&mut self, event: &LevelUpdated) {
+        if self.metrics_depth == 0 || !self.get_side(event.side).in_top(event.price) {
+            return;
+        }
+        let metrics = compute(
+            &self.bids,
+            &self.asks,
+            self.metrics_depth,
+            &self.exchange,
+            &self.ticker,
+            event.timestamp,
+            event.received,
+        );
+        self.metrics_changed = metrics.is_some();
+        self.metrics = metrics;
+    }
+
     pub fn exchange(&self) -> &Exchange{
         &self.exchange
     }
@@ -30,46 +234,92 @@ impl OrderBook {
         &self.ticker
     }
 
-    pub fn bids(&self) -> &BookSide {
+    pub fn bids(&self) -> &BookSide<L> {
         &self.bids
     }
 
-    pub fn asks(&self) -> &BookSide {
+    pub fn asks(&self) -> &BookSide<L> {
         &self.asks
     }
 
-    pub fn get_side(&self, side: Side) -> &BookSide {
+    pub fn get_side(&self, side: Side) -> &BookSide<L> {
         match side {
             Side::Buy => &self.bids,
             Side::Sell => &self.asks,
         }
     }
 
+    pub fn depth(&self, side: Side, depth: usize) -> Vec<DepthLevel> {
+        self.get_side(side).depth(depth).collect()
+    }
+
+    /// Halfway between the best bid and ask, in price units
+    pub fn mid_price(&self) -> Option<f64> {
+        Some((self.bids.best_price()? + self.asks.best_price()?) as f64 / 2.0)
+    }
+
+    /// Liquidity priced within `ticks` price units of the mid
+    pub fn liquidity_within_ticks(&self, ticks: Price) -> Option<Liquidity> {
+        self.liquidity_within(ticks as f64)
+    }
+
+    /// Liquidity priced within `bps` basis points of the mid
+    pub fn liquidity_within_bps(&self, bps: f64) -> Option<Liquidity> {
+        self.liquidity_within(self.mid_price()? * bps / 10_000.0)
+    }
+
+    fn liquidity_within(&self, width: f64) -> Option<Liquidity> {
+        let mid = self.mid_price()?;
+        let lowest_bid = (mid - width).max(0.0).ceil() as Price;
+        let highest_ask = (mid + width).floor() as Price;
+        let (bid_quantity, bid_notional) = self.bids.liquidity_to(lowest_bid);
+        let (ask_quantity, ask_notional) = self.asks.liquidity_to(highest_ask);
+        Some(Liquidity { bid_quantity, bid_notional, ask_quantity, ask_notional })
+    }
+
+    /// Side of the book a market order on `side` executes against
+    fn opposite(&self, side: Side) -> &BookSide<L> {
+        match side {
+            Side::Buy => &self.asks,
+            Side::Sell => &self.bids,
+        }
+    }
+
+    /// Market order of `quantity` on `side` against the displayed depth
+    pub fn cost_to_fill(&self, side: Side, quantity: Quantity) -> FillEstimate {
+        walk(side, self.opposite(side).depth(usize::MAX), FillTarget::Quantity(quantity), self.mid_price())
+    }
+
+    /// Market order spending `notional` (price * quantity in scaled units) on `side`
+    pub fn cost_to_fill_notional(&self, side: Side, notional: u128) -> FillEstimate {
+        walk(side, self.opposite(side).depth(usize::MAX), FillTarget::Notional(notional), self.mid_price())
+    }
+
+    /// Largest market order on `side` whose VWAP stays within `bps` of the mid, `None` without a mid
+    pub fn max_fill_within_slippage(&self, side: Side, bps: f64) -> Option<FillEstimate> {
+        let mid = self.mid_price()?;
+        Some(walk_within_slippage(side, self.opposite(side).depth(usize::MAX), mid, bps))
+    }
+
     pub fn update(&mut self, event: &LevelUpdated) -> Result<(), Level2Error> {
         check_exchange(&self.exchange, &event.exchange)?;
         check_ticker(&self.ticker, &event.ticker)?;
-        match event.side {
-            Side::Buy => self.bids.update(event),
-            Side::Sell => self.asks.update(event),
-        }
+        self.apply(event)
     }
 
     pub fn update_if_instrument_matches(&mut self, event: &LevelUpdated) -> Result<(), Level2Error> {
         if self.ticker == event.ticker && self.exchange == event.exchange {
-            match event.side {
-                Side::Buy => self.bids.update(event)?,
-                Side::Sell => self.asks.update(event)?,
-            }
+            self.apply(event)?;
         }
         Ok(())
     }
 
+    /// Applies updates of this instrument and ignores the rest. Problems with the data show up
+    /// in `state()` instead of an error
     pub fn update_or_miss(&mut self, event: &LevelUpdated) {
         if self.ticker == event.ticker && self.exchange == event.exchange {
-            match event.side {
-                Side::Buy => self.bids.update_or_miss(event),
-                Side::Sell => self.asks.update_or_miss(event),
-            }
+            // The side always matches, so `apply` cannot fail
+            let _ = self.apply(event);
         }
     }
 }
@@ -90,6 +340,8 @@ mod tests {
             quantity: qty,
             timestamp: 0,
             received: now_timestamp_ns(),
+            sequence: 0,
+            prev_sequence: 0,
         }
     }
 
@@ -151,6 +403,18 @@ mod tests {
         assert!(matches!(ob.get_side(Side::Sell).side(), &Side::Sell));
     }
 
+    #[test]
+    fn test_truncated_after_best_level_removed() {
+        let mut ob = OrderBook::new(Exchange::Binance, "BTCUSDT", 2);
+        for price in [100, 99, 98] {
+            ob.update(&event(Exchange::Binance, "BTCUSDT", Side::Buy, price, 1)).unwrap();
+        }
+        assert!(!ob.is_truncated());
+
+        ob.update(&event(Exchange::Binance, "BTCUSDT", Side::Buy, 100, 0)).unwrap();
+        assert!(ob.is_truncated());
+    }
+
     #[test]
     fn test_exchange_and_ticker_check() {
         let mut ob = OrderBook::new(Exchange::Binance, "BTCUSDT", 5);
@@ -163,4 +427,162 @@ mod tests {
         let err = ob.update(&event(Exchange::Binance, "ETHUSDT", Side::Buy, 100, 10));
         assert!(err.is_err());
     }
+
+    #[test]
+    fn test_depth_both_sides() {
+        let mut ob = OrderBook::new(Exchange::Binance, "BTCUSDT", 5);
+        ob.update(&event(Exchange::Binance, "BTCUSDT", Side::Buy, 100, 10)).unwrap();
+        ob.update(&event(Exchange::Binance, "BTCUSDT", Side::Buy, 99, 20)).unwrap();
+        ob.update(&event(Exchange::Binance, "BTCUSDT", Side::Sell, 102, 5)).unwrap();
+
+        let bids = ob.depth(Side::Buy, 5);
+        assert_eq!(bids.iter().map(|l| l.price).collect::<Vec<Price>>(), vec![100, 99]);
+        assert_eq!(bids[1].cumulative_quantity, 30);
+        assert_eq!(bids[1].cumulative_notional, 2_980);
+        assert_eq!(ob.depth(Side::Sell, 5)[0].quantity, 5);
+    }
+
+    #[test]
+    fn test_liquidity_around_mid() {
+        let mut ob = OrderBook::new(Exchange::Binance, "BTCUSDT", 10);
+        assert_eq!(ob.liquidity_within_ticks(10), None);
+
+        for (price, qty) in [(9_999, 1), (9_990, 2), (9_900, 4)] {
+            ob.update(&event(Exchange::Binance, "BTCUSDT", Side::Buy, price, qty)).unwrap();
+        }
+        for (price, qty) in [(10_001, 1), (10_010, 2), (10_100, 4)] {
+            ob.update(&event(Exchange::Binance, "BTCUSDT", Side::Sell, price, qty)).unwrap();
+        }
+        assert_eq!(ob.mid_price(), Some(10_000.0));
+
+        let ticks = ob.liquidity_within_ticks(10).unwrap();
+        assert_eq!((ticks.bid_quantity, ticks.ask_quantity), (3, 3));
+        assert_eq!(ticks.bid_notional, 9_999 + 2 * 9_990);
+
+        // 100 bps of 10_000 is 100 price units
+        let bps = ob.liquidity_within_bps(100.0).unwrap();
+        assert_eq!((bps.bid_quantity, bps.ask_quantity), (7, 7));
+        let bps = ob.liquidity_within_bps(1.0).unwrap();
+        assert_eq!((bps.bid_quantity, bps.ask_quantity), (1, 1));
+    }
+
+    #[test]
+    fn test_cost_to_fill() {
+        let mut ob = OrderBook::new(Exchange::Binance, "BTCUSDT", 10);
+        for (price, qty) in [(99, 10), (98, 10)] {
+            ob.update(&event(Exchange::Binance, "BTCUSDT", Side::Buy, price, qty)).unwrap();
+        }
+        for (price, qty) in [(101, 10), (102, 10)] {
+            ob.update(&event(Exchange::Binance, "BTCUSDT", Side::Sell, price, qty)).unwrap();
+        }
+
+        let buy = ob.cost_to_fill(Side::Buy, 20);
+        assert_eq!((buy.filled_notional, buy.worst_price), (2_030, Some(102)));
+        assert!((buy.slippage_bps.unwrap() - 150.0).abs() < 1e-9);
+
+        let sell = ob.cost_to_fill_notional(Side::Sell, 990);
+        assert_eq!((sell.filled_quantity, sell.worst_price), (10, Some(99)));
+        assert!(sell.is_complete());
+
+        // 100 bps of mid 100 allows an average of 101: exactly the first ask level
+        let max = ob.max_fill_within_slippage(Side::Buy, 100.0).unwrap();
+        assert_eq!(max.filled_quantity, 10);
+    }
+
+    #[test]
+    fn test_metrics() {
+        let mut ob = OrderBook::new(Exchange::Binance, "BTCUSDT", 10).with_metrics(2);
+        ob.update(&event(Exchange::Binance, "BTCUSDT", Side::Buy, 99, 30)).unwrap();
+        assert_eq!(ob.take_metrics(), None);
+
+        ob.update(&event(Exchange::Binance, "BTCUSDT", Side::Buy, 98, 10)).unwrap();
+        ob.update(&event(Exchange::Binance, "BTCUSDT", Side::Sell, 101, 10)).unwrap();
+        ob.update(&event(Exchange::Binance, "BTCUSDT", Side::Sell, 102, 10)).unwrap();
+
+        let m = ob.take_metrics().unwrap();
+        assert_eq!((m.mid_price, m.spread), (100.0, 2));
+        assert_eq!(m.spread_bps, 200.0);
+        // 30 on the bid against 10 on the ask: the price leans towards the ask
+        assert_eq!(m.microprice, 100.5);
+        assert_eq!(m.imbalance, 20.0 / 60.0);
+        // bids 30*2 + 10, asks 10*2 + 10
+        assert_eq!(m.weighted_imbalance, 40.0 / 100.0);
+        assert_eq!(ob.take_metrics(), None);
+
+        // Third level is not tracked
+        ob.update(&event(Exchange::Binance, "BTCUSDT", Side::Sell, 103, 50)).unwrap();
+        assert_eq!(ob.take_metrics(), None);
+
+        ob.update(&event(Exchange::Binance, "BTCUSDT", Side::Sell, 101, 0)).unwrap();
+        let m = ob.take_metrics().unwrap();
+        assert_eq!(m.spread, 3);
+        assert_eq!(m.imbalance, (40.0 - 60.0) / 100.0);
+    }
+
+    fn sequenced(side: Side, price: u64, qty: u64, prev_sequence: u64, sequence: u64) -> LevelUpdated {
+        LevelUpdated {
+            sequence,
+            prev_sequence,
+            ..event(Exchange::Binance, "BTCUSDT", side, price, qty)
+        }
+    }
+
+    #[test]
+    fn test_state_initializing_valid_crossed() {
+        let mut ob = OrderBook::new(Exchange::Binance, "BTCUSDT", 5);
+        assert_eq!(ob.state(), BookState::Initializing);
+
+        ob.update(&event(Exchange::Binance, "BTCUSDT", Side::Buy, 100, 1)).unwrap();
+        assert_eq!(ob.state(), BookState::Initializing);
+        ob.update(&event(Exchange::Binance, "BTCUSDT", Side::Sell, 101, 1)).unwrap();
+        assert!(ob.is_valid());
+
+        ob.update(&event(Exchange::Binance, "BTCUSDT", Side::Buy, 101, 1)).unwrap();
+        assert_eq!(ob.state(), BookState::Crossed);
+        ob.update(&event(Exchange::Binance, "BTCUSDT", Side::Buy, 101, 0)).unwrap();
+        assert_eq!(ob.state(), BookState::Valid);
+    }
+
+    #[test]
+    fn test_state_gapped_and_resync() {
+        let mut ob = OrderBook::new(Exchange::Binance, "BTCUSDT", 5);
+        ob.update(&sequenced(Side::Buy, 100, 1, 0, 10)).unwrap();
+        // Same message
+        ob.update(&sequenced(Side::Sell, 101, 1, 0, 10)).unwrap();
+        ob.update(&sequenced(Side::Sell, 102, 1, 10, 12)).unwrap();
+        assert!(ob.is_valid());
+
+        // An older message is dropped
+        ob.update(&sequenced(Side::Sell, 101, 0, 8, 9)).unwrap();
+        assert_eq!(ob.asks().best_price(), Some(101));
+
+        // 13..14 never arrived
+        ob.update(&sequenced(Side::Buy, 99, 1, 14, 15)).unwrap();
+        assert_eq!(ob.state(), BookState::Gapped);
+        ob.update(&sequenced(Side::Buy, 98, 1, 15, 16)).unwrap();
+        assert_eq!(ob.state(), BookState::Gapped);
+
+        ob.resync();
+        assert!(ob.bids().is_empty() && ob.asks().is_empty());
+        ob.update(&sequenced(Side::Buy, 98, 1, 20, 21)).unwrap();
+        ob.update(&sequenced(Side::Sell, 103, 1, 21, 22)).unwrap();
+        assert!(ob.is_valid());
+    }
+
+    #[test]
+    fn test_state_stale() {
+        let mut ob = OrderBook::new(Exchange::Binance, "BTCUSDT", 5).with_stale_after(Duration::from_secs(5));
+        let mut bid = event(Exchange::Binance, "BTCUSDT", Side::Buy, 100, 1);
+        bid.received = 1_000_000_000;
+        ob.update(&bid).unwrap();
+        let mut ask = event(Exchange::Binance, "BTCUSDT", Side::Sell, 101, 1);
+        ask.received = 2_000_000_000;
+        ob.update(&ask).unwrap();
+
+        assert_eq!(ob.check_stale(6_000_000_000), BookState::Valid);
+        assert_eq!(ob.check_stale(7_000_000_000), BookState::Stale);
+        ask.received = 8_000_000_000;
+        ob.update(&ask).unwrap();
+        assert!(ob.is_valid());
+    }
 }
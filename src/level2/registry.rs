@@ -0,0 +1,191 @@
+use crate::level2::{ConsolidatedBook, Level2Error, LevelUpdated, OrderBook, PriceLevels, TreeLevels};
+use crate::shared::{Exchange, Side, TimestampNS};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Order books per (exchange, ticker), created on the first update of an instrument with the
+/// settings given to the registry
+pub struct BookRegistry<L: PriceLevels = TreeLevels> {
+    max_depth: usize,
+    buffer: usize,
+    metrics_depth: usize,
+    stale_after: Option<Duration>,
+    /// By exchange, then ticker, so lookups by `&str` need no key allocation
+    books: HashMap<&'static str, HashMap<Arc<str>, OrderBook<L>>>,
+}
+
+impl BookRegistry {
+    pub fn new(max_depth: usize) -> Self {
+        Self::with_levels(max_depth)
+    }
+}
+
+impl<L: PriceLevels> BookRegistry<L> {
+    /// Registry whose books keep their levels in `L`
+    pub fn with_levels(max_depth: usize) -> Self {
+        Self {
+            max_depth,
+            buffer: max_depth,
+            metrics_depth: 0,
+            stale_after: None,
+            books: HashMap::new(),
+        }
+    }
+
+    /// See `OrderBook::with_buffer`
+    pub fn with_buffer(mut self, levels: usize) -> Self {
+        self.buffer = levels;
+        self
+    }
+
+    /// See `OrderBook::with_metrics`
+    pub fn with_metrics(mut self, depth: usize) -> Self {
+        self.metrics_depth = depth;
+        self
+    }
+
+    /// See `OrderBook::with_stale_after`
+    pub fn with_stale_after(mut self, window: Duration) -> Self {
+        self.stale_after = Some(window);
+        self
+    }
+
+    /// Applies `event` to the book of its instrument, creating the book if it is the first one
+    pub fn update(&mut self, event: &LevelUpdated) -> &mut OrderBook<L> {
+        let books = self.books.entry(event.exchange.to_str()).or_default();
+        if !books.contains_key(event.ticker.as_str()) {
+            let (bids, asks) = (L::new(Side::Buy), L::new(Side::Sell));
+            let mut book = OrderBook::with_levels(event.exchange.clone(), &event.ticker, self.max_depth, bids, asks)
+                .with_buffer(self.buffer)
+                .with_metrics(self.metrics_depth);
+            if let Some(window) = self.stale_after {
+                book = book.with_stale_after(window);
+            }
+            books.insert(Arc::from(event.ticker.as_str()), book);
+        }
+        let book = books.get_mut(event.ticker.as_str()).unwrap();
+        book.update_or_miss(event);
+        book
+    }
+
+    pub fn get(&self, exchange: &Exchange, ticker: &str) -> Option<&OrderBook<L>> {
+        self.books.get(exchange.to_str())?.get(ticker)
+    }
+
+    /// Runs `OrderBook::check_stale` on every book. Costs a pass over all books, so call it
+    /// periodically rather than per update
+    pub fn check_stale(&mut self, now: TimestampNS) {
+        for book in self.books.values_mut().flat_map(|books| books.values_mut()) {
+            book.check_stale(now);
+        }
+    }
+
+    /// Rebuilds `consolidated` from the valid books of its ticker on every exchange, added in
+    /// `Exchange` order. The books must use the given multipliers. Returns how many were merged
+    pub fn consolidate(
+        &self,
+        consolidated: &mut ConsolidatedBook,
+        price_multiply: f64,
+        quantity_multiply: f64,
+    ) -> Result<usize, Level2Error> {
+        consolidated.clear();
+        let mut books: Vec<&OrderBook<L>> = self
+            .books
+            .values()
+            .filter_map(|books| books.get(consolidated.ticker().as_str()))
+            .collect();
+        books.sort_by_key(|book| book.exchange().clone() as u8);
+        let mut merged = 0;
+        for book in books {
+            if consolidated.add(book, price_multiply, quantity_multiply)? {
+                merged += 1;
+            }
+        }
+        Ok(merged)
+    }
+
+    pub fn len(&self) -> usize {
+        self.books.values().map(|books| books.len()).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::level2::BookState;
+    use crate::shared::Price;
+
+    fn event(exchange: Exchange, ticker: &str, side: Side, price: Price) -> LevelUpdated {
+        LevelUpdated {
+            exchange,
+            ticker: Arc::new(ticker.to_string()),
+            side,
+            price,
+            quantity: 1,
+            timestamp: 0,
+            received: 1,
+            sequence: 0,
+            prev_sequence: 0,
+        }
+    }
+
+    #[test]
+    fn test_routes_and_creates_lazily() {
+        let mut registry = BookRegistry::new(10).with_metrics(5);
+        assert!(registry.is_empty());
+
+        registry.update(&event(Exchange::Binance, "btc/usdt", Side::Buy, 99));
+        let book = registry.update(&event(Exchange::Binance, "btc/usdt", Side::Sell, 100));
+        assert_eq!(book.state(), BookState::Valid);
+        assert!(book.metrics().is_some());
+
+        registry.update(&event(Exchange::Kraken, "btc/usdt", Side::Buy, 98));
+        registry.update(&event(Exchange::Binance, "eth/usdt", Side::Buy, 10));
+        assert_eq!(registry.len(), 3);
+
+        let binance = registry.get(&Exchange::Binance, "btc/usdt").unwrap();
+        assert_eq!((binance.bids().best_price(), binance.asks().best_price()), (Some(99), Some(100)));
+        assert_eq!(registry.get(&Exchange::Kraken, "btc/usdt").unwrap().bids().best_price(), Some(98));
+        assert!(registry.get(&Exchange::Kraken, "eth/usdt").is_none());
+    }
+
+    #[test]
+    fn test_consolidate_valid_books_of_the_ticker() {
+        let mut registry = BookRegistry::new(10);
+        registry.update(&event(Exchange::Kraken, "btc/usdt", Side::Buy, 99));
+        registry.update(&event(Exchange::Kraken, "btc/usdt", Side::Sell, 101));
+        registry.update(&event(Exchange::Binance, "btc/usdt", Side::Buy, 99));
+        registry.update(&event(Exchange::Binance, "btc/usdt", Side::Sell, 100));
+        // One-sided books are not valid and other tickers don't belong
+        registry.update(&event(Exchange::Deribit, "btc/usdt", Side::Buy, 98));
+        registry.update(&event(Exchange::Binance, "eth/usdt", Side::Buy, 10));
+
+        let mut consolidated = ConsolidatedBook::new("btc/usdt", 1.0, 1.0);
+        assert_eq!(registry.consolidate(&mut consolidated, 1.0, 1.0).unwrap(), 2);
+        let best_bid = consolidated.best_bid().unwrap();
+        assert_eq!((best_bid.price, best_bid.quantity), (99, 2));
+        assert_eq!(best_bid.venues, vec![(Exchange::Binance, 1), (Exchange::Kraken, 1)]);
+        assert_eq!(consolidated.best_ask().unwrap().price, 100);
+
+        // A second pass starts over instead of adding the same levels again
+        registry.consolidate(&mut consolidated, 1.0, 1.0).unwrap();
+        assert_eq!(consolidated.best_bid().unwrap().quantity, 2);
+    }
+
+    #[test]
+    fn test_check_stale() {
+        let mut registry = BookRegistry::new(10).with_stale_after(Duration::from_nanos(10));
+        registry.update(&event(Exchange::Binance, "btc/usdt", Side::Buy, 99));
+        registry.update(&event(Exchange::Binance, "btc/usdt", Side::Sell, 100));
+
+        registry.check_stale(5);
+        assert_eq!(registry.get(&Exchange::Binance, "btc/usdt").unwrap().state(), BookState::Valid);
+        registry.check_stale(20);
+        assert_eq!(registry.get(&Exchange::Binance, "btc/usdt").unwrap().state(), BookState::Stale);
+    }
+}
@@ -0,0 +1,187 @@
+use crate::level2::history::BookHistory;
+use crate::level2::{Level2Error, LevelUpdated, OrderBook};
+use crate::shared::{Exchange, TimestampNS};
+use std::collections::VecDeque;
+
+const PAGE: usize = 10_000;
+
+/// Rebuilds an `OrderBook` as it looked at a point in time: the nearest earlier snapshot plus
+/// every stored update up to that time. Afterwards the book can be stepped forward update by update.
+pub struct BookReplay<H: BookHistory> {
+    history: H,
+    book: OrderBook,
+    /// Receive time of the last applied update or snapshot
+    position: TimestampNS,
+    pending: VecDeque<LevelUpdated>,
+}
+
+impl<H: BookHistory> BookReplay<H> {
+    /// Book of `exchange`/`ticker` at local receive time `at`. Without an earlier snapshot the
+    /// replay starts from an empty book and the first stored update.
+    pub async fn at(history: H, exchange: Exchange, ticker: &str, max_depth: usize, at: TimestampNS) -> Result<Self, Level2Error> {
+        let snapshot = history.snapshot_before(&exchange, ticker, at).await?;
+        let (book, position) = match snapshot {
+            Some(s) => (OrderBook::from_snapshot(&s, max_depth), s.received),
+            None => (OrderBook::new(exchange, ticker, max_depth), 0),
+        };
+        let mut replay = Self { history, book, position, pending: VecDeque::new() };
+
+        loop {
+            let page = replay.fetch(at).await?;
+            let done = page < PAGE;
+            while let Some(event) = replay.pending.pop_front() {
+                replay.apply(event)?;
+            }
+            if done {
+                break;
+            }
+        }
+        Ok(replay)
+    }
+
+    pub fn book(&self) -> &OrderBook {
+        &self.book
+    }
+
+    pub fn position(&self) -> TimestampNS {
+        self.position
+    }
+
+    /// Applies the next stored update, `None` at the end of the data
+    pub async fn step(&mut self) -> Result<Option<LevelUpdated>, Level2Error> {
+        if self.pending.is_empty() {
+            self.fetch(TimestampNS::MAX).await?;
+        }
+        let Some(event) = self.pending.pop_front() else {
+            return Ok(None);
+        };
+        self.apply(event.clone())?;
+        Ok(Some(event))
+    }
+
+    async fn fetch(&mut self, until: TimestampNS) -> Result<usize, Level2Error> {
+        let ticker = self.book.ticker().to_string();
+        let page = self
+            .history
+            .updates_after(self.book.exchange(), &ticker, self.position, until, PAGE)
+            .await?;
+        let count = page.len();
+        self.pending.extend(page);
+        Ok(count)
+    }
+
+    fn apply(&mut self, event: LevelUpdated) -> Result<(), Level2Error> {
+        self.book.update(&event)?;
+        self.position = event.received;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::level2::OrderBookSnapshot;
+    use crate::shared::{Price, Quantity, Side};
+    use std::sync::Arc;
+
+    struct MemoryHistory {
+        snapshots: Vec<OrderBookSnapshot>,
+        updates: Vec<LevelUpdated>,
+    }
+
+    impl BookHistory for MemoryHistory {
+        async fn snapshot_before(&self, _: &Exchange, _: &str, at: TimestampNS) -> Result<Option<OrderBookSnapshot>, Level2Error> {
+            Ok(self.snapshots.iter().rev().find(|s| s.received <= at).cloned())
+        }
+
+        async fn updates_after(
+            &self,
+            _: &Exchange,
+            _: &str,
+            after: TimestampNS,
+            until: TimestampNS,
+            limit: usize,
+        ) -> Result<Vec<LevelUpdated>, Level2Error> {
+            let mut page: Vec<LevelUpdated> =
+                self.updates.iter().filter(|u| u.received > after && u.received <= until).take(limit).cloned().collect();
+            if let Some(last) = page.last().map(|u| u.received).filter(|_| page.len() == limit) {
+                page.retain(|u| u.received != last);
+                page.extend(self.updates.iter().filter(|u| u.received == last).cloned());
+            }
+            Ok(page)
+        }
+    }
+
+    fn update(side: Side, price: Price, quantity: Quantity, received: TimestampNS) -> LevelUpdated {
+        LevelUpdated {
+            exchange: Exchange::Binance,
+            ticker: Arc::new("btc/usdt".to_string()),
+            side,
+            price,
+            quantity,
+            timestamp: received,
+            received,
+            sequence: 0,
+            prev_sequence: 0,
+        }
+    }
+
+    fn history(with_snapshot: bool) -> MemoryHistory {
+        let snapshot = OrderBookSnapshot {
+            exchange: Exchange::Binance,
+            ticker: Arc::new("btc/usdt".to_string()),
+            bids: vec![(100, 5)],
+            asks: vec![(102, 5)],
+            timestamp: 20,
+            received: 20,
+        };
+        MemoryHistory {
+            snapshots: if with_snapshot { vec![snapshot] } else { vec![] },
+            updates: vec![
+                update(Side::Buy, 100, 5, 10),
+                update(Side::Sell, 102, 5, 20),
+                update(Side::Buy, 101, 1, 30),
+                update(Side::Sell, 102, 0, 40),
+                update(Side::Sell, 103, 2, 50),
+            ],
+        }
+    }
+
+    #[tokio::test]
+    async fn test_book_at_time() {
+        for with_snapshot in [true, false] {
+            let replay = BookReplay::at(history(with_snapshot), Exchange::Binance, "btc/usdt", 10, 45).await.unwrap();
+            assert_eq!(replay.position(), 40);
+            assert_eq!(replay.book().bids().best_price(), Some(101));
+            assert_eq!(replay.book().asks().best_price(), None);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_step_forward() {
+        let mut replay = BookReplay::at(history(true), Exchange::Binance, "btc/usdt", 10, 25).await.unwrap();
+        assert_eq!(replay.book().bids().best_price(), Some(100));
+
+        assert_eq!(replay.step().await.unwrap().map(|u| u.received), Some(30));
+        assert_eq!(replay.book().bids().best_price(), Some(101));
+        replay.step().await.unwrap();
+        replay.step().await.unwrap();
+        assert_eq!(replay.book().asks().best_price(), Some(103));
+        assert!(replay.step().await.unwrap().is_none());
+        assert_eq!(replay.position(), 50);
+    }
+
+    #[tokio::test]
+    async fn test_pages_keep_updates_sharing_a_receive_time() {
+        // Three updates per receive time, a page boundary falls between two of them
+        let updates = (1..=4_000).flat_map(|r| (0..3).map(move |i| update(Side::Buy, 100 + i, 1, r))).collect();
+        let history = MemoryHistory { snapshots: vec![], updates };
+
+        let mut replay = BookReplay::at(history, Exchange::Binance, "btc/usdt", 10, 0).await.unwrap();
+        let mut steps = 0;
+        while replay.step().await.unwrap().is_some() {
+            steps += 1;
+        }
+        assert_eq!(steps, 12_000);
+    }
+}
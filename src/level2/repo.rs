@@ -1,10 +1,12 @@
-use crate::level2::{Level2Error, LevelUpdated};
+use crate::level2::{BookMetrics, Level2Error, LevelUpdated, OrderBookSnapshot};
+use crate::shared::{Exchange, Side, TimestampNS};
 use crate::shared::logger::Logger;
 use crate::shared::utils::buffer_service::Callback;
 use clickhouse::error::Error;
 use clickhouse::insert::Insert;
 use clickhouse::Client;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
 
 #[derive(clickhouse::Row, Serialize)]
 struct LevelUpdateRow {
@@ -54,6 +56,75 @@ impl<'a> LevelUpdatedRepo<'a> {
         insert.end().await?;
         Ok(())
     }
+
+    /// Up to `limit` updates with `after < received <= until`, oldest first, plus every other
+    /// update sharing the receive time of the last one so the next page can start after it
+    pub async fn updates_after(
+        &self,
+        exchange: &Exchange,
+        ticker: &str,
+        after: TimestampNS,
+        until: TimestampNS,
+        limit: usize,
+    ) -> Result<Vec<LevelUpdated>, Level2Error> {
+        let mut rows: Vec<StoredLevelRow> = self
+            .client
+            .query(
+                "SELECT side, price, quantity, timestamp, received FROM level_updates \
+                 WHERE exchange = ? AND ticker = ? AND received > ? AND received <= ? \
+                 ORDER BY received, side, price, quantity LIMIT ?",
+            )
+            .bind(exchange.clone() as u8)
+            .bind(ticker)
+            .bind(after)
+            .bind(until)
+            .bind(limit as u64)
+            .fetch_all()
+            .await?;
+
+        if let Some(last) = rows.last().map(|row| row.received).filter(|_| rows.len() == limit) {
+            let rest: Vec<StoredLevelRow> = self
+                .client
+                .query(
+                    "SELECT side, price, quantity, timestamp, received FROM level_updates \
+                     WHERE exchange = ? AND ticker = ? AND received = ? \
+                     ORDER BY side, price, quantity",
+                )
+                .bind(exchange.clone() as u8)
+                .bind(ticker)
+                .bind(last)
+                .fetch_all()
+                .await?;
+            rows.retain(|row| row.received != last);
+            rows.extend(rest);
+        }
+
+        let ticker = Arc::new(ticker.to_string());
+        Ok(rows
+            .into_iter()
+            .map(|row| LevelUpdated {
+                exchange: exchange.clone(),
+                ticker: Arc::clone(&ticker),
+                side: if row.side == Side::Buy as u8 { Side::Buy } else { Side::Sell },
+                price: row.price,
+                quantity: row.quantity,
+                timestamp: row.timestamp,
+                received: row.received,
+                // Sequences are not stored
+                sequence: 0,
+                prev_sequence: 0,
+            })
+            .collect())
+    }
+}
+
+#[derive(clickhouse::Row, Deserialize)]
+struct StoredLevelRow {
+    side: u8,
+    price: u64,
+    quantity: u64,
+    timestamp: u64,
+    received: u64,
 }
 
 impl<'a> Callback<LevelUpdated, Level2Error> for LevelUpdatedRepo<'a> {
@@ -62,6 +133,161 @@ impl<'a> Callback<LevelUpdated, Level2Error> for LevelUpdatedRepo<'a> {
     }
 }
 
+#[derive(clickhouse::Row, Serialize)]
+struct BookMetricsRow {
+    exchange: u8,
+    ticker: String,
+    mid_price: f64,
+    spread: u64,
+    spread_bps: f64,
+    microprice: f64,
+    imbalance: f64,
+    weighted_imbalance: f64,
+    timestamp: u64,
+    received: u64,
+}
+
+impl BookMetricsRow {
+    pub fn from_book_metrics(m: &BookMetrics) -> Self {
+        Self {
+            exchange: m.exchange.clone() as u8,
+            ticker: m.ticker.to_string(),
+            mid_price: m.mid_price,
+            spread: m.spread,
+            spread_bps: m.spread_bps,
+            microprice: m.microprice,
+            imbalance: m.imbalance,
+            weighted_imbalance: m.weighted_imbalance,
+            timestamp: m.timestamp,
+            received: m.received,
+        }
+    }
+}
+
+pub struct BookMetricsRepo<'a> {
+    client: &'a Client,
+}
+
+impl<'a> BookMetricsRepo<'a> {
+    pub fn new(client: &'a Client) -> Self {
+        Self { client }
+    }
+
+    pub async fn save(&self, metrics: &[BookMetrics]) -> Result<(), Level2Error> {
+        if metrics.is_empty() {
+            return Ok(());
+        }
+
+        let mut insert: Insert<BookMetricsRow> = self.client.insert("book_metrics").await?;
+        for m in metrics {
+            insert.write(&BookMetricsRow::from_book_metrics(m)).await?;
+        }
+        insert.end().await?;
+        Ok(())
+    }
+}
+
+impl<'a> Callback<BookMetrics, Level2Error> for BookMetricsRepo<'a> {
+    async fn on_buffer_flush(&self, data: &[BookMetrics]) -> Result<(), Level2Error> {
+        self.save(data).await
+    }
+}
+
+#[derive(clickhouse::Row, Serialize)]
+struct OrderBookSnapshotRow {
+    exchange: u8,
+    ticker: String,
+    bid_prices: Vec<u64>,
+    bid_quantities: Vec<u64>,
+    ask_prices: Vec<u64>,
+    ask_quantities: Vec<u64>,
+    timestamp: u64,
+    received: u64,
+}
+
+impl OrderBookSnapshotRow {
+    pub fn from_snapshot(s: &OrderBookSnapshot) -> Self {
+        Self {
+            exchange: s.exchange.clone() as u8,
+            ticker: s.ticker.to_string(),
+            bid_prices: s.bids.iter().map(|l| l.0).collect(),
+            bid_quantities: s.bids.iter().map(|l| l.1).collect(),
+            ask_prices: s.asks.iter().map(|l| l.0).collect(),
+            ask_quantities: s.asks.iter().map(|l| l.1).collect(),
+            timestamp: s.timestamp,
+            received: s.received,
+        }
+    }
+}
+
+pub struct OrderBookSnapshotRepo<'a> {
+    client: &'a Client,
+}
+
+impl<'a> OrderBookSnapshotRepo<'a> {
+    pub fn new(client: &'a Client) -> Self {
+        Self { client }
+    }
+
+    pub async fn save(&self, snapshots: &[OrderBookSnapshot]) -> Result<(), Level2Error> {
+        if snapshots.is_empty() {
+            return Ok(());
+        }
+
+        let mut insert: Insert<OrderBookSnapshotRow> = self.client.insert("order_book_snapshots").await?;
+        for s in snapshots {
+            insert.write(&OrderBookSnapshotRow::from_snapshot(s)).await?;
+        }
+        insert.end().await?;
+        Ok(())
+    }
+
+    /// The latest snapshot with `received <= at`
+    pub async fn latest_before(
+        &self,
+        exchange: &Exchange,
+        ticker: &str,
+        at: TimestampNS,
+    ) -> Result<Option<OrderBookSnapshot>, Level2Error> {
+        let row: Option<StoredSnapshotRow> = self
+            .client
+            .query(
+                "SELECT bid_prices, bid_quantities, ask_prices, ask_quantities, timestamp, received \
+                 FROM order_book_snapshots WHERE exchange = ? AND ticker = ? AND received <= ? \
+                 ORDER BY received DESC LIMIT 1",
+            )
+            .bind(exchange.clone() as u8)
+            .bind(ticker)
+            .bind(at)
+            .fetch_optional()
+            .await?;
+        Ok(row.map(|row| OrderBookSnapshot {
+            exchange: exchange.clone(),
+            ticker: Arc::new(ticker.to_string()),
+            bids: row.bid_prices.into_iter().zip(row.bid_quantities).collect(),
+            asks: row.ask_prices.into_iter().zip(row.ask_quantities).collect(),
+            timestamp: row.timestamp,
+            received: row.received,
+        }))
+    }
+}
+
+#[derive(clickhouse::Row, Deserialize)]
+struct StoredSnapshotRow {
+    bid_prices: Vec<u64>,
+    bid_quantities: Vec<u64>,
+    ask_prices: Vec<u64>,
+    ask_quantities: Vec<u64>,
+    timestamp: u64,
+    received: u64,
+}
+
+impl<'a> Callback<OrderBookSnapshot, Level2Error> for OrderBookSnapshotRepo<'a> {
+    async fn on_buffer_flush(&self, data: &[OrderBookSnapshot]) -> Result<(), Level2Error> {
+        self.save(data).await
+    }
+}
+
 pub async fn create_level_updates_table(
     client: &Client,
     logger: &Logger,
@@ -86,3 +312,57 @@ pub async fn create_level_updates_table(
     );
     client.query(&query).execute().await
 }
+
+pub async fn create_book_metrics_table(
+    client: &Client,
+    logger: &Logger,
+    db_name: &str,
+) -> Result<(), Error> {
+    logger.info("Creating book metrics table");
+
+    let query = format!(
+        r#"
+        CREATE TABLE IF NOT EXISTS {}.book_metrics (
+            exchange UInt8,
+            ticker String,
+            mid_price Float64,
+            spread UInt64,
+            spread_bps Float64,
+            microprice Float64,
+            imbalance Float64,
+            weighted_imbalance Float64,
+            timestamp UInt64,
+            received UInt64
+        ) ENGINE = MergeTree()
+        ORDER BY (exchange, ticker, received)
+    "#,
+        db_name
+    );
+    client.query(&query).execute().await
+}
+
+pub async fn create_order_book_snapshots_table(
+    client: &Client,
+    logger: &Logger,
+    db_name: &str,
+) -> Result<(), Error> {
+    logger.info("Creating order book snapshots table");
+
+    let query = format!(
+        r#"
+        CREATE TABLE IF NOT EXISTS {}.order_book_snapshots (
+            exchange UInt8,
+            ticker String,
+            bid_prices Array(UInt64),
+            bid_quantities Array(UInt64),
+            ask_prices Array(UInt64),
+            ask_quantities Array(UInt64),
+            timestamp UInt64,
+            received UInt64
+        ) ENGINE = MergeTree()
+        ORDER BY (exchange, ticker, received)
+    "#,
+        db_name
+    );
+    client.query(&query).execute().await
+}
@@ -1,6 +1,6 @@
-use crate::level2::OrderBook;
+use crate::level2::{ConsolidatedBook, ConsolidatedLevel, OrderBook};
 use crate::shared::utils::format_price;
-use crate::shared::Exchange;
+use crate::shared::{Exchange, Side};
 
 pub fn display_books(books: &[&OrderBook], decimals: usize) {
     print!("\x1b[H\x1b[2J");
@@ -23,6 +23,8 @@ pub fn display_books(books: &[&OrderBook], decimals: usize) {
         let exc = match book.exchange() {
             Exchange::Binance => "binance",
             Exchange::Kraken => "kraken",
+            Exchange::Deribit => "deribit",
+            Exchange::Hyperliquid => "hyperliquid",
         };
 
         println!("{:<12} {:>12} {:>12}", exc, bid, ask);
@@ -30,3 +32,35 @@ pub fn display_books(books: &[&OrderBook], decimals: usize) {
 
     std::io::Write::flush(&mut std::io::stdout()).unwrap();
 }
+
+/// Ladder of the best `depth` levels, asks above bids
+pub fn print_book(book: &OrderBook, depth: usize, decimals: usize) {
+    println!("{} {}", book.exchange().to_str(), book.ticker());
+    if book.is_truncated() {
+        println!(
+            "truncated: {} bid and {} ask levels known",
+            book.bids().known_depth(),
+            book.asks().known_depth()
+        );
+    }
+    println!("{:>16} {:>16}", "Price", "Quantity");
+    for level in book.depth(Side::Sell, depth).iter().rev() {
+        println!("{:>16} {:>16}  ask", format_price(level.price, decimals), level.quantity);
+    }
+    println!("{}", "-".repeat(38));
+    for level in book.depth(Side::Buy, depth) {
+        println!("{:>16} {:>16}  bid", format_price(level.price, decimals), level.quantity);
+    }
+}
+
+/// Best bid and ask across venues with the quantity each venue shows there
+pub fn print_consolidated(book: &ConsolidatedBook, decimals: usize) {
+    let level = |level: Option<&ConsolidatedLevel>| match level {
+        Some(level) => {
+            let venues: Vec<String> = level.venues.iter().map(|(e, q)| format!("{} {}", e.to_str(), q)).collect();
+            format!("{} x {} ({})", format_price(level.price, decimals), level.quantity, venues.join(", "))
+        }
+        None => "-".to_string(),
+    };
+    println!("{} across venues: bid {} / ask {}", book.ticker(), level(book.best_bid()), level(book.best_ask()));
+}
@@ -0,0 +1,193 @@
+use crate::level2::{LevelUpdated, OrderBook, PriceLevels};
+use crate::shared::{Exchange, Price, Quantity, Side, TimestampMS, TimestampNS};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Full state of an `OrderBook` right after the update with `received`
+#[derive(Debug, Clone, PartialEq)]
+pub struct OrderBookSnapshot {
+    pub exchange: Exchange,
+    pub ticker: Arc<String>,
+    /// Best first
+    pub bids: Vec<(Price, Quantity)>,
+    pub asks: Vec<(Price, Quantity)>,
+    pub timestamp: TimestampMS,
+    pub received: TimestampNS,
+}
+
+impl OrderBookSnapshot {
+    /// Takes every kept level, the hidden buffer past `max_depth` included
+    pub fn from_book<L: PriceLevels>(book: &OrderBook<L>, timestamp: TimestampMS, received: TimestampNS) -> Self {
+        let levels = |side| book.get_side(side).levels().collect();
+        Self {
+            exchange: book.exchange().clone(),
+            ticker: Arc::clone(book.ticker()),
+            bids: levels(Side::Buy),
+            asks: levels(Side::Sell),
+            timestamp,
+            received,
+        }
+    }
+}
+
+/// Decides when a book is due for a snapshot: every `interval` of event time or every `updates`
+/// updates, whichever comes first. Books that are not `Valid` produce none, the first update
+/// of a book and the first one after it becomes valid again always produce one.
+pub struct SnapshotWriter {
+    interval: Option<Duration>,
+    updates: Option<usize>,
+    /// Receive time of the last snapshot and updates since, per exchange and ticker
+    books: HashMap<(&'static str, Arc<String>), (TimestampNS, usize)>,
+}
+
+impl SnapshotWriter {
+    pub fn new() -> Self {
+        Self { interval: None, updates: None, books: HashMap::new() }
+    }
+
+    pub fn every(mut self, interval: Duration) -> Self {
+        self.interval = Some(interval);
+        self
+    }
+
+    pub fn every_updates(mut self, updates: usize) -> Self {
+        self.updates = Some(updates);
+        self
+    }
+
+    /// Call after `event` was applied to `book`
+    pub fn observe<L: PriceLevels>(&mut self, book: &OrderBook<L>, event: &LevelUpdated) -> Option<OrderBookSnapshot> {
+        if book.exchange() != &event.exchange || book.ticker() != &event.ticker {
+            return None;
+        }
+        let key = (event.exchange.to_str(), Arc::clone(&event.ticker));
+        if !book.is_valid() {
+            self.books.remove(&key);
+            return None;
+        }
+        let due = match self.books.get_mut(&key) {
+            None => true,
+            Some((last, count)) => {
+                *count += 1;
+                let by_time = self
+                    .interval
+                    .is_some_and(|i| event.received.saturating_sub(*last) >= i.as_nanos() as u64);
+                let by_count = self.updates.is_some_and(|n| *count >= n);
+                by_time || by_count
+            }
+        };
+        if !due {
+            return None;
+        }
+        self.books.insert(key, (event.received, 0));
+        Some(OrderBookSnapshot::from_book(book, event.timestamp, event.received))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(side: Side, price: Price, quantity: Quantity, received: TimestampNS) -> LevelUpdated {
+        LevelUpdated {
+            exchange: Exchange::Kraken,
+            ticker: Arc::new("btc/usdt".to_string()),
+            side,
+            price,
+            quantity,
+            timestamp: received / 1_000_000,
+            received,
+            sequence: 0,
+            prev_sequence: 0,
+        }
+    }
+
+    fn apply(book: &mut OrderBook, writer: &mut SnapshotWriter, ev: LevelUpdated) -> Option<OrderBookSnapshot> {
+        book.update(&ev).unwrap();
+        writer.observe(book, &ev)
+    }
+
+    /// Book with a bid at 90 and an ask at 200 so it is valid from the first observed update
+    fn two_sided() -> OrderBook {
+        let mut book = OrderBook::new(Exchange::Kraken, "btc/usdt", 10);
+        book.update(&event(Side::Buy, 90, 1, 0)).unwrap();
+        book.update(&event(Side::Sell, 200, 1, 0)).unwrap();
+        book
+    }
+
+    #[test]
+    fn test_snapshot_contents() {
+        let mut book = OrderBook::new(Exchange::Kraken, "btc/usdt", 10);
+        book.update(&event(Side::Buy, 99, 1, 1)).unwrap();
+        book.update(&event(Side::Buy, 100, 2, 2)).unwrap();
+        book.update(&event(Side::Sell, 101, 3, 3)).unwrap();
+
+        let snapshot = OrderBookSnapshot::from_book(&book, 7, 3);
+        assert_eq!(snapshot.bids, vec![(100, 2), (99, 1)]);
+        assert_eq!(snapshot.asks, vec![(101, 3)]);
+        assert_eq!((snapshot.timestamp, snapshot.received), (7, 3));
+    }
+
+    #[test]
+    fn test_snapshot_keeps_hidden_levels() {
+        let mut book = OrderBook::new(Exchange::Kraken, "btc/usdt", 2).with_buffer(4);
+        for price in [97, 98, 99, 100] {
+            book.update(&event(Side::Buy, price, 1, 1)).unwrap();
+        }
+        book.update(&event(Side::Sell, 101, 1, 2)).unwrap();
+
+        let snapshot = OrderBookSnapshot::from_book(&book, 0, 2);
+        assert_eq!(snapshot.bids, vec![(100, 1), (99, 1), (98, 1), (97, 1)]);
+
+        // Removing the top levels brings the buffered ones into view after a restore
+        let mut restored: OrderBook = OrderBook::from_snapshot(&snapshot, 2);
+        restored.update(&event(Side::Buy, 100, 0, 3)).unwrap();
+        restored.update(&event(Side::Buy, 99, 0, 3)).unwrap();
+        assert_eq!(restored.bids().best_prices(2).collect::<Vec<_>>(), vec![98, 97]);
+    }
+
+    #[test]
+    fn test_skips_invalid_books() {
+        let mut book = OrderBook::new(Exchange::Kraken, "btc/usdt", 10);
+        let mut writer = SnapshotWriter::new().every_updates(100);
+
+        // One-sided while initializing
+        assert!(apply(&mut book, &mut writer, event(Side::Buy, 100, 1, 1)).is_none());
+        let snapshot = apply(&mut book, &mut writer, event(Side::Sell, 101, 1, 2)).unwrap();
+        assert_eq!((snapshot.bids.len(), snapshot.asks.len()), (1, 1));
+        assert!(apply(&mut book, &mut writer, event(Side::Sell, 102, 1, 3)).is_none());
+
+        // Crossed, then valid again
+        assert!(apply(&mut book, &mut writer, event(Side::Buy, 101, 1, 4)).is_none());
+        assert!(apply(&mut book, &mut writer, event(Side::Buy, 101, 0, 5)).is_some());
+    }
+
+    #[test]
+    fn test_every_updates() {
+        let mut book = two_sided();
+        let mut writer = SnapshotWriter::new().every_updates(3);
+
+        assert!(apply(&mut book, &mut writer, event(Side::Buy, 100, 1, 1)).is_some());
+        assert!(apply(&mut book, &mut writer, event(Side::Buy, 100, 2, 2)).is_none());
+        assert!(apply(&mut book, &mut writer, event(Side::Buy, 100, 3, 3)).is_none());
+        let snapshot = apply(&mut book, &mut writer, event(Side::Buy, 100, 4, 4)).unwrap();
+        assert_eq!(snapshot.bids, vec![(100, 4), (90, 1)]);
+    }
+
+    #[test]
+    fn test_every_interval() {
+        let mut book = two_sided();
+        let mut writer = SnapshotWriter::new().every(Duration::from_secs(1));
+        let second = 1_000_000_000;
+
+        assert!(apply(&mut book, &mut writer, event(Side::Sell, 100, 1, second)).is_some());
+        assert!(apply(&mut book, &mut writer, event(Side::Sell, 101, 1, second + second / 2)).is_none());
+        assert!(apply(&mut book, &mut writer, event(Side::Sell, 102, 1, 2 * second)).is_some());
+
+        // Other books are not snapshotted
+        let mut other = event(Side::Sell, 100, 1, 3 * second);
+        other.exchange = Exchange::Binance;
+        assert!(writer.observe(&book, &other).is_none());
+    }
+}
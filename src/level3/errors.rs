@@ -0,0 +1,10 @@
+use crate::shared::errors::BaseError;
+
+#[derive(Debug, thiserror::Error)]
+pub enum Level3Error {
+    #[error("EventError")]
+    EventError(#[from] BaseError),
+
+    #[error("UnknownOrder: {0}")]
+    UnknownOrder(String),
+}
@@ -0,0 +1,24 @@
+use crate::shared::{Exchange, Price, Quantity, Side, TimestampMS, TimestampNS};
+use std::sync::Arc;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum OrderAction {
+    Add,
+    Modify,
+    Delete,
+    /// Every known order of the ticker is gone, a snapshot follows. Side, price and quantity are unused
+    Clear,
+}
+
+#[derive(Debug, Clone)]
+pub struct OrderEvent {
+    pub exchange: Exchange,
+    pub ticker: Arc<String>,
+    pub order_id: Arc<String>,
+    pub action: OrderAction,
+    pub side: Side,
+    pub price: Price,
+    pub quantity: Quantity,
+    pub timestamp: TimestampMS,
+    pub received: TimestampNS,
+}
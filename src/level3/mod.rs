@@ -0,0 +1,7 @@
+mod errors;
+mod events;
+mod order_book;
+
+pub use errors::Level3Error;
+pub use events::{OrderAction, OrderEvent};
+pub use order_book::OrderByOrderBook;
@@ -0,0 +1,312 @@
+use crate::level2::{Level2Error, LevelUpdated, OrderBook};
+use crate::level3::{Level3Error, OrderAction, OrderEvent};
+use crate::shared::errors::{check_exchange, check_ticker};
+use crate::shared::{Exchange, Price, Quantity, Side};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+
+struct RestingOrder {
+    side: Side,
+    price: Price,
+    quantity: Quantity,
+}
+
+/// Book of individual orders. Every change is reported as the aggregated level it touched,
+/// so the result can be fed straight into `OrderBook::update`.
+pub struct OrderByOrderBook {
+    exchange: Exchange,
+    ticker: Arc<String>,
+    orders: HashMap<String, RestingOrder>,
+    bids: BTreeMap<Price, Quantity>,
+    asks: BTreeMap<Price, Quantity>,
+}
+
+impl OrderByOrderBook {
+    pub fn new(exchange: Exchange, ticker: &str) -> Self {
+        Self {
+            exchange,
+            ticker: Arc::new(ticker.to_string()),
+            orders: HashMap::new(),
+            bids: BTreeMap::new(),
+            asks: BTreeMap::new(),
+        }
+    }
+
+    fn levels(&self, side: Side) -> &BTreeMap<Price, Quantity> {
+        match side {
+            Side::Buy => &self.bids,
+            Side::Sell => &self.asks,
+        }
+    }
+
+    fn levels_mut(&mut self, side: Side) -> &mut BTreeMap<Price, Quantity> {
+        match side {
+            Side::Buy => &mut self.bids,
+            Side::Sell => &mut self.asks,
+        }
+    }
+
+    fn add_quantity(&mut self, side: Side, price: Price, qty: Quantity) {
+        *self.levels_mut(side).entry(price).or_insert(0) += qty;
+    }
+
+    fn remove_quantity(&mut self, side: Side, price: Price, qty: Quantity) {
+        let levels = self.levels_mut(side);
+        if let Some(total) = levels.get_mut(&price) {
+            *total = total.saturating_sub(qty);
+            if *total == 0 {
+                levels.remove(&price);
+            }
+        }
+    }
+
+    fn remove_order(&mut self, order_id: &str) -> Result<RestingOrder, Level3Error> {
+        let order = self
+            .orders
+            .remove(order_id)
+            .ok_or_else(|| Level3Error::UnknownOrder(order_id.to_string()))?;
+        self.remove_quantity(order.side, order.price, order.quantity);
+        Ok(order)
+    }
+
+    fn level_event(&self, event: &OrderEvent, side: Side, price: Price) -> LevelUpdated {
+        LevelUpdated {
+            exchange: self.exchange.clone(),
+            ticker: Arc::clone(&self.ticker),
+            side,
+            price,
+            quantity: self.level_quantity(side, price),
+            timestamp: event.timestamp,
+            received: event.received,
+            sequence: 0,
+            prev_sequence: 0,
+        }
+    }
+
+    /// Applies an order event and returns the aggregated levels it changed
+    pub fn update(&mut self, event: &OrderEvent) -> Result<Vec<LevelUpdated>, Level3Error> {
+        check_exchange(&self.exchange, &event.exchange)?;
+        check_ticker(&self.ticker, &event.ticker)?;
+
+        let mut touched = vec![(event.side, event.price)];
+
+        match event.action {
+            OrderAction::Clear => {
+                let bids = std::mem::take(&mut self.bids).into_keys().map(|price| (Side::Buy, price));
+                let asks = std::mem::take(&mut self.asks).into_keys().map(|price| (Side::Sell, price));
+                touched = bids.chain(asks).collect();
+                self.orders.clear();
+            }
+            OrderAction::Add | OrderAction::Modify => {
+                match self.remove_order(&event.order_id) {
+                    Ok(prev) => {
+                        if (prev.side, prev.price) != (event.side, event.price) {
+                            touched.insert(0, (prev.side, prev.price));
+                        }
+                    }
+                    // A snapshot after a resubscription re-adds orders we already know
+                    Err(err) if event.action == OrderAction::Modify => return Err(err),
+                    Err(_) => {}
+                }
+                if event.quantity > 0 {
+                    self.add_quantity(event.side, event.price, event.quantity);
+                    self.orders.insert(
+                        event.order_id.to_string(),
+                        RestingOrder {
+                            side: event.side,
+                            price: event.price,
+                            quantity: event.quantity,
+                        },
+                    );
+                }
+            }
+            OrderAction::Delete => {
+                let prev = self.remove_order(&event.order_id)?;
+                touched[0] = (prev.side, prev.price);
+            }
+        }
+
+        Ok(touched
+            .into_iter()
+            .map(|(side, price)| self.level_event(event, side, price))
+            .collect())
+    }
+
+    pub fn order(&self, order_id: &str) -> Option<(Side, Price, Quantity)> {
+        self.orders
+            .get(order_id)
+            .map(|o| (o.side, o.price, o.quantity))
+    }
+
+    pub fn order_count(&self) -> usize {
+        self.orders.len()
+    }
+
+    pub fn level_quantity(&self, side: Side, price: Price) -> Quantity {
+        self.levels(side).get(&price).copied().unwrap_or(0)
+    }
+
+    /// Aggregates orders into a regular level 2 book
+    pub fn to_order_book(&self, max_depth: usize) -> Result<OrderBook, Level2Error> {
+        let mut book = OrderBook::new(self.exchange.clone(), &self.ticker, max_depth);
+        let sides = [
+            (Side::Buy, self.bids.iter().rev().take(max_depth).collect::<Vec<_>>()),
+            (Side::Sell, self.asks.iter().take(max_depth).collect::<Vec<_>>()),
+        ];
+        for (side, levels) in sides {
+            for (price, quantity) in levels {
+                let ev = LevelUpdated {
+                    exchange: self.exchange.clone(),
+                    ticker: Arc::clone(&self.ticker),
+                    side,
+                    price: *price,
+                    quantity: *quantity,
+                    timestamp: 0,
+                    received: 0,
+                    sequence: 0,
+                    prev_sequence: 0,
+                };
+                book.update(&ev)?;
+            }
+        }
+        Ok(book)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shared::utils::now_timestamp_ns;
+
+    fn ev(id: &str, action: OrderAction, side: Side, price: Price, qty: Quantity) -> OrderEvent {
+        OrderEvent {
+            exchange: Exchange::Kraken,
+            ticker: Arc::new("btc/usd".to_string()),
+            order_id: Arc::new(id.to_string()),
+            action,
+            side,
+            price,
+            quantity: qty,
+            timestamp: 0,
+            received: now_timestamp_ns(),
+        }
+    }
+
+    fn book() -> OrderByOrderBook {
+        OrderByOrderBook::new(Exchange::Kraken, "btc/usd")
+    }
+
+    fn levels(updates: &[LevelUpdated]) -> Vec<(Side, Price, Quantity)> {
+        updates.iter().map(|u| (u.side, u.price, u.quantity)).collect()
+    }
+
+    #[test]
+    fn test_add_aggregates_orders_on_level() {
+        let mut b = book();
+        b.update(&ev("a", OrderAction::Add, Side::Buy, 100, 5)).unwrap();
+        let updates = b.update(&ev("b", OrderAction::Add, Side::Buy, 100, 3)).unwrap();
+
+        assert_eq!(levels(&updates), vec![(Side::Buy, 100, 8)]);
+        assert_eq!(b.order_count(), 2);
+    }
+
+    #[test]
+    fn test_modify_quantity() {
+        let mut b = book();
+        b.update(&ev("a", OrderAction::Add, Side::Sell, 200, 5)).unwrap();
+        b.update(&ev("b", OrderAction::Add, Side::Sell, 200, 5)).unwrap();
+        let updates = b.update(&ev("a", OrderAction::Modify, Side::Sell, 200, 2)).unwrap();
+
+        assert_eq!(levels(&updates), vec![(Side::Sell, 200, 7)]);
+        assert_eq!(b.order("a"), Some((Side::Sell, 200, 2)));
+    }
+
+    #[test]
+    fn test_modify_price_touches_both_levels() {
+        let mut b = book();
+        b.update(&ev("a", OrderAction::Add, Side::Buy, 100, 5)).unwrap();
+        let updates = b.update(&ev("a", OrderAction::Modify, Side::Buy, 101, 5)).unwrap();
+
+        assert_eq!(levels(&updates), vec![(Side::Buy, 100, 0), (Side::Buy, 101, 5)]);
+    }
+
+    #[test]
+    fn test_delete() {
+        let mut b = book();
+        b.update(&ev("a", OrderAction::Add, Side::Buy, 100, 5)).unwrap();
+        b.update(&ev("b", OrderAction::Add, Side::Buy, 100, 1)).unwrap();
+        let updates = b.update(&ev("a", OrderAction::Delete, Side::Buy, 100, 0)).unwrap();
+
+        assert_eq!(levels(&updates), vec![(Side::Buy, 100, 1)]);
+        assert_eq!(b.order("a"), None);
+    }
+
+    #[test]
+    fn test_unknown_order() {
+        let mut b = book();
+        assert!(b.update(&ev("x", OrderAction::Delete, Side::Buy, 100, 0)).is_err());
+        assert!(b.update(&ev("x", OrderAction::Modify, Side::Buy, 100, 1)).is_err());
+    }
+
+    #[test]
+    fn test_readd_replaces_order() {
+        let mut b = book();
+        b.update(&ev("a", OrderAction::Add, Side::Buy, 100, 5)).unwrap();
+        b.update(&ev("a", OrderAction::Add, Side::Buy, 100, 4)).unwrap();
+
+        assert_eq!(b.level_quantity(Side::Buy, 100), 4);
+        assert_eq!(b.order_count(), 1);
+    }
+
+    #[test]
+    fn test_clear_before_snapshot() {
+        let mut b = book();
+        b.update(&ev("a", OrderAction::Add, Side::Buy, 100, 5)).unwrap();
+        b.update(&ev("b", OrderAction::Add, Side::Sell, 101, 1)).unwrap();
+        let updates = b.update(&ev("", OrderAction::Clear, Side::Buy, 0, 0)).unwrap();
+
+        assert_eq!(levels(&updates), vec![(Side::Buy, 100, 0), (Side::Sell, 101, 0)]);
+        assert_eq!(b.order_count(), 0);
+        // Orders missing from the new snapshot do not linger
+        b.update(&ev("c", OrderAction::Add, Side::Buy, 99, 2)).unwrap();
+        let ob = b.to_order_book(10).unwrap();
+        assert_eq!((ob.bids().best_price(), ob.asks().best_price()), (Some(99), None));
+    }
+
+    #[test]
+    fn test_wrong_instrument() {
+        let mut b = book();
+        let mut e = ev("a", OrderAction::Add, Side::Buy, 100, 5);
+        e.exchange = Exchange::Binance;
+        assert!(b.update(&e).is_err());
+    }
+
+    #[test]
+    fn test_to_order_book() {
+        let mut b = book();
+        b.update(&ev("a", OrderAction::Add, Side::Buy, 100, 5)).unwrap();
+        b.update(&ev("b", OrderAction::Add, Side::Buy, 99, 1)).unwrap();
+        b.update(&ev("c", OrderAction::Add, Side::Buy, 98, 1)).unwrap();
+        b.update(&ev("d", OrderAction::Add, Side::Sell, 101, 2)).unwrap();
+
+        let ob = b.to_order_book(2).unwrap();
+        assert_eq!(ob.bids().best_prices(10).collect::<Vec<_>>(), vec![100, 99]);
+        assert_eq!(ob.asks().best_price(), Some(101));
+    }
+
+    #[test]
+    fn test_updates_feed_order_book() {
+        let mut b = book();
+        let mut ob = OrderBook::new(Exchange::Kraken, "btc/usd", 10);
+        for e in [
+            ev("a", OrderAction::Add, Side::Buy, 100, 5),
+            ev("b", OrderAction::Add, Side::Buy, 101, 1),
+            ev("b", OrderAction::Delete, Side::Buy, 101, 0),
+        ] {
+            for level in b.update(&e).unwrap() {
+                ob.update(&level).unwrap();
+            }
+        }
+        assert_eq!(ob.bids().best_price(), Some(100));
+    }
+}
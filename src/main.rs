@@ -1,19 +1,44 @@
+mod account;
+mod backfill;
+mod bbo;
+mod candle;
 mod connector;
 mod db;
+mod dead_letter;
+mod derivatives;
+mod latency;
 mod level2;
+mod level3;
+mod order_entry;
 mod shared;
 mod signal;
 mod trade;
 
+use chrono::{NaiveDate, NaiveDateTime};
 use clickhouse::Client;
+use crate::backfill::{Backfill, BackfillError, BinanceTradeSource, KrakenTradeSource, TradeGap, TradeGapTracker, TradeSource};
+use crate::bbo::BestBidAskRepo;
+use crate::candle::{CandleInterval, CandleRepo};
+use crate::connector::config::ApiCredentials;
+use crate::connector::errors::Error;
 use crate::connector::{Event, StreamConnector};
-use crate::level2::{LevelUpdatedRepo, OrderBook};
+use crate::dead_letter::DeadLetterRepo;
+use crate::latency::LatencyTracker;
+use crate::level2::{
+    compare_levels, load_updates, print_book, print_consolidated, BookMetricsRepo, BookRegistry, BookState, BookReplay,
+    ClickhouseBookHistory, ConsolidatedBook, Level2Error, LevelUpdatedRepo, OrderBookSnapshotRepo, SnapshotWriter,
+};
 use crate::shared::utils::buffer_service::BufferService;
-use crate::shared::Exchange;
+use crate::order_entry::{
+    BinanceOrderEntry, KrakenOrderEntry, OrderEntry, OrderRequest, OrderRouter, SimulatedExchange, SimulationConfig,
+};
+use crate::shared::{Exchange, Side, TimestampNS};
 use crate::signal::arbitrage_monitor::{ArbitrageMonitor, ArbitrageSignalRepo};
 use crate::trade::TradeEventRepo;
 use db::DatabaseClient;
 use futures_util::StreamExt;
+use std::env;
+use std::time::Duration;
 use tokio::sync::broadcast;
 
 // Ticker, multiply for price, multiply for quantity
@@ -36,19 +61,255 @@ async fn get_client() -> Client{
     client
 }
 
+static EXCHANGES: [Exchange; 3] = [Exchange::Binance, Exchange::Kraken, Exchange::Hyperliquid];
+
+const RECONNECT_MIN: Duration = Duration::from_secs(1);
+const RECONNECT_MAX: Duration = Duration::from_secs(60);
+
+/// Keys from `<EXCHANGE>_API_KEY` and `<EXCHANGE>_API_SECRET`, e.g. `BINANCE_API_KEY`
+fn api_keys(exchange: &Exchange) -> Option<ApiCredentials> {
+    let prefix = exchange.to_str().to_uppercase();
+    let api_key = env::var(format!("{}_API_KEY", prefix)).ok()?;
+    let api_secret = env::var(format!("{}_API_SECRET", prefix)).ok()?;
+    Some(ApiCredentials { api_key, api_secret })
+}
+
+fn connector(exchange: &Exchange) -> StreamConnector {
+    let mut connector = StreamConnector::new()
+        .exchanges(std::slice::from_ref(exchange))
+        .tickers(&TICKERS)
+        .subscribe_depth(10)
+        .subscribe_trades()
+        .subscribe_bbo()
+        .subscribe_candles(CandleInterval::M1)
+        .dead_letters()
+        .log_level_info();
+    // Private streams are optional
+    if let Some(keys) = api_keys(exchange) {
+        connector = connector.api_key(exchange.clone(), &keys.api_key, &keys.api_secret);
+    }
+    connector
+}
+
+async fn stream_exchange(exchange: Exchange, tx_events: broadcast::Sender<Event>) {
+    // Trades lost while reconnecting are restored by the saver from the REST history
+    let mut backoff = RECONNECT_MIN;
+    loop {
+        match connector(&exchange).connect().await {
+            Ok(mut stream) => {
+                let mut received = false;
+                while let Some(event) = stream.next().await {
+                    received = true;
+                    tx_events.send(event).unwrap();
+                }
+                if received {
+                    backoff = RECONNECT_MIN;
+                }
+                println!("{} stream closed, reconnecting in {:?}", exchange.to_str(), backoff);
+            }
+            Err(e) => println!("{} connect failed: {}, retrying in {:?}", exchange.to_str(), e, backoff),
+        }
+        tokio::time::sleep(backoff).await;
+        backoff = (backoff * 2).min(RECONNECT_MAX);
+    }
+}
+
 async fn stream(tx_events: broadcast::Sender<Event>) {
+    // One task per exchange: a merged stream only ends once every exchange has dropped, so a
+    // single lost exchange would never reconnect
+    let tasks: Vec<_> = EXCHANGES
+        .iter()
+        .map(|exchange| tokio::spawn(stream_exchange(exchange.clone(), tx_events.clone())))
+        .collect();
+    for task in tasks {
+        task.await.unwrap();
+    }
+}
+
+async fn run_backfill<S: TradeSource>(source: S, client: &Client, gap: &TradeGap) -> Result<usize, BackfillError> {
+    Backfill::new(source, client).fill_gap(gap).await
+}
+
+async fn fill_gap(client: Client, gap: TradeGap) {
+    let res = match gap.exchange {
+        Exchange::Binance => run_backfill(BinanceTradeSource::new(&TICKERS), &client, &gap).await,
+        Exchange::Kraken => run_backfill(KrakenTradeSource::new(&TICKERS), &client, &gap).await,
+        _ => {
+            println!("No trade backfill for {}, {:?} stays missing", gap.exchange.to_str(), gap);
+            return;
+        }
+    };
+    if let Err(err) = res {
+        println!("Backfill of {:?} failed: {}", gap, err);
+    }
+}
+
+// spoofer backfill <binance|kraken> <ticker> <YYYY-MM-DD> [days]
+async fn backfill_days(args: &[String]) -> Result<usize, BackfillError> {
+    let usage = "usage: spoofer backfill <binance|kraken> <ticker> <YYYY-MM-DD> [days]";
+    let ticker = args.get(1).expect(usage);
+    let day = NaiveDate::parse_from_str(args.get(2).expect(usage), "%Y-%m-%d").expect(usage);
+    let days = args.get(3).map(|d| d.parse().expect(usage)).unwrap_or(1);
+
+    let client = get_client().await;
+    match args.first().map(String::as_str) {
+        Some("binance") => Backfill::new(BinanceTradeSource::new(&TICKERS), &client).fill_days(ticker, day, days).await,
+        Some("kraken") => Backfill::new(KrakenTradeSource::new(&TICKERS), &client).fill_days(ticker, day, days).await,
+        _ => panic!("{}", usage),
+    }
+}
+
+fn parse_exchange(arg: Option<&String>, usage: &str) -> Exchange {
+    match arg.map(String::as_str) {
+        Some("binance") => Exchange::Binance,
+        Some("kraken") => Exchange::Kraken,
+        Some("deribit") => Exchange::Deribit,
+        Some("hyperliquid") => Exchange::Hyperliquid,
+        _ => panic!("{}", usage),
+    }
+}
+
+fn parse_time_ms(raw_time: &str, usage: &str) -> u64 {
+    match raw_time.parse::<u64>() {
+        Ok(ms) => ms,
+        Err(_) => NaiveDateTime::parse_from_str(raw_time, "%Y-%m-%dT%H:%M:%S%.f")
+            .expect(usage)
+            .and_utc()
+            .timestamp_millis() as u64,
+    }
+}
+
+// spoofer book <binance|kraken|deribit|hyperliquid> <ticker> <YYYY-MM-DDTHH:MM:SS[.fff]|ms> [steps]
+async fn book_at(args: &[String]) -> Result<(), Level2Error> {
+    let usage = "usage: spoofer book <binance|kraken|deribit|hyperliquid> <ticker> <YYYY-MM-DDTHH:MM:SS[.fff]|ms> [steps]";
+    let exchange = parse_exchange(args.first(), usage);
+    let ticker = args.get(1).expect(usage);
+    let at_ms = parse_time_ms(args.get(2).expect(usage), usage);
+    let steps: usize = args.get(3).map(|s| s.parse().expect(usage)).unwrap_or(0);
+    let decimals = TICKERS
+        .iter()
+        .find(|(t, _, _)| t == ticker)
+        .map(|(_, price_multiply, _)| price_multiply.ilog10() as usize)
+        .unwrap_or(2);
+
+    let client = get_client().await;
+    let history = ClickhouseBookHistory::new(&client);
+    let mut replay = BookReplay::at(history, exchange, ticker, 1_000, at_ms * 1_000_000 + 999_999).await?;
+    print_book(replay.book(), 10, decimals);
+    for _ in 0..steps {
+        let Some(update) = replay.step().await? else { break };
+        println!();
+        println!("{:?} {} @ {} received {}", update.side, update.quantity, update.price, update.received);
+        print_book(replay.book(), 10, decimals);
+    }
+    Ok(())
+}
+
+// spoofer bench-book <binance|kraken|deribit|hyperliquid> <ticker> <YYYY-MM-DDTHH:MM:SS[.fff]|ms> [minutes] [rounds]
+async fn bench_book(args: &[String]) -> Result<(), Level2Error> {
+    let usage = "usage: spoofer bench-book <binance|kraken|deribit|hyperliquid> <ticker> <YYYY-MM-DDTHH:MM:SS[.fff]|ms> [minutes] [rounds]";
+    let exchange = parse_exchange(args.first(), usage);
+    let ticker = args.get(1).expect(usage);
+    let from_ms = parse_time_ms(args.get(2).expect(usage), usage);
+    let minutes: u64 = args.get(3).map(|s| s.parse().expect(usage)).unwrap_or(10);
+    let rounds: usize = args.get(4).map(|s| s.parse().expect(usage)).unwrap_or(5);
+
+    let client = get_client().await;
+    let history = ClickhouseBookHistory::new(&client);
+    let from = from_ms * 1_000_000;
+    let updates = load_updates(&history, &exchange, ticker, from, from + minutes * 60_000_000_000).await?;
+    println!("{} recorded updates", updates.len());
+    match compare_levels(&updates, 1_000, 1_000, rounds) {
+        Ok(results) => {
+            for result in results {
+                println!("{:>6}: {:?} total, {:.1} ns per update", result.name, result.elapsed, result.ns_per_update());
+            }
+        }
+        Err(e) => println!("{}", e),
+    }
+    Ok(())
+}
+
+fn credentials(exchange: &Exchange, paper: bool) -> ApiCredentials {
+    match api_keys(exchange) {
+        Some(keys) => keys,
+        // The simulator never signs a request
+        None if paper => ApiCredentials { api_key: String::new(), api_secret: String::new() },
+        None => {
+            let prefix = exchange.to_str().to_uppercase();
+            panic!("{}_API_KEY and {}_API_SECRET are required for live orders", prefix, prefix)
+        }
+    }
+}
+
+async fn paper_exchange(exchange: &Exchange) -> Result<SimulatedExchange, Error> {
+    let simulated = SimulatedExchange::new(exchange.clone(), &TICKERS, SimulationConfig::default());
     let mut stream = StreamConnector::new()
-        .exchanges(&[Exchange::Binance, Exchange::Kraken])
+        .exchanges(std::slice::from_ref(exchange))
         .tickers(&TICKERS)
         .subscribe_depth(10)
         .subscribe_trades()
-        .log_level_info()
         .connect()
-        .await
-        .unwrap();
-    loop {
-        let event = stream.next().await.unwrap();
-        tx_events.send(event).unwrap();
+        .await?;
+    // A few seconds of live data fill the simulated books before the order arrives
+    let warm_up = async {
+        while let Some(event) = stream.next().await {
+            simulated.on_event(&event);
+        }
+    };
+    let _ = tokio::time::timeout(Duration::from_secs(3), warm_up).await;
+    Ok(simulated)
+}
+
+async fn send_order<T: OrderEntry>(router: OrderRouter<T>, request: &OrderRequest) -> Result<(), Error> {
+    let update = router.place_order(request).await?;
+    println!("{:?}", update);
+    if let OrderRouter::Paper(simulated) = &router {
+        for event in simulated.take_events() {
+            println!("{:?}", event);
+        }
+    }
+    Ok(())
+}
+
+// spoofer order [--paper] <binance|kraken> <ticker> <buy|sell> <quantity> [price]
+async fn place_order(args: &[String]) -> Result<(), Error> {
+    let usage = "usage: spoofer order [--paper] <binance|kraken> <ticker> <buy|sell> <quantity> [price]";
+    let paper = args.first().map(String::as_str) == Some("--paper");
+    let args = if paper { &args[1..] } else { args };
+    let exchange = parse_exchange(args.first(), usage);
+    let ticker = args.get(1).expect(usage);
+    let side = match args.get(2).map(String::as_str) {
+        Some("buy") => Side::Buy,
+        Some("sell") => Side::Sell,
+        _ => panic!("{}", usage),
+    };
+    let (_, price_multiply, quantity_multiply) = TICKERS.iter().find(|(t, _, _)| t == ticker).expect(usage);
+    let quantity: f64 = args.get(3).expect(usage).parse().expect(usage);
+    let quantity = (quantity * *quantity_multiply as f64).round() as u64;
+    let request = match args.get(4) {
+        Some(raw) => {
+            let price: f64 = raw.parse().expect(usage);
+            OrderRequest::limit(ticker, side, (price * *price_multiply as f64).round() as u64, quantity)
+        }
+        None => OrderRequest::market(ticker, side, quantity),
+    };
+
+    let simulated = match paper {
+        true => paper_exchange(&exchange).await?,
+        false => SimulatedExchange::new(exchange.clone(), &TICKERS, SimulationConfig::default()),
+    };
+    let credentials = credentials(&exchange, paper);
+    match exchange {
+        Exchange::Binance => {
+            let live = BinanceOrderEntry::new(credentials, &TICKERS);
+            send_order(OrderRouter::new(paper, live, simulated), &request).await
+        }
+        Exchange::Kraken => {
+            let live = KrakenOrderEntry::new(credentials, &TICKERS);
+            send_order(OrderRouter::new(paper, live, simulated), &request).await
+        }
+        _ => panic!("{}", usage),
     }
 }
 
@@ -56,11 +317,28 @@ async fn saver(mut rx_events: broadcast::Receiver<Event>) {
     let client = get_client().await;
     let trade_saver = BufferService::new(TradeEventRepo::new(&client), 10_000);
     let level2saver = BufferService::new(LevelUpdatedRepo::new(&client), 50_000);
+    let bbo_saver = BufferService::new(BestBidAskRepo::new(&client), 10_000);
+    let candle_saver = BufferService::new(CandleRepo::new(&client), 1_000);
+    let dead_letter_saver = BufferService::new(DeadLetterRepo::new(&client), 100);
+    let mut gaps = TradeGapTracker::new();
     loop {
         let event = rx_events.recv().await.unwrap();
         match event {
-            Event::Trade(v) => trade_saver.push(v).await.unwrap(),
+            Event::Trade(v) => {
+                if let Some(gap) = gaps.observe(&v) {
+                    tokio::spawn(fill_gap(client.clone(), gap));
+                }
+                trade_saver.push(v).await.unwrap()
+            }
             Event::LevelUpdate(v) => level2saver.push(v).await.unwrap(),
+            Event::BestBidAsk(v) => bbo_saver.push(v).await.unwrap(),
+            Event::Candle(v) => candle_saver.push(v).await.unwrap(),
+            Event::DerivativeTicker(_) => {}
+            Event::Order(_) => {}
+            Event::OrderUpdate(_) => {}
+            Event::Fill(_) => {}
+            Event::BalanceUpdate(_) => {}
+            Event::DeadLetter(v) => dead_letter_saver.push(v).await.unwrap(),
         };
     }
 }
@@ -68,23 +346,71 @@ async fn saver(mut rx_events: broadcast::Receiver<Event>) {
 async fn processor(mut rx_events: broadcast::Receiver<Event>) {
     let client= get_client().await;
 
-    let mut books = vec![];
-    for (ticker, _, _) in TICKERS.iter() {
-        let ob1 = OrderBook::new(Exchange::Binance, ticker, 10);
-        let ob2 = OrderBook::new(Exchange::Kraken, ticker, 10);
-        books.push((ob1, ob2));
-    }
+    let mut books = BookRegistry::new(10)
+        .with_buffer(50)
+        .with_metrics(5)
+        .with_stale_after(Duration::from_secs(30));
     let signal_saver = BufferService::new(ArbitrageSignalRepo::new(&client), 10_000);
+    let metrics_saver = BufferService::new(BookMetricsRepo::new(&client), 10_000);
+    let snapshot_saver = BufferService::new(OrderBookSnapshotRepo::new(&client), 100);
+    let mut snapshots = SnapshotWriter::new().every(Duration::from_secs(60)).every_updates(50_000);
+    let mut latency = LatencyTracker::new().report_every(Duration::from_secs(60));
+    let mut stale_checked: TimestampNS = 0;
+    let mut consolidated: Vec<ConsolidatedBook> = TICKERS
+        .iter()
+        .map(|(ticker, price_multiply, quantity_multiply)| {
+            ConsolidatedBook::new(ticker, *price_multiply as f64, *quantity_multiply as f64)
+        })
+        .collect();
+    let mut consolidated_at: TimestampNS = 0;
 
     loop {
         let event = rx_events.recv().await.unwrap();
+        latency.observe(&event);
+        latency.report_if_due();
         match event {
             Event::Trade(_v) => {}
+            Event::BestBidAsk(_v) => {}
+            Event::Candle(_v) => {}
+            Event::DerivativeTicker(_v) => {}
+            Event::Order(_v) => {}
+            Event::OrderUpdate(_v) => {}
+            Event::Fill(_v) => {}
+            Event::BalanceUpdate(_v) => {}
+            Event::DeadLetter(_v) => {}
             Event::LevelUpdate(v) => {
-                for pair in books.iter_mut() {
-                    pair.0.update_or_miss(&v);
-                    pair.1.update_or_miss(&v);
-                    let signal = ArbitrageMonitor::new(&pair.0, &pair.1, 0.0002).execute();
+                let book = books.update(&v);
+                if let Some(metrics) = book.take_metrics() {
+                    metrics_saver.push(metrics).await.unwrap();
+                }
+                if let Some(snapshot) = snapshots.observe(book, &v) {
+                    snapshot_saver.push(snapshot).await.unwrap();
+                }
+                if book.state() == BookState::Gapped {
+                    println!("{} {} missed an update, rebuilding", book.exchange().to_str(), book.ticker());
+                    book.resync();
+                }
+                // Books that stopped updating are marked stale once a second of receive time
+                if v.received.saturating_sub(stale_checked) >= Duration::from_secs(1).as_nanos() as TimestampNS {
+                    books.check_stale(v.received);
+                    stale_checked = v.received;
+                }
+                // One view of each ticker across all venues once a minute of receive time
+                if v.received.saturating_sub(consolidated_at) >= Duration::from_secs(60).as_nanos() as TimestampNS {
+                    for (book, (_, price_multiply, quantity_multiply)) in consolidated.iter_mut().zip(TICKERS.iter()) {
+                        match books.consolidate(book, *price_multiply as f64, *quantity_multiply as f64) {
+                            Ok(_) => print_consolidated(book, price_multiply.ilog10() as usize),
+                            Err(e) => println!("Consolidating {} failed: {}", book.ticker(), e),
+                        }
+                    }
+                    consolidated_at = v.received;
+                }
+                // The updated book against the same ticker on every other venue, e.g. Hyperliquid
+                // perps against Binance and Kraken spot
+                let Some(updated) = books.get(&v.exchange, &v.ticker) else { continue };
+                for other in EXCHANGES.iter().filter(|e| **e != v.exchange) {
+                    let Some(other) = books.get(other, &v.ticker) else { continue };
+                    let signal = ArbitrageMonitor::new(updated, other, 0.0002).execute();
                     if let Some(s) = signal {
                         println!("{:?}", s);
                         signal_saver.push(s).await.unwrap();
@@ -97,6 +423,24 @@ async fn processor(mut rx_events: broadcast::Receiver<Event>) {
 
 #[tokio::main]
 async fn main() {
+    let args: Vec<String> = env::args().collect();
+    if args.get(1).map(String::as_str) == Some("backfill") {
+        println!("Inserted {} trades", backfill_days(&args[2..]).await.unwrap());
+        return;
+    }
+    if args.get(1).map(String::as_str) == Some("book") {
+        book_at(&args[2..]).await.unwrap();
+        return;
+    }
+    if args.get(1).map(String::as_str) == Some("order") {
+        place_order(&args[2..]).await.unwrap();
+        return;
+    }
+    if args.get(1).map(String::as_str) == Some("bench-book") {
+        bench_book(&args[2..]).await.unwrap();
+        return;
+    }
+
     let (tx_events, _) = broadcast::channel::<Event>(50_000);
 
     // Stream tread
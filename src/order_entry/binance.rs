@@ -0,0 +1,295 @@
+use crate::account::{OrderStatus, OrderUpdate};
+use crate::connector::config::ApiCredentials;
+use crate::connector::{convert_ticker_into_binance_symbol, SymbolCase};
+use crate::connector::errors::Error;
+use crate::connector::errors::ExchangeError::{
+    AuthenticationFailed, BinanceError, InsufficientBalance, InvalidOrder, OrderNotFound,
+    OrderRejected, RateLimited,
+};
+use crate::connector::errors::ParsingError::ConvertingError;
+use crate::connector::services::http::HttpClient;
+use crate::connector::services::parser::model_from_string;
+use crate::connector::services::signing::binance_signature;
+use crate::order_entry::instruments::{Instrument, InstrumentMap};
+use crate::order_entry::{OrderEntry, OrderRequest, OrderType};
+use crate::shared::utils::{now_timestamp, now_timestamp_ns};
+use crate::shared::{Exchange, Side};
+use reqwest::Method;
+use serde::Deserialize;
+use std::sync::Arc;
+
+const ORDER_PATH: &str = "/api/v3/order";
+const RECV_WINDOW: u64 = 5000;
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BinanceOrder {
+    order_id: u64,
+    client_order_id: String,
+    price: String,
+    orig_qty: String,
+    executed_qty: String,
+    status: String,
+    side: String,
+    // `transactTime` for new orders, `updateTime` for queries
+    transact_time: Option<u64>,
+    update_time: Option<u64>,
+}
+
+#[derive(Debug, Deserialize)]
+struct BinanceErrorResponse {
+    code: i64,
+    msg: String,
+}
+
+fn side_to_binance(side: Side) -> &'static str {
+    match side {
+        Side::Buy => "BUY",
+        Side::Sell => "SELL",
+    }
+}
+
+fn parse_status(raw: &str) -> Result<OrderStatus, Error> {
+    let status = match raw {
+        // Still resting until the exchange confirms the cancel
+        "NEW" | "PENDING_NEW" | "PENDING_CANCEL" => OrderStatus::New,
+        "PARTIALLY_FILLED" => OrderStatus::PartiallyFilled,
+        "FILLED" => OrderStatus::Filled,
+        "CANCELED" => OrderStatus::Canceled,
+        "REJECTED" => OrderStatus::Rejected,
+        "EXPIRED" | "EXPIRED_IN_MATCH" => OrderStatus::Expired,
+        _ => Err(ConvertingError(format!("Unexpected order status {}", raw)))?,
+    };
+    Ok(status)
+}
+
+fn map_binance_error(http_status: u16, code: i64, msg: String) -> Error {
+    let err = match code {
+        -2010 if msg.to_lowercase().contains("insufficient balance") => InsufficientBalance(msg),
+        -2010 => OrderRejected(msg),
+        -2011 | -2013 => OrderNotFound(msg),
+        -1003 | -1015 => RateLimited(msg),
+        -1022 | -2014 | -2015 => AuthenticationFailed(msg),
+        -1013 | -1111 | -1112 | -1116 | -1117 | -1121 => InvalidOrder(msg),
+        _ if http_status == 429 || http_status == 418 => RateLimited(msg),
+        _ => BinanceError(format!("{}: {}", code, msg)),
+    };
+    Error::ExchangeError(err)
+}
+
+/// Spot order entry over Binance REST. Every request is a SIGNED endpoint
+pub struct BinanceOrderEntry {
+    credentials: ApiCredentials,
+    instruments: InstrumentMap,
+    http: Arc<HttpClient>,
+    rest_url: String,
+}
+
+impl BinanceOrderEntry {
+    pub fn new(credentials: ApiCredentials, tickers: &[(&str, u32, u32)]) -> Self {
+        Self {
+            credentials,
+            instruments: InstrumentMap::new(tickers, |t| convert_ticker_into_binance_symbol(t, SymbolCase::Upper)),
+            http: HttpClient::global(),
+            rest_url: "https://api.binance.com".to_string(),
+        }
+    }
+
+    #[cfg(test)]
+    pub fn with_url(mut self, rest_url: &str) -> Self {
+        self.rest_url = rest_url.to_string();
+        self.http = Arc::new(HttpClient::new());
+        self
+    }
+
+    async fn signed_request(&self, method: Method, params: Vec<(&str, String)>) -> Result<String, Error> {
+        let mut query = params
+            .iter()
+            .map(|(k, v)| format!("{}={}", k, v))
+            .collect::<Vec<_>>();
+        query.push(format!("recvWindow={}", RECV_WINDOW));
+        query.push(format!("timestamp={}", now_timestamp()));
+        let query = query.join("&");
+        let signature = binance_signature(&query, &self.credentials.api_secret);
+
+        let url = format!("{}{}?{}&signature={}", self.rest_url, ORDER_PATH, query, signature);
+        // Request weights: query order 4, new and cancel order 1
+        let weight = if method == Method::GET { 4 } else { 1 };
+        let request = self
+            .http
+            .request(method, &url)
+            .header("X-MBX-APIKEY", &self.credentials.api_key);
+        let resp = self.http.send_once(&Exchange::Binance, weight, request).await?;
+
+        let status = resp.status().as_u16();
+        let body = resp.text().await?;
+        if status >= 400 {
+            let err = model_from_string::<BinanceErrorResponse>(&body)
+                .map_err(|_| BinanceError(format!("HTTP {}: {}", status, body)))?;
+            return Err(map_binance_error(status, err.code, err.msg));
+        }
+        Ok(body)
+    }
+
+    fn order_update(&self, instrument: &Instrument, body: &str) -> Result<OrderUpdate, Error> {
+        let order: BinanceOrder = model_from_string(body)?;
+        let side = match order.side.as_str() {
+            "BUY" => Side::Buy,
+            "SELL" => Side::Sell,
+            other => Err(ConvertingError(format!("Unexpected side {}", other)))?,
+        };
+        Ok(OrderUpdate {
+            exchange: Exchange::Binance,
+            ticker: Arc::clone(&instrument.ticker),
+            order_id: order.order_id.to_string(),
+            client_order_id: Some(order.client_order_id),
+            side,
+            status: parse_status(&order.status)?,
+            price: instrument.parse_price(&order.price)?,
+            quantity: instrument.parse_quantity(&order.orig_qty)?,
+            filled_quantity: instrument.parse_quantity(&order.executed_qty)?,
+            timestamp: order
+                .transact_time
+                .or(order.update_time)
+                .unwrap_or_else(now_timestamp),
+            received: now_timestamp_ns(),
+        })
+    }
+}
+
+impl OrderEntry for BinanceOrderEntry {
+    async fn place_order(&self, request: &OrderRequest) -> Result<OrderUpdate, Error> {
+        let instrument = self.instruments.get(&request.ticker)?;
+
+        let mut params = vec![
+            ("symbol", instrument.symbol.clone()),
+            ("side", side_to_binance(request.side).to_string()),
+        ];
+        match request.order_type {
+            OrderType::Limit => {
+                let price = request
+                    .price
+                    .ok_or_else(|| InvalidOrder("Limit order requires a price".to_string()))?;
+                params.push(("type", "LIMIT".to_string()));
+                params.push(("timeInForce", "GTC".to_string()));
+                params.push(("price", instrument.format_price(price)));
+            }
+            OrderType::Market => params.push(("type", "MARKET".to_string())),
+        }
+        params.push(("quantity", instrument.format_quantity(request.quantity)));
+        if let Some(id) = &request.client_order_id {
+            params.push(("newClientOrderId", id.clone()));
+        }
+        params.push(("newOrderRespType", "RESULT".to_string()));
+
+        let body = self.signed_request(Method::POST, params).await?;
+        self.order_update(instrument, &body)
+    }
+
+    async fn cancel_order(&self, ticker: &str, order_id: &str) -> Result<(), Error> {
+        let instrument = self.instruments.get(ticker)?;
+        let params = vec![
+            ("symbol", instrument.symbol.clone()),
+            ("orderId", order_id.to_string()),
+        ];
+        self.signed_request(Method::DELETE, params).await?;
+        Ok(())
+    }
+
+    async fn query_order(&self, ticker: &str, order_id: &str) -> Result<OrderUpdate, Error> {
+        let instrument = self.instruments.get(ticker)?;
+        let params = vec![
+            ("symbol", instrument.symbol.clone()),
+            ("orderId", order_id.to_string()),
+        ];
+        let body = self.signed_request(Method::GET, params).await?;
+        self.order_update(instrument, &body)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::connector::errors::ExchangeError;
+    use crate::connector::services::mock_server::mock_http_server;
+
+    fn entry(url: &str) -> BinanceOrderEntry {
+        let credentials = ApiCredentials {
+            api_key: "key".to_string(),
+            api_secret: "secret".to_string(),
+        };
+        BinanceOrderEntry::new(credentials, &[("btc/usdt", 100, 1000)]).with_url(url)
+    }
+
+    const ORDER: &str = r#"{"symbol":"BTCUSDT","orderId":42,"clientOrderId":"my-1","transactTime":1700000000000,
+        "price":"100.50000000","origQty":"0.50000000","executedQty":"0.00000000","status":"NEW",
+        "timeInForce":"GTC","type":"LIMIT","side":"BUY"}"#;
+
+    #[tokio::test]
+    async fn test_place_limit_order() {
+        let (url, server) = mock_http_server(vec![(200, ORDER.to_string())]).await;
+
+        let request = OrderRequest::limit("btc/usdt", Side::Buy, 10050, 500).with_client_order_id("my-1");
+        let update = entry(&url).place_order(&request).await.unwrap();
+
+        let requests = server.await.unwrap();
+        let line = requests[0].lines().next().unwrap();
+        assert!(line.starts_with("POST /api/v3/order?symbol=BTCUSDT&side=BUY&type=LIMIT&timeInForce=GTC&price=100.50&quantity=0.500&newClientOrderId=my-1"));
+        assert!(line.contains("&signature="));
+        assert!(requests[0].to_lowercase().contains("x-mbx-apikey: key"));
+
+        assert_eq!(update.order_id, "42");
+        assert_eq!(update.status, OrderStatus::New);
+        assert_eq!((update.side, update.price, update.quantity, update.filled_quantity), (Side::Buy, 10050, 500, 0));
+    }
+
+    #[tokio::test]
+    async fn test_cancel_and_query() {
+        let (url, server) = mock_http_server(vec![
+            (200, ORDER.replace("\"NEW\"", "\"CANCELED\"")),
+            (200, ORDER.replace("transactTime", "updateTime").replace("\"NEW\"", "\"CANCELED\"")),
+        ])
+        .await;
+        let entry = entry(&url);
+
+        entry.cancel_order("btc/usdt", "42").await.unwrap();
+        let update = entry.query_order("btc/usdt", "42").await.unwrap();
+
+        let requests = server.await.unwrap();
+        assert!(requests[0].starts_with("DELETE /api/v3/order?symbol=BTCUSDT&orderId=42"));
+        assert!(requests[1].starts_with("GET /api/v3/order?symbol=BTCUSDT&orderId=42"));
+        assert_eq!(update.status, OrderStatus::Canceled);
+    }
+
+    #[tokio::test]
+    async fn test_errors() {
+        let (url, _server) = mock_http_server(vec![
+            (400, r#"{"code":-2010,"msg":"Account has insufficient balance for requested action."}"#.to_string()),
+            (400, r#"{"code":-2011,"msg":"Unknown order sent."}"#.to_string()),
+            (401, r#"{"code":-2015,"msg":"Invalid API-key, IP, or permissions for action."}"#.to_string()),
+            (429, r#"{"code":-1003,"msg":"Too many requests."}"#.to_string()),
+        ])
+        .await;
+        let entry = entry(&url);
+
+        let res = entry.place_order(&OrderRequest::market("btc/usdt", Side::Sell, 1)).await;
+        assert!(matches!(res, Err(Error::ExchangeError(ExchangeError::InsufficientBalance(_)))));
+
+        let res = entry.cancel_order("btc/usdt", "1").await;
+        assert!(matches!(res, Err(Error::ExchangeError(ExchangeError::OrderNotFound(_)))));
+
+        let res = entry.query_order("btc/usdt", "1").await;
+        assert!(matches!(res, Err(Error::ExchangeError(ExchangeError::AuthenticationFailed(_)))));
+
+        let res = entry.query_order("btc/usdt", "1").await;
+        assert!(matches!(res, Err(Error::ExchangeError(ExchangeError::RateLimited(_)))));
+    }
+
+    #[tokio::test]
+    async fn test_limit_requires_price() {
+        let mut request = OrderRequest::limit("btc/usdt", Side::Buy, 1, 1);
+        request.price = None;
+        let res = entry("http://127.0.0.1:1").place_order(&request).await;
+        assert!(matches!(res, Err(Error::ExchangeError(ExchangeError::InvalidOrder(_)))));
+    }
+}
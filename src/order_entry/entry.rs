@@ -0,0 +1,12 @@
+use crate::account::OrderUpdate;
+use crate::connector::errors::Error;
+use crate::order_entry::OrderRequest;
+
+/// Places and manages orders on one exchange. Rejections come back as typed `ExchangeError` variants
+pub trait OrderEntry: Send + Sync {
+    async fn place_order(&self, request: &OrderRequest) -> Result<OrderUpdate, Error>;
+
+    async fn cancel_order(&self, ticker: &str, order_id: &str) -> Result<(), Error>;
+
+    async fn query_order(&self, ticker: &str, order_id: &str) -> Result<OrderUpdate, Error>;
+}
@@ -0,0 +1,133 @@
+use crate::connector::errors::Error;
+use crate::connector::errors::Error::InternalError;
+use crate::connector::services::parser::parse_number;
+use crate::shared::{Price, Quantity};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+type Converter = fn(&str) -> String;
+
+pub struct Instrument {
+    pub ticker: Arc<String>,
+    pub symbol: String,
+    price_multiply: f64,
+    quantity_multiply: f64,
+    price_decimals: usize,
+    quantity_decimals: usize,
+}
+
+/// Decimal places that show every multiple of the tick `1 / multiply` exactly, e.g. 2 for 4
+/// (tick 0.25). `None` when the tick has no finite decimal form, e.g. for 3
+fn decimals(multiply: u32) -> Option<usize> {
+    let (mut rest, mut twos, mut fives) = (multiply, 0, 0);
+    while rest > 0 && rest % 2 == 0 {
+        rest /= 2;
+        twos += 1;
+    }
+    while rest > 0 && rest % 5 == 0 {
+        rest /= 5;
+        fives += 1;
+    }
+    (rest == 1).then_some(twos.max(fives))
+}
+
+impl Instrument {
+    pub fn format_price(&self, price: Price) -> String {
+        let value = price as f64 / self.price_multiply;
+        format!("{:.*}", self.price_decimals, value)
+    }
+
+    pub fn format_quantity(&self, quantity: Quantity) -> String {
+        let value = quantity as f64 / self.quantity_multiply;
+        format!("{:.*}", self.quantity_decimals, value)
+    }
+
+    /// Value in quote asset units
+    pub fn notional(&self, price: Price, quantity: Quantity) -> f64 {
+        (price as f64 / self.price_multiply) * (quantity as f64 / self.quantity_multiply)
+    }
+
+    pub fn quote_asset(&self) -> String {
+        self.ticker
+            .split_once('/')
+            .map(|(_, quote)| quote.to_uppercase())
+            .unwrap_or_default()
+    }
+
+    pub fn parse_price(&self, raw: &str) -> Result<Price, Error> {
+        Ok((parse_number(raw)? * self.price_multiply).round() as Price)
+    }
+
+    pub fn parse_quantity(&self, raw: &str) -> Result<Quantity, Error> {
+        Ok((parse_number(raw)? * self.quantity_multiply).round() as Quantity)
+    }
+}
+
+/// Tickers with multipliers, in the same `(ticker, price_multiply, quantity_multiply)` form as `StreamConnector`.
+/// Multipliers must be the inverse of a decimal tick (10, 100, 4 for 0.25 ...) so amounts format exactly
+pub struct InstrumentMap {
+    data: HashMap<String, Instrument>,
+}
+
+impl InstrumentMap {
+    pub fn new(tickers: &[(&str, u32, u32)], converter: Converter) -> Self {
+        let data = tickers
+            .iter()
+            .map(|&(ticker, price_multiply, quantity_multiply)| {
+                let decimals = |multiply| {
+                    decimals(multiply)
+                        .unwrap_or_else(|| panic!("{}: multiplier {} is not the inverse of a decimal tick", ticker, multiply))
+                };
+                let instrument = Instrument {
+                    ticker: Arc::new(ticker.to_string()),
+                    symbol: converter(ticker),
+                    price_multiply: price_multiply as f64,
+                    quantity_multiply: quantity_multiply as f64,
+                    price_decimals: decimals(price_multiply),
+                    quantity_decimals: decimals(quantity_multiply),
+                };
+                (ticker.to_lowercase(), instrument)
+            })
+            .collect();
+        Self { data }
+    }
+
+    pub fn get(&self, ticker: &str) -> Result<&Instrument, Error> {
+        self.data
+            .get(&ticker.to_lowercase())
+            .ok_or_else(|| InternalError(format!("Unknown ticker {}", ticker)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_and_parse() {
+        let map = InstrumentMap::new(&[("btc/usdt", 100, 1000)], |t| t.replace('/', ""));
+        let btc = map.get("BTC/USDT").unwrap();
+
+        assert_eq!(btc.symbol, "btcusdt");
+        assert_eq!(btc.format_price(10050), "100.50");
+        assert_eq!(btc.format_quantity(1), "0.001");
+        assert_eq!(btc.parse_price("100.50000000").unwrap(), 10050);
+        assert_eq!(btc.parse_quantity("0.3").unwrap(), 300);
+        assert!(map.get("eth/usdt").is_err());
+        assert_eq!(btc.notional(10050, 2000), 201.0);
+        assert_eq!(btc.quote_asset(), "USDT");
+    }
+
+    #[test]
+    fn test_decimals_from_tick() {
+        assert_eq!(decimals(1), Some(0));
+        assert_eq!(decimals(100), Some(2));
+        assert_eq!(decimals(4), Some(2));
+        assert_eq!(decimals(400), Some(4));
+        assert_eq!(decimals(3), None);
+        assert_eq!(decimals(0), None);
+
+        let map = InstrumentMap::new(&[("eth/usdt", 4, 1)], |t| t.to_string());
+        assert_eq!(map.get("eth/usdt").unwrap().format_price(5), "1.25");
+    }
+}
@@ -0,0 +1,350 @@
+use crate::account::{OrderStatus, OrderUpdate};
+use crate::connector::config::ApiCredentials;
+use crate::connector::errors::Error;
+use crate::connector::errors::ExchangeError::{
+    AuthenticationFailed, InsufficientBalance, InvalidOrder, KrakenError, OrderNotFound,
+    OrderRejected, RateLimited,
+};
+use crate::connector::errors::ParsingError::ConvertingError;
+use crate::connector::services::http::HttpClient;
+use crate::connector::services::parser::model_from_serde_value;
+use crate::connector::services::signing::kraken_signature;
+use crate::order_entry::instruments::{Instrument, InstrumentMap};
+use crate::order_entry::{OrderEntry, OrderRequest, OrderType};
+use crate::shared::utils::{now_timestamp, now_timestamp_ns};
+use crate::shared::{Exchange, Side};
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+#[derive(Debug, Deserialize)]
+struct KrakenResponse {
+    #[serde(default)]
+    error: Vec<String>,
+    result: Option<Value>,
+}
+
+#[derive(Debug, Deserialize)]
+struct AddOrderResult {
+    txid: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct OrderDescription {
+    #[serde(rename = "type")]
+    side: String,
+    price: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct KrakenOrder {
+    status: String,
+    vol: String,
+    vol_exec: String,
+    descr: OrderDescription,
+    cl_ord_id: Option<String>,
+    // Seconds with fractional part
+    opentm: f64,
+    closetm: Option<f64>,
+}
+
+/// REST pairs use the legacy asset codes: btc/usd -> XBTUSD
+pub(crate) fn convert_ticker_into_kraken_pair(raw: &str) -> String {
+    raw.to_uppercase()
+        .split('/')
+        .map(|asset| match asset {
+            "BTC" => "XBT",
+            "DOGE" => "XDG",
+            other => other,
+        })
+        .collect()
+}
+
+fn side_to_kraken(side: Side) -> &'static str {
+    match side {
+        Side::Buy => "buy",
+        Side::Sell => "sell",
+    }
+}
+
+fn parse_status(order: &KrakenOrder) -> Result<OrderStatus, Error> {
+    let status = match order.status.as_str() {
+        "pending" => OrderStatus::New,
+        "open" if order.vol_exec.parse::<f64>().unwrap_or(0.0) > 0.0 => OrderStatus::PartiallyFilled,
+        "open" => OrderStatus::New,
+        "closed" => OrderStatus::Filled,
+        "canceled" => OrderStatus::Canceled,
+        "expired" => OrderStatus::Expired,
+        other => Err(ConvertingError(format!("Unexpected order status {}", other)))?,
+    };
+    Ok(status)
+}
+
+fn map_kraken_error(errors: &[String]) -> Error {
+    let msg = errors.join(", ");
+    let err = match errors.first().map(|e| e.as_str()).unwrap_or_default() {
+        "EOrder:Insufficient funds" | "EOrder:Insufficient margin" => InsufficientBalance(msg),
+        "EOrder:Unknown order" => OrderNotFound(msg),
+        "EAPI:Rate limit exceeded" | "EOrder:Rate limit exceeded" | "EGeneral:Too many requests" => {
+            RateLimited(msg)
+        }
+        "EAPI:Invalid key" | "EAPI:Invalid signature" | "EAPI:Invalid nonce" | "EGeneral:Permission denied" => {
+            AuthenticationFailed(msg)
+        }
+        e if e.starts_with("EGeneral:Invalid arguments") || e == "EOrder:Order minimum not met" => {
+            InvalidOrder(msg)
+        }
+        e if e.starts_with("EOrder:") => OrderRejected(msg),
+        _ => KrakenError(msg),
+    };
+    Error::ExchangeError(err)
+}
+
+/// Spot order entry over Kraken REST private endpoints
+pub struct KrakenOrderEntry {
+    credentials: ApiCredentials,
+    instruments: InstrumentMap,
+    http: Arc<HttpClient>,
+    rest_url: String,
+    last_nonce: AtomicU64,
+}
+
+impl KrakenOrderEntry {
+    pub fn new(credentials: ApiCredentials, tickers: &[(&str, u32, u32)]) -> Self {
+        Self {
+            credentials,
+            instruments: InstrumentMap::new(tickers, convert_ticker_into_kraken_pair),
+            http: HttpClient::global(),
+            rest_url: "https://api.kraken.com".to_string(),
+            last_nonce: AtomicU64::new(0),
+        }
+    }
+
+    #[cfg(test)]
+    pub fn with_url(mut self, rest_url: &str) -> Self {
+        self.rest_url = rest_url.to_string();
+        self.http = Arc::new(HttpClient::new());
+        self
+    }
+
+    // Kraken rejects a nonce that is not greater than the previous one
+    fn next_nonce(&self) -> u64 {
+        let now = now_timestamp();
+        let prev = self
+            .last_nonce
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |last| Some(now.max(last + 1)))
+            .unwrap();
+        now.max(prev + 1)
+    }
+
+    async fn private_request(&self, method: &str, params: &[(&str, String)]) -> Result<Value, Error> {
+        let path = format!("/0/private/{}", method);
+        let nonce = self.next_nonce();
+
+        let mut form = url::form_urlencoded::Serializer::new(String::new());
+        form.append_pair("nonce", &nonce.to_string());
+        for (k, v) in params {
+            form.append_pair(k, v);
+        }
+        let body = form.finish();
+        let sign = kraken_signature(&path, nonce, &body, &self.credentials.api_secret)?;
+
+        let request = self
+            .http
+            .post(&format!("{}{}", self.rest_url, path))
+            .header("API-Key", &self.credentials.api_key)
+            .header("API-Sign", sign)
+            .header("Content-Type", "application/x-www-form-urlencoded")
+            .body(body);
+        let resp: KrakenResponse = self
+            .http
+            .send_once(&Exchange::Kraken, 1, request)
+            .await?
+            .json()
+            .await?;
+
+        if !resp.error.is_empty() {
+            return Err(map_kraken_error(&resp.error));
+        }
+        resp.result
+            .ok_or_else(|| KrakenError("Response has no result".to_string()).into())
+    }
+
+    fn order_update(&self, instrument: &Instrument, order_id: &str, order: KrakenOrder) -> Result<OrderUpdate, Error> {
+        let side = match order.descr.side.as_str() {
+            "buy" => Side::Buy,
+            "sell" => Side::Sell,
+            other => Err(ConvertingError(format!("Unexpected side {}", other)))?,
+        };
+        let timestamp = (order.closetm.unwrap_or(order.opentm) * 1000.0) as u64;
+        Ok(OrderUpdate {
+            exchange: Exchange::Kraken,
+            ticker: Arc::clone(&instrument.ticker),
+            order_id: order_id.to_string(),
+            status: parse_status(&order)?,
+            client_order_id: order.cl_ord_id,
+            side,
+            price: instrument.parse_price(&order.descr.price)?,
+            quantity: instrument.parse_quantity(&order.vol)?,
+            filled_quantity: instrument.parse_quantity(&order.vol_exec)?,
+            timestamp,
+            received: now_timestamp_ns(),
+        })
+    }
+}
+
+impl OrderEntry for KrakenOrderEntry {
+    async fn place_order(&self, request: &OrderRequest) -> Result<OrderUpdate, Error> {
+        let instrument = self.instruments.get(&request.ticker)?;
+
+        let mut params = vec![
+            ("pair", instrument.symbol.clone()),
+            ("type", side_to_kraken(request.side).to_string()),
+            ("volume", instrument.format_quantity(request.quantity)),
+        ];
+        match request.order_type {
+            OrderType::Limit => {
+                let price = request
+                    .price
+                    .ok_or_else(|| InvalidOrder("Limit order requires a price".to_string()))?;
+                params.push(("ordertype", "limit".to_string()));
+                params.push(("price", instrument.format_price(price)));
+            }
+            OrderType::Market => params.push(("ordertype", "market".to_string())),
+        }
+        if let Some(id) = &request.client_order_id {
+            params.push(("cl_ord_id", id.clone()));
+        }
+
+        let result: AddOrderResult = model_from_serde_value(self.private_request("AddOrder", &params).await?)?;
+        let order_id = result
+            .txid
+            .into_iter()
+            .next()
+            .ok_or_else(|| KrakenError("AddOrder returned no txid".to_string()))?;
+
+        // AddOrder only acknowledges, fills arrive later via the user data stream
+        Ok(OrderUpdate {
+            exchange: Exchange::Kraken,
+            ticker: Arc::clone(&instrument.ticker),
+            order_id,
+            client_order_id: request.client_order_id.clone(),
+            side: request.side,
+            status: OrderStatus::New,
+            price: request.price.unwrap_or(0),
+            quantity: request.quantity,
+            filled_quantity: 0,
+            timestamp: now_timestamp(),
+            received: now_timestamp_ns(),
+        })
+    }
+
+    async fn cancel_order(&self, ticker: &str, order_id: &str) -> Result<(), Error> {
+        self.instruments.get(ticker)?;
+        self.private_request("CancelOrder", &[("txid", order_id.to_string())])
+            .await?;
+        Ok(())
+    }
+
+    async fn query_order(&self, ticker: &str, order_id: &str) -> Result<OrderUpdate, Error> {
+        let instrument = self.instruments.get(ticker)?;
+        let result = self
+            .private_request("QueryOrders", &[("txid", order_id.to_string())])
+            .await?;
+
+        let mut orders: HashMap<String, KrakenOrder> = model_from_serde_value(result)?;
+        let order = orders
+            .remove(order_id)
+            .ok_or_else(|| OrderNotFound(order_id.to_string()))?;
+        self.order_update(instrument, order_id, order)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::connector::errors::ExchangeError;
+    use crate::connector::services::mock_server::mock_http_server;
+
+    fn entry(url: &str) -> KrakenOrderEntry {
+        let credentials = ApiCredentials {
+            api_key: "key".to_string(),
+            api_secret: "c2VjcmV0".to_string(),
+        };
+        KrakenOrderEntry::new(credentials, &[("btc/usd", 10, 100)]).with_url(url)
+    }
+
+    #[test]
+    fn test_pair() {
+        assert_eq!(convert_ticker_into_kraken_pair("btc/usd"), "XBTUSD");
+        assert_eq!(convert_ticker_into_kraken_pair("eth/usdt"), "ETHUSDT");
+    }
+
+    #[tokio::test]
+    async fn test_place_limit_order() {
+        let (url, server) = mock_http_server(vec![(
+            200,
+            r#"{"error":[],"result":{"descr":{"order":"buy 1.50 XBTUSD @ limit 100.5"},"txid":["OABC-1"]}}"#.to_string(),
+        )])
+        .await;
+
+        let request = OrderRequest::limit("btc/usd", Side::Buy, 1005, 150);
+        let update = entry(&url).place_order(&request).await.unwrap();
+
+        let requests = server.await.unwrap();
+        assert!(requests[0].starts_with("POST /0/private/AddOrder"));
+        assert!(requests[0].to_lowercase().contains("api-sign: "));
+        assert!(requests[0].ends_with("&pair=XBTUSD&type=buy&volume=1.50&ordertype=limit&price=100.5"));
+        assert_eq!(update.order_id, "OABC-1");
+        assert_eq!((update.price, update.quantity, update.status), (1005, 150, OrderStatus::New));
+    }
+
+    #[tokio::test]
+    async fn test_query_order() {
+        let (url, server) = mock_http_server(vec![(
+            200,
+            r#"{"error":[],"result":{"OABC-1":{"status":"open","vol":"1.50000000","vol_exec":"0.50000000",
+                "descr":{"pair":"XBTUSD","type":"sell","ordertype":"limit","price":"100.5"},"opentm":1700000000.5}}}"#
+                .to_string(),
+        )])
+        .await;
+
+        let update = entry(&url).query_order("btc/usd", "OABC-1").await.unwrap();
+
+        let requests = server.await.unwrap();
+        assert!(requests[0].ends_with("&txid=OABC-1"));
+        assert_eq!(update.status, OrderStatus::PartiallyFilled);
+        assert_eq!((update.side, update.price, update.filled_quantity), (Side::Sell, 1005, 50));
+        assert_eq!(update.timestamp, 1700000000500);
+    }
+
+    #[tokio::test]
+    async fn test_errors() {
+        let (url, _server) = mock_http_server(vec![
+            (200, r#"{"error":["EOrder:Insufficient funds"]}"#.to_string()),
+            (200, r#"{"error":["EOrder:Unknown order"]}"#.to_string()),
+            (200, r#"{"error":["EAPI:Invalid key"]}"#.to_string()),
+        ])
+        .await;
+        let entry = entry(&url);
+
+        let res = entry.place_order(&OrderRequest::market("btc/usd", Side::Buy, 1)).await;
+        assert!(matches!(res, Err(Error::ExchangeError(ExchangeError::InsufficientBalance(_)))));
+
+        let res = entry.cancel_order("btc/usd", "X").await;
+        assert!(matches!(res, Err(Error::ExchangeError(ExchangeError::OrderNotFound(_)))));
+
+        let res = entry.query_order("btc/usd", "X").await;
+        assert!(matches!(res, Err(Error::ExchangeError(ExchangeError::AuthenticationFailed(_)))));
+    }
+
+    #[test]
+    fn test_nonce_increases() {
+        let entry = entry("http://127.0.0.1:1");
+        let a = entry.next_nonce();
+        let b = entry.next_nonce();
+        assert!(b > a);
+    }
+}
@@ -0,0 +1,16 @@
+mod binance;
+mod instruments;
+mod kraken;
+mod models;
+mod entry;
+mod router;
+mod simulated;
+
+pub use binance::BinanceOrderEntry;
+pub(crate) use instruments::{Instrument, InstrumentMap};
+pub(crate) use kraken::convert_ticker_into_kraken_pair;
+pub use kraken::KrakenOrderEntry;
+pub use models::{OrderRequest, OrderType};
+pub use entry::OrderEntry;
+pub use router::OrderRouter;
+pub use simulated::{SimulatedExchange, SimulationConfig};
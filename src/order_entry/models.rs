@@ -0,0 +1,48 @@
+use crate::shared::{Price, Quantity, Side};
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum OrderType {
+    Limit,
+    Market,
+}
+
+/// Price and quantity use the same multipliers as the market data of the ticker
+#[derive(Debug, Clone)]
+pub struct OrderRequest {
+    pub ticker: String,
+    pub side: Side,
+    pub order_type: OrderType,
+    // Required for limit orders
+    pub price: Option<Price>,
+    pub quantity: Quantity,
+    pub client_order_id: Option<String>,
+}
+
+impl OrderRequest {
+    pub fn limit(ticker: &str, side: Side, price: Price, quantity: Quantity) -> Self {
+        Self {
+            ticker: ticker.to_string(),
+            side,
+            order_type: OrderType::Limit,
+            price: Some(price),
+            quantity,
+            client_order_id: None,
+        }
+    }
+
+    pub fn market(ticker: &str, side: Side, quantity: Quantity) -> Self {
+        Self {
+            ticker: ticker.to_string(),
+            side,
+            order_type: OrderType::Market,
+            price: None,
+            quantity,
+            client_order_id: None,
+        }
+    }
+
+    pub fn with_client_order_id(mut self, id: &str) -> Self {
+        self.client_order_id = Some(id.to_string());
+        self
+    }
+}
@@ -0,0 +1,81 @@
+use crate::account::OrderUpdate;
+use crate::connector::errors::Error;
+use crate::order_entry::{OrderEntry, OrderRequest, SimulatedExchange};
+
+/// Routes orders either to the real venue or to the paper-trading simulator.
+/// Strategies hold an `OrderRouter` and don't care which one is behind it.
+pub enum OrderRouter<T: OrderEntry> {
+    Live(T),
+    Paper(Box<SimulatedExchange>),
+}
+
+impl<T: OrderEntry> OrderRouter<T> {
+    pub fn new(paper_trading: bool, live: T, paper: SimulatedExchange) -> Self {
+        if paper_trading {
+            OrderRouter::Paper(Box::new(paper))
+        } else {
+            OrderRouter::Live(live)
+        }
+    }
+
+    pub fn is_paper(&self) -> bool {
+        matches!(self, OrderRouter::Paper(_))
+    }
+}
+
+impl<T: OrderEntry> OrderEntry for OrderRouter<T> {
+    async fn place_order(&self, request: &OrderRequest) -> Result<OrderUpdate, Error> {
+        match self {
+            OrderRouter::Live(v) => v.place_order(request).await,
+            OrderRouter::Paper(v) => v.place_order(request).await,
+        }
+    }
+
+    async fn cancel_order(&self, ticker: &str, order_id: &str) -> Result<(), Error> {
+        match self {
+            OrderRouter::Live(v) => v.cancel_order(ticker, order_id).await,
+            OrderRouter::Paper(v) => v.cancel_order(ticker, order_id).await,
+        }
+    }
+
+    async fn query_order(&self, ticker: &str, order_id: &str) -> Result<OrderUpdate, Error> {
+        match self {
+            OrderRouter::Live(v) => v.query_order(ticker, order_id).await,
+            OrderRouter::Paper(v) => v.query_order(ticker, order_id).await,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::connector::config::ApiCredentials;
+    use crate::order_entry::{BinanceOrderEntry, SimulationConfig};
+    use crate::shared::{Exchange, Side};
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_paper_flag_routes_to_simulator() {
+        let tickers = [("btc/usdt", 1, 1)];
+        let credentials = ApiCredentials {
+            api_key: "key".to_string(),
+            api_secret: "secret".to_string(),
+        };
+        let config = SimulationConfig {
+            latency: Duration::from_millis(0),
+            ..SimulationConfig::default()
+        };
+        let router = OrderRouter::new(
+            true,
+            BinanceOrderEntry::new(credentials, &tickers),
+            SimulatedExchange::new(Exchange::Binance, &tickers, config),
+        );
+
+        assert!(router.is_paper());
+        let update = router
+            .place_order(&OrderRequest::limit("btc/usdt", Side::Buy, 100, 1))
+            .await
+            .unwrap();
+        assert!(update.order_id.starts_with("SIM-"));
+    }
+}
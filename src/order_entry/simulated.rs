@@ -0,0 +1,523 @@
+use crate::account::{Fill, OrderStatus, OrderUpdate};
+use crate::connector::errors::Error;
+use crate::connector::errors::ExchangeError::{InvalidOrder, OrderNotFound, OrderRejected};
+use crate::connector::Event;
+use crate::level2::OrderBook;
+use crate::order_entry::instruments::{Instrument, InstrumentMap};
+use crate::order_entry::{OrderEntry, OrderRequest, OrderType};
+use crate::shared::utils::{now_timestamp, now_timestamp_ns};
+use crate::shared::{Exchange, Price, Quantity, Side};
+use crate::trade::TradeEvent;
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Closed orders kept for `query_order`
+const CLOSED_ORDERS: usize = 1_000;
+
+#[derive(Debug, Clone)]
+pub struct SimulationConfig {
+    /// Delay between `place_order` and the order reaching the simulated book
+    pub latency: Duration,
+    /// Fee rates, 0.001 is 0.1% of the notional
+    pub maker_fee: f64,
+    pub taker_fee: f64,
+    pub book_depth: usize,
+}
+
+impl Default for SimulationConfig {
+    fn default() -> Self {
+        Self {
+            latency: Duration::from_millis(50),
+            maker_fee: 0.001,
+            taker_fee: 0.001,
+            book_depth: 100,
+        }
+    }
+}
+
+/// Open limit orders of one ticker in price-time priority, keyed by price and placement sequence
+#[derive(Default)]
+struct RestingOrders {
+    bids: BTreeMap<(Reverse<Price>, u64), String>,
+    asks: BTreeMap<(Price, u64), String>,
+}
+
+struct SimOrder {
+    sequence: u64,
+    update: OrderUpdate,
+}
+
+struct SimState {
+    books: HashMap<String, OrderBook>,
+    /// Open orders only, closed ones move to `closed`
+    orders: HashMap<String, SimOrder>,
+    resting: HashMap<String, RestingOrders>,
+    closed: VecDeque<OrderUpdate>,
+    events: Vec<Event>,
+    next_id: u64,
+}
+
+impl SimState {
+    fn rest(&mut self, sequence: u64, update: OrderUpdate) {
+        let resting = self.resting.entry(update.ticker.to_lowercase()).or_default();
+        let order_id = update.order_id.clone();
+        match update.side {
+            Side::Buy => resting.bids.insert((Reverse(update.price), sequence), order_id.clone()),
+            Side::Sell => resting.asks.insert((update.price, sequence), order_id.clone()),
+        };
+        self.orders.insert(order_id, SimOrder { sequence, update });
+    }
+
+    /// Takes a filled or canceled order off the book
+    fn close(&mut self, order_id: &str) {
+        let Some(order) = self.orders.remove(order_id) else {
+            return;
+        };
+        if let Some(resting) = self.resting.get_mut(&order.update.ticker.to_lowercase()) {
+            match order.update.side {
+                Side::Buy => resting.bids.remove(&(Reverse(order.update.price), order.sequence)),
+                Side::Sell => resting.asks.remove(&(order.update.price, order.sequence)),
+            };
+        }
+        self.remember(order.update);
+    }
+
+    fn remember(&mut self, update: OrderUpdate) {
+        if self.closed.len() == CLOSED_ORDERS {
+            self.closed.pop_front();
+        }
+        self.closed.push_back(update);
+    }
+}
+
+/// Paper-trading venue. Consumes the live `LevelUpdate`/`Trade` events of one exchange and fills orders
+/// against them: incoming orders take book liquidity, resting limit orders are filled by trades printing
+/// through their price, best price first and then in placement order. The live book is not depleted by
+/// simulated fills.
+pub struct SimulatedExchange {
+    exchange: Exchange,
+    instruments: InstrumentMap,
+    config: SimulationConfig,
+    state: Mutex<SimState>,
+}
+
+fn is_open(status: OrderStatus) -> bool {
+    matches!(status, OrderStatus::New | OrderStatus::PartiallyFilled)
+}
+
+impl SimulatedExchange {
+    pub fn new(exchange: Exchange, tickers: &[(&str, u32, u32)], config: SimulationConfig) -> Self {
+        let books = tickers
+            .iter()
+            .map(|(ticker, _, _)| {
+                let book = OrderBook::new(exchange.clone(), ticker, config.book_depth);
+                (ticker.to_lowercase(), book)
+            })
+            .collect();
+        Self {
+            instruments: InstrumentMap::new(tickers, |t| t.to_lowercase()),
+            exchange,
+            config,
+            state: Mutex::new(SimState {
+                books,
+                orders: HashMap::new(),
+                resting: HashMap::new(),
+                closed: VecDeque::new(),
+                events: Vec::new(),
+                next_id: 1,
+            }),
+        }
+    }
+
+    /// Feeds market data. Events of other exchanges or tickers are ignored
+    pub fn on_event(&self, event: &Event) {
+        match event {
+            Event::LevelUpdate(v) if v.exchange == self.exchange => {
+                let mut state = self.state.lock().unwrap();
+                if let Some(book) = state.books.get_mut(&v.ticker.to_lowercase()) {
+                    book.update_or_miss(v);
+                }
+            }
+            Event::Trade(v) if v.exchange == self.exchange => self.match_trade(v),
+            _ => {}
+        }
+    }
+
+    /// Order updates and fills produced since the last call, in the same form as a user data stream
+    pub fn take_events(&self) -> Vec<Event> {
+        std::mem::take(&mut self.state.lock().unwrap().events)
+    }
+
+    fn fill(&self, instrument: &Instrument, order: &OrderUpdate, price: Price, quantity: Quantity, is_maker: bool, trade_id: u64) -> Fill {
+        let rate = if is_maker { self.config.maker_fee } else { self.config.taker_fee };
+        Fill {
+            exchange: self.exchange.clone(),
+            ticker: order.ticker.clone(),
+            order_id: order.order_id.clone(),
+            trade_id: format!("SIM-T{}", trade_id),
+            side: order.side,
+            price,
+            quantity,
+            fee: instrument.notional(price, quantity) * rate,
+            fee_asset: instrument.quote_asset(),
+            is_maker,
+            timestamp: now_timestamp(),
+            received: now_timestamp_ns(),
+        }
+    }
+
+    fn match_trade(&self, trade: &TradeEvent) {
+        let Ok(instrument) = self.instruments.get(&trade.ticker) else {
+            return;
+        };
+        let mut state = self.state.lock().unwrap();
+        let Some(resting) = state.resting.get(&instrument.ticker.to_lowercase()) else {
+            return;
+        };
+        let mut available = trade.quantity;
+
+        // A print exactly at our price is assumed to hit the queue ahead of us
+        let bids = resting.bids.iter().take_while(|((Reverse(price), _), _)| trade.price < *price).map(|(_, id)| id);
+        let asks = resting.asks.iter().take_while(|((price, _), _)| trade.price > *price).map(|(_, id)| id);
+        let mut matched: Vec<(String, Quantity)> = Vec::new();
+        for order_id in bids.chain(asks) {
+            if available == 0 {
+                break;
+            }
+            let order = &state.orders[order_id].update;
+            let qty = available.min(order.quantity - order.filled_quantity);
+            available -= qty;
+            matched.push((order_id.clone(), qty));
+        }
+
+        for (order_id, qty) in matched {
+            let trade_id = state.next_id;
+            state.next_id += 1;
+
+            let order = &mut state.orders.get_mut(&order_id).unwrap().update;
+            order.filled_quantity += qty;
+            order.status = if order.filled_quantity == order.quantity {
+                OrderStatus::Filled
+            } else {
+                OrderStatus::PartiallyFilled
+            };
+            order.timestamp = now_timestamp();
+            order.received = now_timestamp_ns();
+            let update = order.clone();
+
+            // Resting orders are filled at their own limit price
+            let fill = self.fill(instrument, &update, update.price, qty, true, trade_id);
+            state.events.push(Event::Fill(fill));
+            if !is_open(update.status) {
+                state.close(&order_id);
+            }
+            state.events.push(Event::OrderUpdate(update));
+        }
+    }
+
+    fn execute(&self, instrument: &Instrument, request: &OrderRequest) -> Result<OrderUpdate, Error> {
+        let mut state = self.state.lock().unwrap();
+        let book = &state.books[&instrument.ticker.to_lowercase()];
+        let opposite = match request.side {
+            Side::Buy => book.asks(),
+            Side::Sell => book.bids(),
+        };
+
+        if request.order_type == OrderType::Market && opposite.is_empty() {
+            Err(OrderRejected("No liquidity in the simulated book".to_string()))?;
+        }
+
+        let mut taken: Vec<(Price, Quantity)> = Vec::new();
+        let mut remaining = request.quantity;
+        for price in opposite.best_prices(usize::MAX) {
+            if remaining == 0 {
+                break;
+            }
+            let within_limit = match (request.price, request.side) {
+                (None, _) => true,
+                (Some(limit), Side::Buy) => price <= limit,
+                (Some(limit), Side::Sell) => price >= limit,
+            };
+            if !within_limit {
+                break;
+            }
+            let qty = remaining.min(opposite.quantity_at(price).unwrap_or(0));
+            remaining -= qty;
+            taken.push((price, qty));
+        }
+
+        let sequence = state.next_id;
+        let order_id = format!("SIM-{}", sequence);
+        state.next_id += 1;
+
+        let filled = request.quantity - remaining;
+        let status = match (remaining, request.order_type) {
+            (0, _) => OrderStatus::Filled,
+            // Market leftovers are not kept, like an IOC order
+            (_, OrderType::Market) => OrderStatus::Expired,
+            _ if filled > 0 => OrderStatus::PartiallyFilled,
+            _ => OrderStatus::New,
+        };
+        let update = OrderUpdate {
+            exchange: self.exchange.clone(),
+            ticker: instrument.ticker.clone(),
+            order_id: order_id.clone(),
+            client_order_id: request.client_order_id.clone(),
+            side: request.side,
+            status,
+            price: request.price.unwrap_or(0),
+            quantity: request.quantity,
+            filled_quantity: filled,
+            timestamp: now_timestamp(),
+            received: now_timestamp_ns(),
+        };
+
+        for (price, qty) in taken {
+            let trade_id = state.next_id;
+            state.next_id += 1;
+            let fill = self.fill(instrument, &update, price, qty, false, trade_id);
+            state.events.push(Event::Fill(fill));
+        }
+        state.events.push(Event::OrderUpdate(update.clone()));
+        if is_open(status) {
+            state.rest(sequence, update.clone());
+        } else {
+            state.remember(update.clone());
+        }
+
+        Ok(update)
+    }
+}
+
+impl OrderEntry for SimulatedExchange {
+    async fn place_order(&self, request: &OrderRequest) -> Result<OrderUpdate, Error> {
+        let instrument = self.instruments.get(&request.ticker)?;
+        if request.quantity == 0 {
+            Err(InvalidOrder("Quantity must be more than 0".to_string()))?;
+        }
+        if request.order_type == OrderType::Limit && request.price.is_none() {
+            Err(InvalidOrder("Limit order requires a price".to_string()))?;
+        }
+
+        tokio::time::sleep(self.config.latency).await;
+        self.execute(instrument, request)
+    }
+
+    async fn cancel_order(&self, ticker: &str, order_id: &str) -> Result<(), Error> {
+        self.instruments.get(ticker)?;
+        let mut state = self.state.lock().unwrap();
+
+        let order = &mut state
+            .orders
+            .get_mut(order_id)
+            .ok_or_else(|| OrderNotFound(order_id.to_string()))?
+            .update;
+        order.status = OrderStatus::Canceled;
+        order.timestamp = now_timestamp();
+        order.received = now_timestamp_ns();
+
+        let update = order.clone();
+        state.close(order_id);
+        state.events.push(Event::OrderUpdate(update));
+        Ok(())
+    }
+
+    async fn query_order(&self, ticker: &str, order_id: &str) -> Result<OrderUpdate, Error> {
+        self.instruments.get(ticker)?;
+        let state = self.state.lock().unwrap();
+        let order = match state.orders.get(order_id) {
+            Some(order) => &order.update,
+            None => state
+                .closed
+                .iter()
+                .rev()
+                .find(|o| o.order_id == order_id)
+                .ok_or_else(|| OrderNotFound(order_id.to_string()))?,
+        };
+        Ok(order.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::connector::errors::ExchangeError;
+    use crate::level2::LevelUpdated;
+    use std::sync::Arc;
+
+    fn level(side: Side, price: Price, quantity: Quantity) -> Event {
+        Event::LevelUpdate(LevelUpdated {
+            exchange: Exchange::Binance,
+            ticker: Arc::new("btc/usdt".to_string()),
+            side,
+            price,
+            quantity,
+            timestamp: 0,
+            received: 0,
+            sequence: 0,
+            prev_sequence: 0,
+        })
+    }
+
+    fn trade(price: Price, quantity: Quantity) -> Event {
+        Event::Trade(TradeEvent {
+            exchange: Exchange::Binance,
+            ticker: Arc::new("btc/usdt".to_string()),
+            trade_id: 1,
+            price,
+            quantity,
+            timestamp: 0,
+            received: 0,
+            market_maker: Side::Buy,
+        })
+    }
+
+    fn venue() -> SimulatedExchange {
+        let config = SimulationConfig {
+            latency: Duration::from_millis(0),
+            maker_fee: 0.0,
+            taker_fee: 0.01,
+            book_depth: 10,
+        };
+        let sim = SimulatedExchange::new(Exchange::Binance, &[("btc/usdt", 1, 1)], config);
+        for ev in [
+            level(Side::Sell, 101, 2),
+            level(Side::Sell, 102, 3),
+            level(Side::Buy, 99, 5),
+        ] {
+            sim.on_event(&ev);
+        }
+        sim
+    }
+
+    fn fills(events: &[Event]) -> Vec<(Price, Quantity, bool)> {
+        events
+            .iter()
+            .filter_map(|e| match e {
+                Event::Fill(f) => Some((f.price, f.quantity, f.is_maker)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_market_order_walks_book() {
+        let sim = venue();
+        let update = sim
+            .place_order(&OrderRequest::market("btc/usdt", Side::Buy, 4))
+            .await
+            .unwrap();
+
+        assert_eq!(update.status, OrderStatus::Filled);
+        let events = sim.take_events();
+        assert_eq!(fills(&events), vec![(101, 2, false), (102, 2, false)]);
+        match &events[0] {
+            Event::Fill(f) => assert_eq!((f.fee, f.fee_asset.as_str()), (2.02, "USDT")),
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_market_order_leftover_expires() {
+        let sim = venue();
+        let update = sim
+            .place_order(&OrderRequest::market("btc/usdt", Side::Sell, 7))
+            .await
+            .unwrap();
+        assert_eq!((update.status, update.filled_quantity), (OrderStatus::Expired, 5));
+    }
+
+    #[tokio::test]
+    async fn test_limit_order_rests_and_fills_on_trades() {
+        let sim = venue();
+        let update = sim
+            .place_order(&OrderRequest::limit("btc/usdt", Side::Buy, 100, 3))
+            .await
+            .unwrap();
+        assert_eq!(update.status, OrderStatus::New);
+        sim.take_events();
+
+        // At our price: queue ahead of us
+        sim.on_event(&trade(100, 10));
+        assert!(sim.take_events().is_empty());
+
+        sim.on_event(&trade(99, 2));
+        sim.on_event(&trade(98, 5));
+        assert_eq!(fills(&sim.take_events()), vec![(100, 2, true), (100, 1, true)]);
+
+        let order = sim.query_order("btc/usdt", &update.order_id).await.unwrap();
+        assert_eq!((order.status, order.filled_quantity), (OrderStatus::Filled, 3));
+    }
+
+    #[tokio::test]
+    async fn test_trades_fill_by_price_then_time() {
+        let sim = venue();
+        let first = sim.place_order(&OrderRequest::limit("btc/usdt", Side::Buy, 97, 2)).await.unwrap();
+        let second = sim.place_order(&OrderRequest::limit("btc/usdt", Side::Buy, 97, 2)).await.unwrap();
+        let best = sim.place_order(&OrderRequest::limit("btc/usdt", Side::Buy, 98, 2)).await.unwrap();
+        sim.take_events();
+
+        sim.on_event(&trade(96, 3));
+        let filled: Vec<(String, Quantity)> = sim
+            .take_events()
+            .into_iter()
+            .filter_map(|e| match e {
+                Event::Fill(f) => Some((f.order_id, f.quantity)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(filled, vec![(best.order_id.clone(), 2), (first.order_id.clone(), 1)]);
+
+        // Filled orders leave the book but can still be queried
+        {
+            let state = sim.state.lock().unwrap();
+            assert_eq!(state.orders.len(), 2);
+            assert_eq!(state.resting["btc/usdt"].bids.len(), 2);
+            assert!(state.orders.contains_key(&second.order_id));
+        }
+        let order = sim.query_order("btc/usdt", &best.order_id).await.unwrap();
+        assert_eq!(order.status, OrderStatus::Filled);
+    }
+
+    #[tokio::test]
+    async fn test_marketable_limit_takes_then_rests() {
+        let sim = venue();
+        let update = sim
+            .place_order(&OrderRequest::limit("btc/usdt", Side::Buy, 101, 5))
+            .await
+            .unwrap();
+        assert_eq!((update.status, update.filled_quantity), (OrderStatus::PartiallyFilled, 2));
+    }
+
+    #[tokio::test]
+    async fn test_cancel() {
+        let sim = venue();
+        let update = sim
+            .place_order(&OrderRequest::limit("btc/usdt", Side::Sell, 110, 1))
+            .await
+            .unwrap();
+
+        sim.cancel_order("btc/usdt", &update.order_id).await.unwrap();
+        let res = sim.cancel_order("btc/usdt", &update.order_id).await;
+        assert!(matches!(res, Err(Error::ExchangeError(ExchangeError::OrderNotFound(_)))));
+
+        sim.on_event(&trade(120, 1));
+        let order = sim.query_order("btc/usdt", &update.order_id).await.unwrap();
+        assert_eq!((order.status, order.filled_quantity), (OrderStatus::Canceled, 0));
+    }
+
+    #[tokio::test]
+    async fn test_latency() {
+        let config = SimulationConfig {
+            latency: Duration::from_millis(30),
+            ..SimulationConfig::default()
+        };
+        let sim = SimulatedExchange::new(Exchange::Binance, &[("btc/usdt", 1, 1)], config);
+
+        let start = std::time::Instant::now();
+        let _ = sim.place_order(&OrderRequest::limit("btc/usdt", Side::Buy, 1, 1)).await;
+        assert!(start.elapsed() >= Duration::from_millis(30));
+    }
+}
@@ -2,6 +2,8 @@
 pub enum Exchange {
     Binance = 0,
     Kraken = 1,
+    Deribit = 2,
+    Hyperliquid = 3,
 }
 
 impl Exchange {
@@ -9,6 +11,8 @@ impl Exchange {
         match self {
             Exchange::Binance => "binance",
             Exchange::Kraken => "kraken",
+            Exchange::Deribit => "deribit",
+            Exchange::Hyperliquid => "hyperliquid",
         }
     }
 }
@@ -0,0 +1,154 @@
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum OptionKind {
+    Call,
+    Put,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum InstrumentKind {
+    /// BTC or BTC/USDT
+    Spot,
+    /// BTC-PERPETUAL or BTC_USDC-PERPETUAL
+    Perpetual,
+    /// BTC-27DEC24
+    Future { expiry: String },
+    /// BTC-27DEC24-60000-C
+    Option {
+        expiry: String,
+        strike: f64,
+        kind: OptionKind,
+    },
+}
+
+fn is_valid_asset(s: &str) -> bool {
+    !s.is_empty() && s.len() <= 10 && s.chars().all(|c| c.is_ascii_alphabetic())
+}
+
+fn is_valid_base(s: &str) -> bool {
+    // Deribit linear instruments look like BTC_USDC
+    match s.split_once('_') {
+        Some((base, quote)) => is_valid_asset(base) && is_valid_asset(quote),
+        None => is_valid_asset(s),
+    }
+}
+
+fn is_valid_expiry(s: &str) -> bool {
+    // 27DEC24, 5JAN25
+    let digits = s.chars().take_while(|c| c.is_ascii_digit()).count();
+    if !s.is_ascii() || !(1..=2).contains(&digits) || s.len() != digits + 5 {
+        return false;
+    }
+    let (month, year) = s[digits..].split_at(3);
+    month.chars().all(|c| c.is_ascii_alphabetic()) && year.chars().all(|c| c.is_ascii_digit())
+}
+
+fn parse_strike(s: &str) -> Option<f64> {
+    // Deribit writes fractional strikes with 'd' instead of a dot: 0d625 (already uppercased here)
+    if s.is_empty() || !s.chars().all(|c| c.is_ascii_digit() || c == 'D') {
+        return None;
+    }
+    s.replacen('D', ".", 1).parse::<f64>().ok()
+}
+
+impl InstrumentKind {
+    pub fn from_ticker(ticker: &str) -> Option<Self> {
+        let t = ticker.to_uppercase();
+
+        if let Some((base, quote)) = t.split_once('/') {
+            return (is_valid_asset(base) && is_valid_asset(quote)).then_some(InstrumentKind::Spot);
+        }
+
+        let parts: Vec<&str> = t.split('-').collect();
+        match parts.as_slice() {
+            [asset] if is_valid_asset(asset) => Some(InstrumentKind::Spot),
+            [base, "PERPETUAL"] if is_valid_base(base) => Some(InstrumentKind::Perpetual),
+            [base, expiry] if is_valid_base(base) && is_valid_expiry(expiry) => {
+                Some(InstrumentKind::Future {
+                    expiry: expiry.to_string(),
+                })
+            }
+            [base, expiry, strike, kind] if is_valid_base(base) && is_valid_expiry(expiry) => {
+                let strike = parse_strike(strike)?;
+                let kind = match *kind {
+                    "C" => OptionKind::Call,
+                    "P" => OptionKind::Put,
+                    _ => return None,
+                };
+                Some(InstrumentKind::Option {
+                    expiry: expiry.to_string(),
+                    strike,
+                    kind,
+                })
+            }
+            _ => None,
+        }
+    }
+
+    pub fn is_option(&self) -> bool {
+        matches!(self, InstrumentKind::Option { .. })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_spot() {
+        assert_eq!(InstrumentKind::from_ticker("btc/usdt"), Some(InstrumentKind::Spot));
+        assert_eq!(InstrumentKind::from_ticker("AAPL"), Some(InstrumentKind::Spot));
+    }
+
+    #[test]
+    fn test_perpetual() {
+        assert_eq!(
+            InstrumentKind::from_ticker("BTC-PERPETUAL"),
+            Some(InstrumentKind::Perpetual)
+        );
+        assert_eq!(
+            InstrumentKind::from_ticker("btc_usdc-perpetual"),
+            Some(InstrumentKind::Perpetual)
+        );
+    }
+
+    #[test]
+    fn test_future() {
+        assert_eq!(
+            InstrumentKind::from_ticker("ETH-27DEC24"),
+            Some(InstrumentKind::Future {
+                expiry: "27DEC24".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn test_option() {
+        assert_eq!(
+            InstrumentKind::from_ticker("BTC-27DEC24-60000-C"),
+            Some(InstrumentKind::Option {
+                expiry: "27DEC24".to_string(),
+                strike: 60000.0,
+                kind: OptionKind::Call,
+            })
+        );
+        assert_eq!(
+            InstrumentKind::from_ticker("XRP_USDC-5JAN25-0d625-P"),
+            Some(InstrumentKind::Option {
+                expiry: "5JAN25".to_string(),
+                strike: 0.625,
+                kind: OptionKind::Put,
+            })
+        );
+    }
+
+    #[test]
+    fn test_invalid() {
+        assert_eq!(InstrumentKind::from_ticker(""), None);
+        assert_eq!(InstrumentKind::from_ticker("btc/"), None);
+        assert_eq!(InstrumentKind::from_ticker("BTC-27DEC24-60000-X"), None);
+        assert_eq!(InstrumentKind::from_ticker("BTC-DEC-60000-C"), None);
+        assert_eq!(InstrumentKind::from_ticker("BTC-27DEC24-abc-C"), None);
+        // Non-ASCII expiry whose byte length matches must not split inside a char
+        assert_eq!(InstrumentKind::from_ticker("btc-1ééa"), None);
+    }
+}
@@ -18,6 +18,7 @@ pub fn color_for_level(level: Level) -> &'static str {
     }
 }
 
+#[derive(Clone)]
 pub struct Logger {
     pub name: &'static str,
     level: Level, // минимальный уровень логирования
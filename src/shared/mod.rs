@@ -4,6 +4,8 @@ pub mod utils;
 pub mod logger;
 pub mod errors;
 mod exchange;
+mod instrument;
 
 pub use types::{Period, Price, Quantity, Side, TimestampMS, Profit, TimestampNS};
-pub use exchange::Exchange;
\ No newline at end of file
+pub use exchange::Exchange;
+pub use instrument::InstrumentKind;
\ No newline at end of file
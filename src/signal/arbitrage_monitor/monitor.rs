@@ -21,6 +21,10 @@ impl<'a> ArbitrageMonitor<'a> {
     }
 
     pub fn execute(&self) -> Option<ArbitrageSignal> {
+        // Crossed, gapped or stale books produce phantom spreads
+        if !self.book_a.is_valid() || !self.book_b.is_valid() {
+            return None;
+        }
         if self.book_a.bids().is_empty()
             || self.book_a.asks().is_empty()
             || self.book_b.bids().is_empty()
@@ -104,6 +108,8 @@ mod tests {
             quantity: qty,
             timestamp: 0,
             received: now_timestamp_ns(),
+            sequence: 0,
+            prev_sequence: 0,
         }
     }
 
@@ -210,7 +216,8 @@ mod tests {
 
         a.update(&ev(Exchange::Binance, "BTC/USDT", Side::Buy, 99, 1))
             .unwrap();
-        b.update(&ev(Exchange::Kraken, "BTC/USDT", Side::Sell, 103, 1))
+        // Above B's best bid, a crossed book is skipped
+        b.update(&ev(Exchange::Kraken, "BTC/USDT", Side::Sell, 104, 1))
             .unwrap();
 
         // An asks: 100, 101, 102 → best = 100
@@ -258,4 +265,16 @@ mod tests {
         let sig = mon.execute();
         assert!(sig.is_none());
     }
+
+    #[test]
+    fn test_skips_crossed_book() {
+        let mut a = OrderBook::new(Exchange::Binance, "BTC/USDT", 10);
+        let mut b = OrderBook::new(Exchange::Kraken, "BTC/USDT", 10);
+        a.update(&ev(Exchange::Binance, "BTC/USDT", Side::Buy, 99, 1)).unwrap();
+        a.update(&ev(Exchange::Binance, "BTC/USDT", Side::Sell, 100, 1)).unwrap();
+        b.update(&ev(Exchange::Kraken, "BTC/USDT", Side::Buy, 110, 1)).unwrap();
+        b.update(&ev(Exchange::Kraken, "BTC/USDT", Side::Sell, 105, 1)).unwrap();
+
+        assert!(ArbitrageMonitor::new(&a, &b, 0.0).execute().is_none());
+    }
 }
@@ -5,6 +5,8 @@ use crate::shared::{Exchange, Price, Quantity, Side, TimestampMS, TimestampNS};
 pub struct TradeEvent {
     pub exchange: Exchange,
     pub ticker: Arc<String>,
+    /// Exchange trade id: Binance aggregate id, Kraken trade id, Deribit trade_seq, Hyperliquid tid
+    pub trade_id: u64,
     pub price: Price,
     pub quantity: Quantity,
     pub timestamp: TimestampMS,
@@ -3,13 +3,15 @@ use serde::Serialize;
 
 use crate::shared::logger::Logger;
 use crate::shared::utils::buffer_service::Callback;
-use crate::shared::{Price, Quantity, TimestampMS};
+use crate::shared::{Exchange, Price, Quantity, TimestampMS};
+use std::collections::HashSet;
 use crate::trade::{TradeError, TradeEvent};
 
 #[derive(Row, Serialize)]
 pub struct TradeEventRow {
     exchange: u8,
     ticker: String,
+    trade_id: u64,
     price: Price,
     quantity: Quantity,
     timestamp: TimestampMS,
@@ -22,6 +24,7 @@ impl TradeEventRow {
         Self {
             exchange: ev.exchange.clone() as u8,
             ticker: ev.ticker.as_ref().clone(),
+            trade_id: ev.trade_id,
             price: ev.price,
             quantity: ev.quantity,
             timestamp: ev.timestamp,
@@ -54,6 +57,29 @@ impl<'a> TradeEventRepo<'a> {
         insert.end().await?;
         Ok(())
     }
+
+    /// Ids of the stored trades of one instrument within `[from, to]`
+    pub async fn trade_ids(
+        &self,
+        exchange: &Exchange,
+        ticker: &str,
+        from: TimestampMS,
+        to: TimestampMS,
+    ) -> Result<HashSet<u64>, TradeError> {
+        let ids: Vec<u64> = self
+            .client
+            .query(
+                "SELECT trade_id FROM trade_events \
+                 WHERE exchange = ? AND ticker = ? AND timestamp >= ? AND timestamp <= ?",
+            )
+            .bind(exchange.clone() as u8)
+            .bind(ticker)
+            .bind(from)
+            .bind(to)
+            .fetch_all()
+            .await?;
+        Ok(ids.into_iter().collect())
+    }
 }
 
 impl<'a> Callback<TradeEvent, TradeError> for TradeEventRepo<'a> {
@@ -74,6 +100,7 @@ pub async fn create_trade_event_table(
         CREATE TABLE IF NOT EXISTS {}.trade_events (
             exchange UInt8,
             ticker String,
+            trade_id UInt64,
             price UInt64,
             quantity UInt64,
             timestamp UInt64,
@@ -84,7 +111,10 @@ pub async fn create_trade_event_table(
     "#,
         db_name
     );
-
     client.query(&query).execute().await?;
+
+    // Tables created before trade ids were stored
+    let migrate = format!("ALTER TABLE {}.trade_events ADD COLUMN IF NOT EXISTS trade_id UInt64 AFTER ticker", db_name);
+    client.query(&migrate).execute().await?;
     Ok(())
 }
@@ -62,6 +62,7 @@ mod tests {
         TradeEvent {
             exchange,
             ticker: Arc::new(ticker.to_string()),
+            trade_id: ts,
             timestamp: ts,
             price: 100,
             quantity: 10,